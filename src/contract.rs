@@ -3,277 +3,1037 @@ use cosmwasm_std::entry_point;
 
 use crate::error::ContractError;
 use crate::helpers::{
-    aggregate_coins, validate_fees_are_paid,
-    validate_no_additional_funds_sent_with_proposal_creation,
+    aggregate_coins, aggregate_gift, available_fees, claimed_amount, coins_to_string,
+    compute_gift_fee, compute_proposal_expiration, contributions_to_string, credit_treasury,
+    escrow_gift, fee_share_msgs, fee_shares_to_string, gift_return_share, gift_to_string,
+    gift_transfer_msgs, gift_transfers_msgs, instantiate_certificate_collection, is_zero_duration,
+    mint_certificates, native_coins, native_gift, refund_proposer, release_gift,
+    release_resolved_gift, released_gift, resolve_stage, route_gift, routes_to_string, split_fees,
+    stop_vesting, subtract_gift, unpicked_gift_options, validate_counter_gift, validate_deferral,
+    validate_exact_funds_sent, validate_fee_shares, validate_fees_are_paid,
+    validate_gift_disposition, validate_gift_fee, validate_gift_option, validate_gift_options,
+    validate_gift_policy, validate_no_additional_funds_sent_with_proposal_creation,
+    validate_proposal_durations, validate_receive_as, validate_reveal_at, validate_stages,
+    validate_staking_config, validate_vesting_schedule, vested_amount, Refund,
+    BASIS_POINTS_DENOMINATOR,
 };
 use crate::msg::{
-    ExecuteMsg, InstantiateMsg, MigrateMsg, ProposalBy, ProposalsResponse, QueryMsg, Status,
+    CertificatesResponse, ExecuteMsg, GiftContribution, GiftContributorsResponse, GiftFeeResponse,
+    InstantiateMsg, MigrateMsg, PoolExecuteMsg, ProposalBy, ProposalRevisionsResponse,
+    ProposalsResponse, QueryMsg, ReceiveMsg, StageResponse, Status, TreasuryFees, TreasuryResponse,
+    VestingAmount, VestingResponse,
 };
 use crate::proposal::state::{
-    Config, Proposal, ProposalStatus, CANCELED_COUNTER, CONFIG, DEFAULT_LIMIT, FAILED_COUNTER,
-    MAX_ITEMS_LIMIT, PROPOSALS, PROPOSAL_COUNTER, SUCCESSFUL_COUNTER,
+    pair_key, Config, Dissolution, Gift, GiftDisposition, GiftStaking, PendingSwap, Proposal,
+    ProposalRevision, ProposalStatus, ReceiveAs, Vesting, VestingSchedule, ACCEPTED_STAGES,
+    CANCELED_COUNTER, CERTIFICATES, CONFIG, COUNTERED_COUNTER, COUNTER_PROPOSALS, DEFAULT_LIMIT,
+    DEFERRED_COUNTER, DISSOLVED_COUNTER, EXPIRED_COUNTER, FAILED_COUNTER, GIFT_CONTRIBUTIONS,
+    MAX_ITEMS_LIMIT, PENDING_SWAP, PROPOSALS, PROPOSAL_COUNTER, PROPOSAL_REVISIONS, STAKING_POOLS,
+    SUCCESSFUL_COUNTER, TREASURY, UNBONDING_BATCHES, WITHDRAWN_FEES,
 };
+use crate::proposal::{lifecycle, migration, staking};
 use crate::validate_contract;
 use cosmwasm_std::{
-    to_json_binary, Binary, Deps, DepsMut, Env, MessageInfo, Order, Response, StdError,
+    from_json, to_json_binary, Binary, Deps, DepsMut, Env, MessageInfo, Order, Response, StdError,
+};
+use cosmwasm_std::{
+    Addr, Attribute, BankMsg, Coin, CosmosMsg, Decimal256, Reply, StdResult, SubMsg, Uint128,
+    WasmMsg,
 };
-use cosmwasm_std::{BankMsg, Coin, CosmosMsg, StdResult};
 use cw2::set_contract_version;
-use cw_storage_plus::IndexPrefix;
+use cw20::Cw20CoinVerified;
+use cw_storage_plus::{Bound, IndexPrefix};
+use cw_utils::{Expiration, PaymentError};
 
 // version info for migration info
 const CONTRACT_NAME: &str = "proposal-manager";
 const CONTRACT_VERSION: &str = env!("CARGO_PKG_VERSION");
+/// The first version whose state layout can be migrated from.
+const MIN_MIGRATABLE_VERSION: &str = "0.1.0";
+/// The reply id of the last swap of an accepted proposal's gift
+const SWAP_REPLY_ID: u64 = 1;
 
 #[cfg_attr(not(feature = "library"), entry_point)]
 pub fn instantiate(
     deps: DepsMut,
-    _env: Env,
+    env: Env,
     info: MessageInfo,
     msg: InstantiateMsg,
-) -> Result<Response, StdError> {
+) -> Result<Response, ContractError> {
     set_contract_version(deps.storage, CONTRACT_NAME, CONTRACT_VERSION)?;
-    let config: Config = Config {
-        successful_proposal_fee: msg.successful_proposal_fee,
+    let mut config: Config = Config {
+        successful_proposal_fees: msg.successful_proposal_fees,
+        default_proposal_duration: msg.default_proposal_duration,
+        max_proposal_duration: msg.max_proposal_duration,
+        finalization_delay: msg
+            .finalization_delay
+            .filter(|delay| !is_zero_duration(delay)),
+        stages: msg.stages,
+        certificate_collection: None,
+        gift_policy: msg.gift_policy.unwrap_or_default(),
+        staking: msg.staking,
+        swap_pool: msg
+            .swap_pool
+            .map(|swap_pool| deps.api.addr_validate(&swap_pool))
+            .transpose()?,
+        gift_fee_bps: msg.gift_fee_bps.filter(|bps| !bps.is_zero()),
+        fee_shares: msg.fee_shares,
     };
+    validate_proposal_durations(&config, &env.block)?;
+    validate_stages(&config.stages)?;
+    validate_gift_policy(deps.as_ref(), &config.gift_policy)?;
+    if let Some(gift_fee_bps) = config.gift_fee_bps {
+        validate_gift_fee(gift_fee_bps)?;
+    }
+    validate_fee_shares(deps.as_ref(), &config.fee_shares)?;
+    if let Some(staking) = &config.staking {
+        validate_staking_config(deps.as_ref(), staking)?;
+    }
+
+    let mut messages = vec![];
+    if let Some(code_id) = msg.certificate_code_id {
+        let (msg, collection) = instantiate_certificate_collection(deps.as_ref(), &env, code_id)?;
+        messages.push(msg);
+        config.certificate_collection = Some(collection);
+    }
+
     CONFIG.save(deps.storage, &config)?;
     // Initialize counter
     PROPOSAL_COUNTER.save(deps.storage, &0)?;
     SUCCESSFUL_COUNTER.save(deps.storage, &0)?;
     CANCELED_COUNTER.save(deps.storage, &0)?;
     FAILED_COUNTER.save(deps.storage, &0)?;
+    EXPIRED_COUNTER.save(deps.storage, &0)?;
+    COUNTERED_COUNTER.save(deps.storage, &0)?;
+    DEFERRED_COUNTER.save(deps.storage, &0)?;
+    DISSOLVED_COUNTER.save(deps.storage, &0)?;
 
     let owner = deps
         .api
         .addr_validate(&msg.owner.unwrap_or(info.sender.into_string()))?;
     cw_ownable::initialize_owner(deps.storage, deps.api, Some(owner.as_str()))?;
 
-    Ok(Response::default().add_attributes(vec![
-        ("action", "instantiate".to_string()),
-        ("owner", owner.to_string()),
-        (
-            "successful_proposal_fee",
-            config.successful_proposal_fee.to_string(),
-        ),
-    ]))
+    Ok(Response::default()
+        .add_messages(messages)
+        .add_attributes(vec![
+            ("action", "instantiate".to_string()),
+            ("owner", owner.to_string()),
+            (
+                "successful_proposal_fees",
+                coins_to_string(&config.successful_proposal_fees),
+            ),
+            (
+                "certificate_collection",
+                config
+                    .certificate_collection
+                    .map(|collection| collection.to_string())
+                    .unwrap_or_default(),
+            ),
+        ]))
 }
 
-#[allow(unused_must_use)]
 #[cfg_attr(not(feature = "library"), entry_point)]
 pub fn execute(
-    deps: DepsMut,
+    mut deps: DepsMut,
     env: Env,
     info: MessageInfo,
     msg: ExecuteMsg,
 ) -> Result<Response, ContractError> {
+    if migration::is_migrating(deps.storage)
+        && !matches!(
+            msg,
+            ExecuteMsg::MigrateProposals { .. } | ExecuteMsg::UpdateOwnership(_)
+        )
+    {
+        return Err(ContractError::MigrationInProgress);
+    }
+
     match msg {
         ExecuteMsg::CreateProposal {
             title,
             speech,
             receiver,
             gift,
+            expires_at,
+            stage,
+            reveal_at,
+            vesting,
+            stake_gift,
+            counter_gift,
+            gift_options,
         } => {
-            let config = CONFIG.load(deps.storage)?;
-            // proposer is not the receiver
-            if info.sender == deps.api.addr_validate(&receiver)? {
-                return Err(ContractError::InvalidReceiver);
+            let receiver = deps.api.addr_validate(&receiver)?;
+            create_proposal(
+                deps,
+                env,
+                info,
+                NewProposal {
+                    receiver,
+                    title,
+                    speech,
+                    gift: native_gift(gift),
+                    expires_at,
+                    parent_id: None,
+                    stage,
+                    reveal_at,
+                    vesting,
+                    stake_gift,
+                    counter_gift,
+                    gift_options: gift_options.into_iter().map(native_gift).collect(),
+                },
+            )
+        }
+        ExecuteMsg::UpdateProposal {
+            id,
+            title,
+            speech,
+            add_gift,
+            gift_option,
+        } => {
+            let proposal = PROPOSALS.load(deps.storage, id)?;
+            if proposal.proposer != info.sender {
+                return Err(ContractError::Unauthorized);
             }
-            let gift = aggregate_coins(gift)?;
-            // check if the proposal and gift fees were paid
-            let total_fees =
-                validate_fees_are_paid(&config.successful_proposal_fee, gift.clone(), &info)?;
 
-            // make sure the user doesn't accidentally send more tokens than needed
+            // the fee was already paid when creating the proposal, only the added gift is due
+            let add_gift = aggregate_coins(add_gift)?;
+            let gift = aggregate_coins([native_coins(&proposal.gift), add_gift.clone()].concat())?;
+            let no_fee = Coin::new(0u128, proposal.fee.denom);
+            let (_, total_fees) = validate_fees_are_paid(
+                deps.as_ref(),
+                &CONFIG.load(deps.storage)?.gift_policy,
+                &[no_fee],
+                add_gift.clone(),
+                &gift,
+                &info,
+            )?;
             validate_no_additional_funds_sent_with_proposal_creation(&info, total_fees)?;
+            escrow_gift(deps.storage, &add_gift)?;
 
-            let proposal_id = PROPOSAL_COUNTER.load(deps.storage)?;
-            let proposal = Proposal {
-                id: proposal_id,
-                proposer: info.sender.clone(),
-                receiver: deps.api.addr_validate(&receiver)?,
-                gift,
-                fee: config.successful_proposal_fee.clone(),
+            update_proposal(
+                deps,
+                env,
+                &info.sender,
+                id,
                 title,
                 speech,
-                reply: None,
-                status: ProposalStatus::Pending,
-                created_at: env.block.height,
-                replied_at: None,
+                native_gift(add_gift),
+                gift_option,
+            )
+        }
+        ExecuteMsg::ContributeGift { id } => contribute_gift(deps, env, info, id),
+        ExecuteMsg::Receive(cw20_msg) => {
+            cw_utils::nonpayable(&info)?;
+            // anyone can call the hook, only trust the sender forwarded by allowed tokens
+            let gift_policy = CONFIG.load(deps.storage)?.gift_policy;
+            if !gift_policy.allowed_cw20_tokens.contains(&info.sender) {
+                return Err(ContractError::GiftTokenNotAllowed {
+                    address: info.sender.to_string(),
+                });
+            }
+            let gift = vec![Gift::Cw20(Cw20CoinVerified {
+                address: info.sender,
+                amount: cw20_msg.amount,
+            })];
+            let sender = deps.api.addr_validate(&cw20_msg.sender)?;
+
+            receive_gift(deps, env, sender, gift, from_json(&cw20_msg.msg)?)
+        }
+        ExecuteMsg::ReceiveNft(cw721_msg) => {
+            cw_utils::nonpayable(&info)?;
+            // anyone can call the hook, only trust the sender forwarded by allowed collections
+            let gift_policy = CONFIG.load(deps.storage)?.gift_policy;
+            if !gift_policy.allowed_cw721_collections.contains(&info.sender) {
+                return Err(ContractError::GiftTokenNotAllowed {
+                    address: info.sender.to_string(),
+                });
+            }
+            let gift = vec![Gift::Cw721 {
+                contract: info.sender,
+                token_id: cw721_msg.token_id,
+            }];
+            let sender = deps.api.addr_validate(&cw721_msg.sender)?;
+
+            receive_gift(deps, env, sender, gift, from_json(&cw721_msg.msg)?)
+        }
+        ExecuteMsg::ClaimUnbonded { id } => {
+            let mut proposal = PROPOSALS.load(deps.storage, id)?;
+            let Some(staking) = proposal.staking.as_mut() else {
+                return Err(ContractError::NoUnbondedGift { id });
             };
+            let Some(unbonding) = staking
+                .unbonding
+                .as_mut()
+                .filter(|unbonding| !unbonding.claimed)
+            else {
+                return Err(ContractError::NoUnbondedGift { id });
+            };
+            let Some(claimable_at) =
+                UNBONDING_BATCHES.may_load(deps.storage, (&staking.validator, unbonding.batch))?
+            else {
+                return Err(ContractError::UnbondingNotStarted { id });
+            };
+            if env.block.time < claimable_at {
+                return Err(ContractError::UnbondingNotOver { id, claimable_at });
+            }
 
-            PROPOSALS.save(deps.storage, proposal_id, &proposal)?;
-            PROPOSAL_COUNTER.save(deps.storage, &(proposal_id + 1))?;
+            unbonding.claimed = true;
+            let fee = unbonding.fee.clone();
+            let mut messages = unbonding
+                .payouts
+                .iter()
+                .map(|payout| {
+                    CosmosMsg::Bank(BankMsg::Send {
+                        to_address: payout.recipient.to_string(),
+                        amount: vec![payout.amount.clone()],
+                    })
+                })
+                .collect::<Vec<_>>();
+            let payouts = unbonding
+                .payouts
+                .iter()
+                .map(|payout| format!("{}:{}", payout.recipient, payout.amount))
+                .collect::<Vec<_>>()
+                .join(";");
+            PROPOSALS.save(deps.storage, id, &proposal)?;
+            // the whole staked gift was held in escrow until now
+            if let Some(staking) = &proposal.staking {
+                release_gift(deps.storage, std::slice::from_ref(&staking.amount))?;
+            }
 
-            Ok(Response::new().add_attributes(vec![
-                ("action", "create_proposal"),
-                ("proposal_id", proposal_id.to_string().as_str()),
-                ("proposer", info.sender.to_string().as_str()),
-                ("receiver", receiver.as_str()),
+            // the gift fee charged on the staked gift is paid along with it
+            let mut fee_attributes = vec![];
+            if let Some(fee) = &fee {
+                let config = CONFIG.load(deps.storage)?;
+                let (fee_messages, attributes) = settle_fees(
+                    deps.branch(),
+                    &config,
+                    &proposal,
+                    vec![],
+                    native_gift(vec![fee.clone()]),
+                )?;
+                messages.extend(fee_messages);
+                fee_attributes = attributes;
+            }
+
+            Ok(Response::new()
+                .add_messages(messages)
+                .add_attributes(vec![
+                    ("action", "claim_unbonded"),
+                    ("proposal_id", id.to_string().as_str()),
+                    ("payouts", payouts.as_str()),
+                    (
+                        "gift_fee",
+                        fee.map(|fee| fee.to_string()).unwrap_or_default().as_str(),
+                    ),
+                ])
+                .add_attributes(fee_attributes))
+        }
+        ExecuteMsg::UndelegateQueued { validator } => {
+            cw_utils::nonpayable(&info)?;
+            if !STAKING_POOLS.has(deps.storage, &validator) {
+                return Err(ContractError::NothingToUndelegate { validator });
+            }
+            let bonded_denom = deps.querier.query_bonded_denom()?;
+            let Some((messages, attributes)) =
+                staking::undelegate_queued(deps.branch(), &env, &validator, &bonded_denom)?
+            else {
+                return Err(ContractError::UndelegationNotDue { validator });
+            };
+
+            Ok(Response::new()
+                .add_messages(messages)
+                .add_attributes(vec![
+                    ("action", "undelegate_queued"),
+                    ("validator", validator.as_str()),
+                ])
+                .add_attributes(attributes))
+        }
+        ExecuteMsg::ClaimGift { id } => {
+            let mut proposal = PROPOSALS.load(deps.storage, id)?;
+            if proposal.receiver != info.sender {
+                return Err(ContractError::Unauthorized);
+            }
+            let Some(vesting) = proposal.vesting.as_mut() else {
+                return Err(ContractError::GiftNotVesting { id });
+            };
+            let Some(started_at) = vesting.started_at else {
+                return Err(ContractError::GiftNotVesting { id });
+            };
+            if vesting.stopped_at.is_some() {
+                return Err(ContractError::VestingStopped { id });
+            }
+
+            let mut claimable = vec![];
+            for part in &proposal.gift {
+                let (Some(denom), Some(total)) = (part.denom(), part.amount()) else {
+                    continue;
+                };
+                let vested = vested_amount(&vesting.schedule, started_at, total, env.block.time);
+                let claimed = claimed_amount(&vesting.claimed, &denom);
+                let amount = vested.checked_sub(claimed)?;
+                if !amount.is_zero() {
+                    claimable.push(part.with_amount(amount));
+                }
+            }
+            if claimable.is_empty() {
+                return Err(ContractError::NothingToClaim { id });
+            }
+
+            vesting.claimed =
+                aggregate_gift([vesting.claimed.clone(), claimable.clone()].concat())?;
+            PROPOSALS.save(deps.storage, id, &proposal)?;
+            release_gift(deps.storage, &native_coins(&claimable))?;
+
+            // the released gift is routed the way the receiver chose when accepting
+            let routes = route_gift(deps.storage, &proposal, &claimable)?;
+            let mut messages = vec![];
+            for (recipient, gift) in &routes {
+                messages.extend(gift_transfer_msgs(gift, recipient)?);
+            }
+
+            Ok(Response::new().add_messages(messages).add_attributes(vec![
+                ("action", "claim_gift"),
+                ("proposal_id", id.to_string().as_str()),
+                ("receiver", proposal.receiver.as_str()),
+                ("claimed", &gift_to_string(&claimable)),
+                ("gift_transfers", &routes_to_string(&routes)),
             ]))
         }
         ExecuteMsg::CancelProposal { id } => {
-            let proposal = PROPOSALS.load(deps.storage, id)?;
+            let mut proposal = PROPOSALS.load(deps.storage, id)?;
             if proposal.proposer != info.sender {
                 return Err(ContractError::Unauthorized);
             }
-            if proposal.status != ProposalStatus::Pending {
-                return Err(ContractError::CancelProposalInvalidStatus {
-                    current_status: proposal.status.to_string(),
-                });
-            }
+            lifecycle::resume_if_deferral_over(deps.storage, &mut proposal, &env.block)?;
+            lifecycle::transition(deps.storage, &mut proposal, ProposalStatus::Canceled)?;
 
-            let mut total_refund: Vec<Coin> = vec![];
-            total_refund.push(proposal.fee.clone());
-            let mut messages: Vec<CosmosMsg> = vec![];
-            messages.push(CosmosMsg::Bank(BankMsg::Send {
-                to_address: proposal.proposer.to_string(),
-                amount: vec![proposal.fee.clone()],
-            }));
+            let Refund {
+                mut messages,
+                mut transfers,
+                proposer: total_refund,
+                contributors,
+            } = refund_proposer(deps.storage, &proposal)?;
+            let proposer = proposal.proposer.clone();
+            let (staking_messages, staking_attributes) = staking::undelegate(
+                deps.branch(),
+                &env,
+                &mut proposal,
+                &mut transfers,
+                &proposer,
+            )?;
+            messages.extend(staking_messages);
+            messages.extend(gift_transfers_msgs(&transfers)?);
+            release_resolved_gift(deps.storage, &proposal, &proposal.gift, &[])?;
 
-            if !proposal.gift.is_empty() {
-                total_refund.extend(proposal.gift.clone());
-                messages.push(CosmosMsg::Bank(BankMsg::Send {
-                    to_address: proposal.proposer.to_string(),
-                    amount: proposal.gift,
-                }));
+            CANCELED_COUNTER.update(deps.storage, |count| -> StdResult<_> { Ok(count + 1) })?;
+            proposal.canceled_at = Some(env.block.height);
+            PROPOSALS.save(deps.storage, id, &proposal)?;
+
+            Ok(Response::new()
+                .add_messages(messages)
+                .add_attributes(vec![
+                    ("action", "cancel_proposal"),
+                    ("proposal_id", id.to_string().as_str()),
+                    ("proposer", proposal.proposer.as_str()),
+                    ("receiver", proposal.receiver.as_str()),
+                    ("total_refund_to_proposer", &gift_to_string(&total_refund)),
+                    (
+                        "refunded_contributors",
+                        &contributions_to_string(&contributors),
+                    ),
+                ])
+                .add_attributes(staking_attributes))
+        }
+        ExecuteMsg::Yes {
+            id,
+            reply,
+            gift_disposition,
+            receive_as,
+            gift_option,
+        } => answer_proposal(
+            deps,
+            env,
+            info,
+            id,
+            reply,
+            ProposalStatus::Yes,
+            gift_disposition,
+            receive_as,
+            gift_option,
+        ),
+        ExecuteMsg::No { id, reply } => answer_proposal(
+            deps,
+            env,
+            info,
+            id,
+            reply,
+            ProposalStatus::No,
+            None,
+            None,
+            None,
+        ),
+        ExecuteMsg::CounterPropose { id, speech, gift } => {
+            let mut proposal = PROPOSALS.load(deps.storage, id)?;
+            if proposal.receiver != info.sender {
+                return Err(ContractError::Unauthorized);
+            }
+            lifecycle::resume_if_deferral_over(deps.storage, &mut proposal, &env.block)?;
+            lifecycle::transition(deps.storage, &mut proposal, ProposalStatus::Countered)?;
+            if proposal.expires_at.is_expired(&env.block) {
+                return Err(ContractError::ProposalExpired { id });
+            }
+            if !proposal.is_revealed(&env.block) {
+                return Err(ContractError::ProposalNotRevealed { id });
             }
 
-            total_refund = aggregate_coins(total_refund)?;
+            let Refund {
+                mut messages,
+                mut transfers,
+                proposer: total_refund,
+                contributors,
+            } = refund_proposer(deps.storage, &proposal)?;
+            let proposer = proposal.proposer.clone();
+            let (staking_messages, staking_attributes) = staking::undelegate(
+                deps.branch(),
+                &env,
+                &mut proposal,
+                &mut transfers,
+                &proposer,
+            )?;
+            messages.extend(staking_messages);
+            messages.extend(gift_transfers_msgs(&transfers)?);
+            release_resolved_gift(deps.storage, &proposal, &proposal.gift, &[])?;
 
-            PROPOSALS.remove(deps.storage, id);
-            CANCELED_COUNTER.update(deps.storage, |count| -> StdResult<_> { Ok(count + 1) })?;
+            COUNTERED_COUNTER.update(deps.storage, |count| -> StdResult<_> { Ok(count + 1) })?;
+            proposal.replied_at = Some(env.block.height);
+            PROPOSALS.save(deps.storage, id, &proposal)?;
 
-            Ok(Response::new().add_messages(messages).add_attributes(vec![
-                ("action", "cancel_proposal"),
+            let counter_proposal = save_new_proposal(
+                deps.branch(),
+                &env,
+                &info,
+                NewProposal {
+                    receiver: proposal.proposer.clone(),
+                    title: proposal.title,
+                    speech,
+                    gift: native_gift(gift),
+                    expires_at: None,
+                    parent_id: Some(id),
+                    stage: proposal.stage,
+                    reveal_at: None,
+                    vesting: None,
+                    stake_gift: false,
+                    counter_gift: vec![],
+                    gift_options: vec![],
+                },
+            )?;
+            COUNTER_PROPOSALS.save(deps.storage, id, &counter_proposal.id)?;
+
+            Ok(Response::new()
+                .add_messages(messages)
+                .add_attributes(vec![
+                    ("action", "counter_propose"),
+                    ("proposal_id", id.to_string().as_str()),
+                    (
+                        "counter_proposal_id",
+                        counter_proposal.id.to_string().as_str(),
+                    ),
+                    ("proposer", counter_proposal.proposer.as_str()),
+                    ("receiver", counter_proposal.receiver.as_str()),
+                    (
+                        "expires_at",
+                        counter_proposal.expires_at.to_string().as_str(),
+                    ),
+                    ("total_refund_to_proposer", &gift_to_string(&total_refund)),
+                    (
+                        "refunded_contributors",
+                        &contributions_to_string(&contributors),
+                    ),
+                ])
+                .add_attributes(staking_attributes))
+        }
+        ExecuteMsg::Defer { id, until, reply } => {
+            let mut proposal = PROPOSALS.load(deps.storage, id)?;
+            if proposal.receiver != info.sender {
+                return Err(ContractError::Unauthorized);
+            }
+            lifecycle::resume_if_deferral_over(deps.storage, &mut proposal, &env.block)?;
+            lifecycle::transition(deps.storage, &mut proposal, ProposalStatus::Deferred)?;
+            if proposal.expires_at.is_expired(&env.block) {
+                return Err(ContractError::ProposalExpired { id });
+            }
+            if !proposal.is_revealed(&env.block) {
+                return Err(ContractError::ProposalNotRevealed { id });
+            }
+            validate_deferral(&until, &proposal.expires_at, &env.block)?;
+
+            proposal.deferred_until = Some(until);
+            proposal.reply = reply;
+            proposal.replied_at = Some(env.block.height);
+            PROPOSALS.save(deps.storage, id, &proposal)?;
+
+            Ok(Response::new().add_attributes(vec![
+                ("action", "defer"),
                 ("proposal_id", id.to_string().as_str()),
+                ("reply", proposal.reply.unwrap_or_default().as_str()),
                 ("proposer", proposal.proposer.as_str()),
                 ("receiver", proposal.receiver.as_str()),
-                (
-                    "total_refund_to_proposer",
-                    &total_refund
-                        .iter()
-                        .map(|coin| coin.to_string())
-                        .collect::<Vec<_>>()
-                        .join(","),
-                ),
+                ("deferred_until", until.to_string().as_str()),
             ]))
         }
-        ExecuteMsg::Yes { id, reply } => {
+        ExecuteMsg::RevokeAnswer { id } => {
             let mut proposal = PROPOSALS.load(deps.storage, id)?;
             if proposal.receiver != info.sender {
                 return Err(ContractError::Unauthorized);
             }
-
-            let mut messages: Vec<CosmosMsg> = vec![];
-            if let Some(owner) = cw_ownable::get_ownership(deps.storage)?.owner {
-                messages.push(CosmosMsg::Bank(BankMsg::Send {
-                    to_address: owner.to_string(),
-                    amount: vec![proposal.fee.clone()],
-                }));
-            } else {
-                messages.push(CosmosMsg::Bank(BankMsg::Send {
-                    to_address: proposal.proposer.to_string(),
-                    amount: vec![proposal.fee.clone()],
-                }));
+            let revoked_status = proposal.status.clone();
+            lifecycle::transition(deps.storage, &mut proposal, ProposalStatus::Pending)?;
+            // the swap of the gift can keep an acceptance from being finalized, e.g. when the
+            // pool's price falls below the minimum, so the receiver can still revoke it to
+            // answer again
+            if proposal
+                .finalizes_at
+                .is_none_or(|finalizes_at| finalizes_at.is_expired(&env.block))
+                && proposal.receive_as.is_none()
+            {
+                return Err(ContractError::CoolingOffWindowClosed { id });
             }
 
-            if !proposal.gift.is_empty() {
-                messages.push(CosmosMsg::Bank(BankMsg::Send {
+            proposal.reply = None;
+            proposal.replied_at = None;
+            proposal.finalizes_at = None;
+            proposal.gift_disposition = None;
+            proposal.receive_as = None;
+            proposal.gift_option = None;
+            PROPOSALS.save(deps.storage, id, &proposal)?;
+
+            // the counter-gift deposited when accepting goes back to the receiver
+            let mut counter_gift_refund = vec![];
+            if revoked_status == ProposalStatus::ProvisionalYes {
+                counter_gift_refund = proposal.counter_gift.clone();
+                release_gift(deps.storage, &counter_gift_refund)?;
+            }
+            let mut messages = vec![];
+            if !counter_gift_refund.is_empty() {
+                messages.push(BankMsg::Send {
                     to_address: proposal.receiver.to_string(),
-                    amount: proposal.gift.clone(),
-                }));
+                    amount: counter_gift_refund.clone(),
+                });
             }
 
-            SUCCESSFUL_COUNTER.update(deps.storage, |count| -> StdResult<_> { Ok(count + 1) })?;
-            proposal.status = ProposalStatus::Yes;
-            proposal.reply = reply.clone();
-            proposal.replied_at = Some(env.block.height);
+            Ok(Response::new().add_messages(messages).add_attributes(vec![
+                ("action", "revoke_answer"),
+                ("proposal_id", id.to_string().as_str()),
+                ("revoked_status", revoked_status.to_string().as_str()),
+                (
+                    "counter_gift_refunded",
+                    &coins_to_string(&counter_gift_refund),
+                ),
+            ]))
+        }
+        ExecuteMsg::Finalize { id } => {
+            let mut proposal = PROPOSALS.load(deps.storage, id)?;
+            let final_status = lifecycle::final_status(&proposal.status).ok_or(
+                ContractError::NoProvisionalAnswer {
+                    current_status: proposal.status.to_string(),
+                },
+            )?;
+            lifecycle::transition(deps.storage, &mut proposal, final_status.clone())?;
+            if !proposal
+                .finalizes_at
+                .is_none_or(|finalizes_at| finalizes_at.is_expired(&env.block))
+            {
+                return Err(ContractError::CoolingOffWindowOpen { id });
+            }
+            // the gift is paid unswapped if the owner disabled swapping since the acceptance
+            if CONFIG.load(deps.storage)?.swap_pool.is_none() {
+                proposal.receive_as = None;
+            }
+
+            match final_status {
+                ProposalStatus::Yes => {
+                    // the counter-gift held during the window is paid to the proposer
+                    release_gift(deps.storage, &proposal.counter_gift)?;
+                    settle_yes(deps, &env, proposal, "finalize_yes")
+                }
+                _ => settle_no(deps, &env, proposal, "finalize_no"),
+            }
+        }
+        ExecuteMsg::ExpireProposal { id } => {
+            let mut proposal = PROPOSALS.load(deps.storage, id)?;
+            lifecycle::resume_if_deferral_over(deps.storage, &mut proposal, &env.block)?;
+            lifecycle::transition(deps.storage, &mut proposal, ProposalStatus::Expired)?;
+            if !proposal.expires_at.is_expired(&env.block) {
+                return Err(ContractError::ProposalNotExpired { id });
+            }
+
+            let Refund {
+                mut messages,
+                mut transfers,
+                proposer: total_refund,
+                contributors,
+            } = refund_proposer(deps.storage, &proposal)?;
+            let proposer = proposal.proposer.clone();
+            let (staking_messages, staking_attributes) = staking::undelegate(
+                deps.branch(),
+                &env,
+                &mut proposal,
+                &mut transfers,
+                &proposer,
+            )?;
+            messages.extend(staking_messages);
+            messages.extend(gift_transfers_msgs(&transfers)?);
+            release_resolved_gift(deps.storage, &proposal, &proposal.gift, &[])?;
+
+            EXPIRED_COUNTER.update(deps.storage, |count| -> StdResult<_> { Ok(count + 1) })?;
             PROPOSALS.save(deps.storage, id, &proposal)?;
 
-            Ok(Response::new().add_messages(messages).add_attributes(vec![
-                ("action", "say_yes"),
+            Ok(Response::new()
+                .add_messages(messages)
+                .add_attributes(vec![
+                    ("action", "expire_proposal"),
+                    ("proposal_id", id.to_string().as_str()),
+                    ("proposer", proposal.proposer.as_str()),
+                    ("receiver", proposal.receiver.as_str()),
+                    ("expires_at", proposal.expires_at.to_string().as_str()),
+                    ("total_refund_to_proposer", &gift_to_string(&total_refund)),
+                    (
+                        "refunded_contributors",
+                        &contributions_to_string(&contributors),
+                    ),
+                ])
+                .add_attributes(staking_attributes))
+        }
+        ExecuteMsg::RequestDissolution {
+            id,
+            gift_return_bps,
+        } => {
+            let mut proposal = PROPOSALS.load(deps.storage, id)?;
+            if proposal.proposer != info.sender && proposal.receiver != info.sender {
+                return Err(ContractError::Unauthorized);
+            }
+            if !lifecycle::can_transition(&proposal.status, &ProposalStatus::Dissolved) {
+                return Err(ContractError::InvalidTransition {
+                    from: proposal.status,
+                    to: ProposalStatus::Dissolved,
+                });
+            }
+            if proposal.dissolution.is_some() {
+                return Err(ContractError::DissolutionAlreadyRequested { id });
+            }
+            if gift_return_bps > BASIS_POINTS_DENOMINATOR {
+                return Err(ContractError::InvalidGiftReturnBps { gift_return_bps });
+            }
+
+            // the receiver escrows the returned share of the gift until the proposer confirms
+            let gift_return =
+                gift_return_share(&released_gift(deps.storage, &proposal)?, gift_return_bps);
+            if info.sender == proposal.receiver {
+                validate_exact_funds_sent(&info, &gift_return, |expected| {
+                    ContractError::GiftReturnNotPaid { expected }
+                })?;
+            } else {
+                cw_utils::nonpayable(&info)?;
+            }
+
+            proposal.dissolution = Some(Dissolution {
+                initiator: info.sender.clone(),
+                gift_return_bps,
+                gift_return: gift_return.clone(),
+                requested_at: env.block.height,
+                dissolved_at: None,
+            });
+            PROPOSALS.save(deps.storage, id, &proposal)?;
+
+            Ok(Response::new().add_attributes(vec![
+                ("action", "request_dissolution"),
                 ("proposal_id", id.to_string().as_str()),
-                ("reply", reply.unwrap_or_default().as_str()),
                 ("proposer", proposal.proposer.as_str()),
                 ("receiver", proposal.receiver.as_str()),
-                (
-                    "gift_received_by_receiver",
-                    &proposal
-                        .gift
-                        .clone()
-                        .iter()
-                        .map(|coin| coin.to_string())
-                        .collect::<Vec<_>>()
-                        .join(","),
-                ),
-                ("fee_received_by_owner", &proposal.fee.to_string()),
+                ("initiator", info.sender.as_str()),
+                ("gift_return_bps", gift_return_bps.to_string().as_str()),
+                ("gift_return_to_proposer", &coins_to_string(&gift_return)),
             ]))
         }
-        ExecuteMsg::No { id, reply } => {
+        ExecuteMsg::ConfirmDissolution { id } => {
             let mut proposal = PROPOSALS.load(deps.storage, id)?;
-            if proposal.receiver != info.sender {
+            let Some(mut dissolution) = proposal.dissolution.clone() else {
+                return Err(ContractError::DissolutionNotRequested { id });
+            };
+            if (proposal.proposer != info.sender && proposal.receiver != info.sender)
+                || dissolution.initiator == info.sender
+            {
                 return Err(ContractError::Unauthorized);
             }
+            lifecycle::transition(deps.storage, &mut proposal, ProposalStatus::Dissolved)?;
 
-            let mut total_refund: Vec<Coin> = vec![];
-            total_refund.push(proposal.fee.clone());
-            let mut messages: Vec<CosmosMsg> = vec![];
-            messages.push(CosmosMsg::Bank(BankMsg::Send {
-                to_address: proposal.proposer.to_string(),
-                amount: vec![proposal.fee.clone()],
-            }));
+            // the receiver pays the returned share of the gift now, unless it was escrowed
+            // when requesting the dissolution
+            let gift_return = dissolution.gift_return.clone();
+            if info.sender == proposal.receiver {
+                validate_exact_funds_sent(&info, &gift_return, |expected| {
+                    ContractError::GiftReturnNotPaid { expected }
+                })?;
+            } else {
+                cw_utils::nonpayable(&info)?;
+            }
 
-            if !proposal.gift.is_empty() {
-                total_refund.extend(proposal.gift.clone());
+            let mut messages: Vec<CosmosMsg> = vec![];
+            if !gift_return.is_empty() {
                 messages.push(CosmosMsg::Bank(BankMsg::Send {
                     to_address: proposal.proposer.to_string(),
-                    amount: proposal.gift.clone(),
+                    amount: gift_return.clone(),
                 }));
             }
 
-            total_refund = aggregate_coins(total_refund)?;
+            // a vesting gift stops being released, the receiver gets what vested so far and
+            // the rest goes back to the proposer
+            let (vested, unvested) = stop_vesting(&mut proposal, env.block.time)?;
+            release_gift(
+                deps.storage,
+                &native_coins(&[vested.clone(), unvested.clone()].concat()),
+            )?;
+            let routes = route_gift(deps.storage, &proposal, &vested)?;
+            for (recipient, gift) in &routes {
+                messages.extend(gift_transfer_msgs(gift, recipient)?);
+            }
+            messages.extend(gift_transfer_msgs(&unvested, &proposal.proposer)?);
 
-            FAILED_COUNTER.update(deps.storage, |count| -> StdResult<_> { Ok(count + 1) })?;
-            proposal.status = ProposalStatus::No;
-            proposal.reply = reply.clone();
-            proposal.replied_at = Some(env.block.height);
+            SUCCESSFUL_COUNTER.update(deps.storage, |count| -> StdResult<_> { Ok(count - 1) })?;
+            DISSOLVED_COUNTER.update(deps.storage, |count| -> StdResult<_> { Ok(count + 1) })?;
+            dissolution.dissolved_at = Some(env.block.height);
+            proposal.dissolution = Some(dissolution.clone());
             PROPOSALS.save(deps.storage, id, &proposal)?;
+            // the pair no longer holds the stage reached through the dissolved proposal
+            if let Some(stage) = &proposal.stage {
+                let (a, b) = pair_key(&proposal.proposer, &proposal.receiver);
+                if ACCEPTED_STAGES.may_load(deps.storage, (a, b, stage))? == Some(id) {
+                    ACCEPTED_STAGES.remove(deps.storage, (a, b, stage));
+                }
+            }
 
             Ok(Response::new().add_messages(messages).add_attributes(vec![
-                ("action", "say_no"),
+                ("action", "confirm_dissolution"),
                 ("proposal_id", id.to_string().as_str()),
-                ("reply", reply.unwrap_or_default().as_str()),
                 ("proposer", proposal.proposer.as_str()),
                 ("receiver", proposal.receiver.as_str()),
+                ("initiator", dissolution.initiator.as_str()),
                 (
-                    "total_refund_to_proposer",
-                    &total_refund
-                        .iter()
-                        .map(|coin| coin.to_string())
-                        .collect::<Vec<_>>()
-                        .join(","),
+                    "gift_return_bps",
+                    dissolution.gift_return_bps.to_string().as_str(),
                 ),
+                ("gift_returned_to_proposer", &coins_to_string(&gift_return)),
+                ("gift_transfers", &routes_to_string(&routes)),
+                ("unvested_gift_to_proposer", &gift_to_string(&unvested)),
+            ]))
+        }
+        ExecuteMsg::CancelDissolution { id } => {
+            let mut proposal = PROPOSALS.load(deps.storage, id)?;
+            let dissolution = match &proposal.dissolution {
+                Some(dissolution) if dissolution.dissolved_at.is_none() => dissolution.clone(),
+                _ => return Err(ContractError::DissolutionNotRequested { id }),
+            };
+            if dissolution.initiator != info.sender {
+                return Err(ContractError::Unauthorized);
+            }
+
+            // refund the returned share of the gift the receiver escrowed
+            let mut messages: Vec<CosmosMsg> = vec![];
+            let mut refund = vec![];
+            if dissolution.initiator == proposal.receiver {
+                refund = dissolution.gift_return.clone();
+                if !refund.is_empty() {
+                    messages.push(CosmosMsg::Bank(BankMsg::Send {
+                        to_address: proposal.receiver.to_string(),
+                        amount: refund.clone(),
+                    }));
+                }
+            }
+
+            proposal.dissolution = None;
+            PROPOSALS.save(deps.storage, id, &proposal)?;
+
+            Ok(Response::new().add_messages(messages).add_attributes(vec![
+                ("action", "cancel_dissolution"),
+                ("proposal_id", id.to_string().as_str()),
+                ("initiator", dissolution.initiator.as_str()),
+                ("total_refund_to_receiver", &coins_to_string(&refund)),
             ]))
         }
         ExecuteMsg::UpdateConfig {
-            successful_proposal_fee,
+            successful_proposal_fees,
+            default_proposal_duration,
+            max_proposal_duration,
+            finalization_delay,
+            stages,
+            gift_policy,
+            staking,
+            swap_pool,
+            gift_fee_bps,
+            fee_shares,
         } => {
             // only the owner of the contract can create a campaign
             cw_ownable::assert_owner(deps.storage, &info.sender)?;
             let mut config = CONFIG.load(deps.storage)?;
 
-            if let Some(successful_proposal_fee) = successful_proposal_fee {
-                config.successful_proposal_fee = successful_proposal_fee;
+            if let Some(successful_proposal_fees) = successful_proposal_fees {
+                config.successful_proposal_fees = successful_proposal_fees;
+            }
+
+            if let Some(default_proposal_duration) = default_proposal_duration {
+                config.default_proposal_duration =
+                    Some(default_proposal_duration).filter(|duration| !is_zero_duration(duration));
+            }
+
+            if let Some(max_proposal_duration) = max_proposal_duration {
+                config.max_proposal_duration =
+                    Some(max_proposal_duration).filter(|duration| !is_zero_duration(duration));
+            }
+
+            if let Some(finalization_delay) = finalization_delay {
+                config.finalization_delay =
+                    Some(finalization_delay).filter(|delay| !is_zero_duration(delay));
+            }
+
+            if let Some(stages) = stages {
+                validate_stages(&stages)?;
+                config.stages = stages;
             }
 
+            if let Some(gift_policy) = gift_policy {
+                validate_gift_policy(deps.as_ref(), &gift_policy)?;
+                config.gift_policy = gift_policy;
+            }
+
+            if let Some(gift_fee_bps) = gift_fee_bps {
+                validate_gift_fee(gift_fee_bps)?;
+                config.gift_fee_bps = Some(gift_fee_bps).filter(|bps| !bps.is_zero());
+            }
+
+            if let Some(fee_shares) = fee_shares {
+                validate_fee_shares(deps.as_ref(), &fee_shares)?;
+                config.fee_shares = fee_shares;
+            }
+
+            if let Some(swap_pool) = swap_pool {
+                config.swap_pool = match swap_pool.is_empty() {
+                    true => None,
+                    false => Some(deps.api.addr_validate(&swap_pool)?),
+                };
+            }
+
+            if let Some(staking) = staking {
+                // the gifts staked already stay delegated until their proposal is resolved
+                config.staking = Some(staking).filter(|staking| !staking.validator.is_empty());
+                if let Some(staking) = &config.staking {
+                    validate_staking_config(deps.as_ref(), staking)?;
+                }
+            }
+
+            validate_proposal_durations(&config, &env.block)?;
             CONFIG.save(deps.storage, &config)?;
 
             Ok(Response::new().add_attributes(vec![
                 ("action", "update_config"),
                 (
-                    "successful_proposal_fee",
-                    &config.successful_proposal_fee.to_string(),
+                    "successful_proposal_fees",
+                    &coins_to_string(&config.successful_proposal_fees),
+                ),
+                (
+                    "default_proposal_duration",
+                    &config
+                        .default_proposal_duration
+                        .map(|duration| duration.to_string())
+                        .unwrap_or_default(),
+                ),
+                (
+                    "max_proposal_duration",
+                    &config
+                        .max_proposal_duration
+                        .map(|duration| duration.to_string())
+                        .unwrap_or_default(),
                 ),
+                (
+                    "finalization_delay",
+                    &config
+                        .finalization_delay
+                        .map(|duration| duration.to_string())
+                        .unwrap_or_default(),
+                ),
+                ("stages", &config.stages.join(",")),
+                (
+                    "gift_fee_bps",
+                    &config
+                        .gift_fee_bps
+                        .map(|bps| bps.to_string())
+                        .unwrap_or_default(),
+                ),
+                (
+                    "fee_shares",
+                    &config
+                        .fee_shares
+                        .iter()
+                        .map(|share| format!("{}:{}", share.recipient, share.weight_bps))
+                        .collect::<Vec<_>>()
+                        .join(","),
+                ),
+            ]))
+        }
+
+        ExecuteMsg::WithdrawFees { denoms, to } => {
+            cw_ownable::assert_owner(deps.storage, &info.sender)?;
+            let to = match to {
+                Some(to) => deps.api.addr_validate(&to)?,
+                None => info.sender,
+            };
+            let denoms = if denoms.is_empty() {
+                TREASURY
+                    .keys(deps.storage, None, None, Order::Ascending)
+                    .collect::<StdResult<Vec<_>>>()?
+            } else {
+                denoms
+            };
+
+            let mut fees = vec![];
+            for denom in denoms {
+                let Some(available) = available_fees(deps.storage, &denom)? else {
+                    continue;
+                };
+                let amount = available.amount().unwrap_or_default();
+                if amount.is_zero() {
+                    continue;
+                }
+                WITHDRAWN_FEES.update(deps.storage, &denom, |withdrawn| -> StdResult<_> {
+                    Ok(withdrawn.unwrap_or_default().checked_add(amount)?)
+                })?;
+                fees.push(available);
+            }
+            if fees.is_empty() {
+                return Err(ContractError::NoFeesToWithdraw);
+            }
+
+            Ok(Response::new()
+                .add_messages(gift_transfer_msgs(&fees, &to)?)
+                .add_attributes(vec![
+                    ("action", "withdraw_fees"),
+                    ("to", to.as_str()),
+                    ("fees", &gift_to_string(&fees)),
+                ]))
+        }
+
+        ExecuteMsg::MigrateProposals { limit } => {
+            cw_utils::nonpayable(&info)?;
+            if !migration::is_migrating(deps.storage) {
+                return Err(ContractError::NothingToMigrate);
+            }
+            let limit = limit.unwrap_or(MAX_ITEMS_LIMIT).min(MAX_ITEMS_LIMIT);
+            let migrated = migration::migrate_legacy_proposals(deps.storage, limit)?;
+
+            Ok(Response::new().add_attributes(vec![
+                ("action", "migrate_proposals".to_string()),
+                ("migrated", migrated.to_string()),
+                ("done", (!migration::is_migrating(deps.storage)).to_string()),
             ]))
         }
 
@@ -292,26 +1052,882 @@ pub fn execute(
 }
 
 #[cfg_attr(not(feature = "library"), entry_point)]
-pub fn query(deps: Deps, _env: Env, msg: QueryMsg) -> Result<Binary, StdError> {
+pub fn query(deps: Deps, env: Env, msg: QueryMsg) -> Result<Binary, StdError> {
     match msg {
         QueryMsg::Config {} => query_config(deps),
-        QueryMsg::Proposal { id } => query_proposal(deps, id),
+        QueryMsg::Proposal { id } => query_proposal(deps, env, id),
         QueryMsg::Proposals {
             limit,
             filter_by,
             status,
             sort,
-        } => query_proposals(deps, limit, filter_by, status, sort),
+        } => query_proposals(deps, env, limit, filter_by, status, sort),
         QueryMsg::Status {} => query_status(deps),
+        QueryMsg::NegotiationChain { id } => query_negotiation_chain(deps, env, id),
+        QueryMsg::ProposalRevisions {
+            id,
+            start_after,
+            limit,
+        } => query_proposal_revisions(deps, env, id, start_after, limit),
+        QueryMsg::Stage {
+            address_a,
+            address_b,
+        } => query_stage(deps, address_a, address_b),
+        QueryMsg::GiftContributors {
+            id,
+            start_after,
+            limit,
+        } => query_gift_contributors(deps, env, id, start_after, limit),
+        QueryMsg::Certificates { id } => query_certificates(deps, id),
+        QueryMsg::Vesting { id } => query_vesting(deps, env, id),
+        QueryMsg::GiftFee { gift } => query_gift_fee(deps, gift),
+        QueryMsg::Treasury { start_after, limit } => query_treasury(deps, start_after, limit),
         QueryMsg::Ownership {} => Ok(to_json_binary(&cw_ownable::get_ownership(deps.storage)?)?),
     }
 }
 
 #[cfg_attr(not(feature = "library"), entry_point)]
 pub fn migrate(deps: DepsMut, _env: Env, _msg: MigrateMsg) -> Result<Response, ContractError> {
+    let stored_version: semver::Version =
+        cw2::get_contract_version(deps.storage)?.version.parse()?;
+    let min_version: semver::Version = MIN_MIGRATABLE_VERSION.parse()?;
+    if stored_version < min_version {
+        return Err(ContractError::MigrationUnsupported {
+            version: stored_version.to_string(),
+            min_version: min_version.to_string(),
+        });
+    }
     validate_contract!(deps, CONTRACT_NAME, CONTRACT_VERSION);
+    // 0.2.0 changed the stored shape of proposals, the config, the counters and the proposer index
+    if (stored_version.major, stored_version.minor) < (0, 2) {
+        migration::migrate_from_v0_1(deps.storage, MAX_ITEMS_LIMIT)?;
+    }
     set_contract_version(deps.storage, CONTRACT_NAME, CONTRACT_VERSION)?;
-    Ok(Response::default())
+    Ok(Response::default().add_attribute(
+        "proposals_migrated",
+        (!migration::is_migrating(deps.storage)).to_string(),
+    ))
+}
+
+#[cfg_attr(not(feature = "library"), entry_point)]
+pub fn reply(deps: DepsMut, env: Env, msg: Reply) -> Result<Response, ContractError> {
+    match msg.id {
+        SWAP_REPLY_ID => settle_gift_swap(deps, env),
+        id => Err(ContractError::UnknownReplyId { id }),
+    }
+}
+
+/// The fields of a new proposal provided by its proposer.
+struct NewProposal {
+    receiver: Addr,
+    title: Option<String>,
+    speech: Option<String>,
+    gift: Vec<Gift>,
+    expires_at: Option<Expiration>,
+    parent_id: Option<u64>,
+    stage: Option<String>,
+    reveal_at: Option<Expiration>,
+    vesting: Option<VestingSchedule>,
+    stake_gift: bool,
+    counter_gift: Vec<Coin>,
+    gift_options: Vec<Vec<Gift>>,
+}
+
+/// Creates a new pending proposal.
+fn create_proposal(
+    mut deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    new_proposal: NewProposal,
+) -> Result<Response, ContractError> {
+    let mut proposal = save_new_proposal(deps.branch(), &env, &info, new_proposal)?;
+
+    let gift = native_coins(&proposal.gift);
+    let messages = staking::delegate(deps.branch(), &env, &mut proposal, &gift)?;
+    if !messages.is_empty() {
+        PROPOSALS.save(deps.storage, proposal.id, &proposal)?;
+    }
+
+    Ok(Response::new().add_messages(messages).add_attributes(vec![
+        ("action", "create_proposal"),
+        ("proposal_id", proposal.id.to_string().as_str()),
+        ("proposer", proposal.proposer.as_str()),
+        ("receiver", proposal.receiver.as_str()),
+        ("expires_at", proposal.expires_at.to_string().as_str()),
+        ("stage", proposal.stage.unwrap_or_default().as_str()),
+        (
+            "reveal_at",
+            proposal
+                .reveal_at
+                .map(|reveal_at| reveal_at.to_string())
+                .unwrap_or_default()
+                .as_str(),
+        ),
+        (
+            "staked",
+            proposal
+                .staking
+                .map(|staking| staking.amount.to_string())
+                .unwrap_or_default()
+                .as_str(),
+        ),
+        ("counter_gift", &coins_to_string(&proposal.counter_gift)),
+    ]))
+}
+
+/// Validates the fees paid by the sender and saves a new pending proposal.
+fn save_new_proposal(
+    deps: DepsMut,
+    env: &Env,
+    info: &MessageInfo,
+    new_proposal: NewProposal,
+) -> Result<Proposal, ContractError> {
+    let config = CONFIG.load(deps.storage)?;
+    // proposer is not the receiver
+    if info.sender == new_proposal.receiver {
+        return Err(ContractError::InvalidReceiver);
+    }
+    let gift_options = validate_gift_options(new_proposal.gift_options)?;
+    if !gift_options.is_empty() && (new_proposal.vesting.is_some() || new_proposal.stake_gift) {
+        return Err(ContractError::InvalidGiftOptions {
+            reason: "a gift with options can't vest or be staked".to_string(),
+        });
+    }
+    // the gift options are escrowed along with the gift
+    let gift = aggregate_gift([new_proposal.gift, gift_options.concat()].concat())?;
+    // check if one of the proposal fees and the gift were paid, CW20 tokens were received already
+    let (fee, total_fees) = validate_fees_are_paid(
+        deps.as_ref(),
+        &config.gift_policy,
+        &config.successful_proposal_fees,
+        native_coins(&gift),
+        &native_coins(&gift),
+        info,
+    )?;
+
+    // make sure the user doesn't accidentally send more tokens than needed
+    validate_no_additional_funds_sent_with_proposal_creation(info, total_fees)?;
+
+    let expires_at = compute_proposal_expiration(&config, new_proposal.expires_at, &env.block)?;
+    if let Some(reveal_at) = &new_proposal.reveal_at {
+        validate_reveal_at(reveal_at, &expires_at, &env.block)?;
+    }
+    if let Some(schedule) = &new_proposal.vesting {
+        validate_vesting_schedule(schedule)?;
+    }
+    let counter_gift = validate_counter_gift(&config.gift_policy, new_proposal.counter_gift)?;
+    let stage = resolve_stage(
+        deps.storage,
+        &config,
+        &info.sender,
+        &new_proposal.receiver,
+        new_proposal.stage,
+    )?;
+
+    let staking = match (new_proposal.stake_gift, &config.staking) {
+        (false, _) => None,
+        (true, None) => return Err(ContractError::StakingNotEnabled),
+        (true, Some(staking_config)) => {
+            if new_proposal.vesting.is_some() {
+                return Err(ContractError::InvalidGiftStaking {
+                    reason: "a vesting gift can't be staked".to_string(),
+                });
+            }
+            let bonded_denom = deps.querier.query_bonded_denom()?;
+            if !native_coins(&gift)
+                .iter()
+                .any(|coin| coin.denom == bonded_denom)
+            {
+                return Err(ContractError::InvalidGiftStaking {
+                    reason: format!("the gift has no {bonded_denom} to stake"),
+                });
+            }
+            Some(GiftStaking {
+                validator: staking_config.validator.clone(),
+                amount: Coin::new(0u128, bonded_denom),
+                shares: Uint128::zero(),
+                reward_index: Decimal256::zero(),
+                rewards: Uint128::zero(),
+                unbonding_period: staking_config.unbonding_period,
+                unbonding: None,
+            })
+        }
+    };
+
+    let proposal_id = PROPOSAL_COUNTER.load(deps.storage)?;
+    let proposal = Proposal {
+        id: proposal_id,
+        proposer: info.sender.clone(),
+        receiver: new_proposal.receiver,
+        gift,
+        fee,
+        title: new_proposal.title,
+        speech: new_proposal.speech,
+        reply: None,
+        status: ProposalStatus::Pending,
+        created_at: env.block.height,
+        replied_at: None,
+        canceled_at: None,
+        expires_at,
+        finalizes_at: None,
+        deferred_until: None,
+        parent_id: new_proposal.parent_id,
+        stage,
+        dissolution: None,
+        revision: 0,
+        reveal_at: new_proposal.reveal_at,
+        vesting: new_proposal.vesting.map(|schedule| Vesting {
+            schedule,
+            started_at: None,
+            claimed: vec![],
+            stopped_at: None,
+        }),
+        gift_disposition: None,
+        staking,
+        receive_as: None,
+        counter_gift,
+        gift_options,
+        gift_option: None,
+        gift_fee: vec![],
+    };
+
+    escrow_gift(deps.storage, &native_coins(&proposal.gift))?;
+    PROPOSALS.save(deps.storage, proposal_id, &proposal)?;
+    PROPOSAL_COUNTER.save(deps.storage, &(proposal_id + 1))?;
+
+    Ok(proposal)
+}
+
+/// Creates or tops up a proposal with the CW20 tokens or NFT received from the sender.
+fn receive_gift(
+    deps: DepsMut,
+    env: Env,
+    sender: Addr,
+    gift: Vec<Gift>,
+    msg: ReceiveMsg,
+) -> Result<Response, ContractError> {
+    match msg {
+        ReceiveMsg::CreateProposal {
+            title,
+            speech,
+            receiver,
+            expires_at,
+            stage,
+            reveal_at,
+            vesting,
+        } => {
+            // the proposal fee can't be paid along with CW20 tokens or NFTs, such proposals are
+            // created with CreateProposal and topped up through the hook instead
+            if !CONFIG
+                .load(deps.storage)?
+                .successful_proposal_fees
+                .is_empty()
+            {
+                return Err(ContractError::ProposalCreationFeeMissing);
+            }
+            let receiver = deps.api.addr_validate(&receiver)?;
+            let info = MessageInfo {
+                sender,
+                funds: vec![],
+            };
+            create_proposal(
+                deps,
+                env,
+                info,
+                NewProposal {
+                    receiver,
+                    title,
+                    speech,
+                    gift,
+                    expires_at,
+                    parent_id: None,
+                    stage,
+                    reveal_at,
+                    vesting,
+                    stake_gift: false,
+                    counter_gift: vec![],
+                    gift_options: vec![],
+                },
+            )
+        }
+        ReceiveMsg::UpdateProposal {
+            id,
+            title,
+            speech,
+            gift_option,
+        } => update_proposal(deps, env, &sender, id, title, speech, gift, gift_option),
+    }
+}
+
+/// Updates a pending proposal, adding the given gift that was already paid by the proposer.
+#[allow(clippy::too_many_arguments)]
+fn update_proposal(
+    mut deps: DepsMut,
+    env: Env,
+    sender: &Addr,
+    id: u64,
+    title: Option<String>,
+    speech: Option<String>,
+    add_gift: Vec<Gift>,
+    gift_option: Option<u32>,
+) -> Result<Response, ContractError> {
+    let mut proposal = PROPOSALS.load(deps.storage, id)?;
+    if proposal.proposer != sender {
+        return Err(ContractError::Unauthorized);
+    }
+    lifecycle::resume_if_deferral_over(deps.storage, &mut proposal, &env.block)?;
+    if proposal.status != ProposalStatus::Pending {
+        return Err(ContractError::ProposalNotPending {
+            status: proposal.status,
+        });
+    }
+    if proposal.expires_at.is_expired(&env.block) {
+        return Err(ContractError::ProposalExpired { id });
+    }
+    if gift_option.is_some() {
+        validate_gift_option(&proposal, gift_option)?;
+    }
+
+    PROPOSAL_REVISIONS.save(
+        deps.storage,
+        (id, proposal.revision),
+        &ProposalRevision {
+            revision: proposal.revision,
+            title: proposal.title.clone(),
+            speech: proposal.speech.clone(),
+            gift: proposal.gift.clone(),
+            replaced_at: env.block.height,
+        },
+    )?;
+
+    if let Some(title) = title {
+        proposal.title = Some(title);
+    }
+    if let Some(speech) = speech {
+        proposal.speech = Some(speech);
+    }
+    proposal.gift = aggregate_gift([proposal.gift, add_gift.clone()].concat())?;
+    if let Some(option) = gift_option {
+        let option = &mut proposal.gift_options[option as usize];
+        *option = aggregate_gift([option.clone(), add_gift.clone()].concat())?;
+    }
+    proposal.revision += 1;
+    let messages = staking::delegate(deps.branch(), &env, &mut proposal, &native_coins(&add_gift))?;
+    PROPOSALS.save(deps.storage, id, &proposal)?;
+
+    Ok(Response::new().add_messages(messages).add_attributes(vec![
+        ("action", "update_proposal"),
+        ("proposal_id", id.to_string().as_str()),
+        ("proposer", proposal.proposer.as_str()),
+        ("receiver", proposal.receiver.as_str()),
+        ("revision", proposal.revision.to_string().as_str()),
+        ("added_gift", &gift_to_string(&add_gift)),
+        (
+            "gift_option",
+            &gift_option
+                .map(|option| option.to_string())
+                .unwrap_or_default(),
+        ),
+        ("gift", &gift_to_string(&proposal.gift)),
+    ]))
+}
+
+/// Adds the attached coins to a pending proposal's gift, tracking them per contributor so they
+/// can be refunded if the proposal isn't accepted.
+fn contribute_gift(
+    mut deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    id: u64,
+) -> Result<Response, ContractError> {
+    let mut proposal = PROPOSALS.load(deps.storage, id)?;
+    if proposal.receiver == info.sender {
+        return Err(ContractError::Unauthorized);
+    }
+    lifecycle::resume_if_deferral_over(deps.storage, &mut proposal, &env.block)?;
+    if proposal.status != ProposalStatus::Pending {
+        return Err(ContractError::ProposalNotPending {
+            status: proposal.status,
+        });
+    }
+    if proposal.expires_at.is_expired(&env.block) {
+        return Err(ContractError::ProposalExpired { id });
+    }
+    if info.funds.is_empty() {
+        return Err(PaymentError::NoFunds {}.into());
+    }
+
+    let contribution = aggregate_coins(info.funds.clone())?;
+    let gift = aggregate_coins([native_coins(&proposal.gift), contribution.clone()].concat())?;
+    let no_fee = Coin::new(0u128, proposal.fee.denom.clone());
+    validate_fees_are_paid(
+        deps.as_ref(),
+        &CONFIG.load(deps.storage)?.gift_policy,
+        &[no_fee],
+        contribution.clone(),
+        &gift,
+        &info,
+    )?;
+    escrow_gift(deps.storage, &contribution)?;
+    GIFT_CONTRIBUTIONS.update(
+        deps.storage,
+        (id, &info.sender),
+        |previous| -> StdResult<_> {
+            aggregate_coins([previous.unwrap_or_default(), contribution.clone()].concat())
+        },
+    )?;
+    proposal.gift = aggregate_gift([proposal.gift, native_gift(contribution.clone())].concat())?;
+    let messages = staking::delegate(deps.branch(), &env, &mut proposal, &contribution)?;
+    PROPOSALS.save(deps.storage, id, &proposal)?;
+
+    Ok(Response::new().add_messages(messages).add_attributes(vec![
+        ("action", "contribute_gift"),
+        ("proposal_id", id.to_string().as_str()),
+        ("contributor", info.sender.as_str()),
+        ("contribution", &coins_to_string(&contribution)),
+        ("gift", &gift_to_string(&proposal.gift)),
+    ]))
+}
+
+/// Answers a pending proposal. If the contract has a finalization delay, the answer is
+/// provisional and the funds stay in escrow until the proposal is finalized.
+#[allow(clippy::too_many_arguments)]
+fn answer_proposal(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    id: u64,
+    reply: Option<String>,
+    answer: ProposalStatus,
+    gift_disposition: Option<GiftDisposition>,
+    receive_as: Option<ReceiveAs>,
+    gift_option: Option<u32>,
+) -> Result<Response, ContractError> {
+    let mut proposal = PROPOSALS.load(deps.storage, id)?;
+    if proposal.receiver != info.sender {
+        return Err(ContractError::Unauthorized);
+    }
+    lifecycle::resume_if_deferral_over(deps.storage, &mut proposal, &env.block)?;
+
+    let config = CONFIG.load(deps.storage)?;
+    let finalization_delay = config.finalization_delay;
+    let status = match (&answer, finalization_delay) {
+        (ProposalStatus::Yes, Some(_)) => ProposalStatus::ProvisionalYes,
+        (ProposalStatus::No, Some(_)) => ProposalStatus::ProvisionalNo,
+        _ => answer.clone(),
+    };
+    lifecycle::transition(deps.storage, &mut proposal, status)?;
+    if proposal.expires_at.is_expired(&env.block) {
+        return Err(ContractError::ProposalExpired { id });
+    }
+    if !proposal.is_revealed(&env.block) {
+        return Err(ContractError::ProposalNotRevealed { id });
+    }
+
+    if let Some(gift_disposition) = &gift_disposition {
+        validate_gift_disposition(deps.as_ref(), gift_disposition)?;
+    }
+    if answer == ProposalStatus::Yes {
+        validate_gift_option(&proposal, gift_option)?;
+    }
+
+    // the receiver deposits the counter-gift when accepting, nothing is taken when rejecting
+    match answer {
+        ProposalStatus::Yes if !proposal.counter_gift.is_empty() => {
+            // the gift policy may have changed since the proposal was created
+            validate_counter_gift(&config.gift_policy, proposal.counter_gift.clone())?;
+            validate_exact_funds_sent(&info, &proposal.counter_gift, |expected| {
+                ContractError::CounterGiftNotPaid { expected }
+            })?;
+        }
+        _ => cw_utils::nonpayable(&info)?,
+    }
+
+    proposal.reply = reply;
+    proposal.replied_at = Some(env.block.height);
+    proposal.gift_disposition = gift_disposition;
+    if let Some(receive_as) = &receive_as {
+        validate_receive_as(&config, &proposal, receive_as)?;
+    }
+    proposal.receive_as = receive_as;
+    proposal.gift_option = gift_option;
+
+    let action = match answer {
+        ProposalStatus::Yes => "say_yes",
+        _ => "say_no",
+    };
+
+    match finalization_delay {
+        Some(finalization_delay) => {
+            let finalizes_at = finalization_delay.after(&env.block);
+            proposal.finalizes_at = Some(finalizes_at);
+            PROPOSALS.save(deps.storage, id, &proposal)?;
+            // the counter-gift is held until the acceptance is final, counting towards the
+            // escrowed totals although its deposit isn't capped
+            if answer == ProposalStatus::Yes {
+                escrow_gift(deps.storage, &proposal.counter_gift)?;
+            }
+
+            Ok(Response::new().add_attributes(vec![
+                ("action", action),
+                ("proposal_id", id.to_string().as_str()),
+                ("reply", proposal.reply.unwrap_or_default().as_str()),
+                ("proposer", proposal.proposer.as_str()),
+                ("receiver", proposal.receiver.as_str()),
+                ("provisional", "true"),
+                ("finalizes_at", finalizes_at.to_string().as_str()),
+            ]))
+        }
+        None if answer == ProposalStatus::Yes => settle_yes(deps, &env, proposal, action),
+        None => settle_no(deps, &env, proposal, action),
+    }
+}
+
+/// Executes the transfers of an accepted proposal, splitting the fees or crediting them to the
+/// treasury and routing the gift according to the receiver's disposition. A vesting gift starts
+/// being released instead, only its NFTs are sent, unless the receiver declined it.
+fn settle_yes(
+    mut deps: DepsMut,
+    env: &Env,
+    mut proposal: Proposal,
+    action: &str,
+) -> Result<Response, ContractError> {
+    let config = CONFIG.load(deps.storage)?;
+    let mut messages: Vec<CosmosMsg> = vec![];
+    let escrowed = proposal.gift.clone();
+
+    // the gift options the receiver didn't pick go back to the proposer
+    let unpicked = unpicked_gift_options(&proposal)?;
+    proposal.gift = subtract_gift(&proposal.gift, &unpicked)?;
+    messages.extend(gift_transfer_msgs(&unpicked, &proposal.proposer)?);
+
+    // the gift fee is charged on the accepted gift, unless the receiver declined it
+    let declined = proposal.gift_disposition == Some(GiftDisposition::Decline);
+    if let Some(gift_fee_bps) = config.gift_fee_bps.filter(|_| !declined) {
+        proposal.gift_fee = compute_gift_fee(&proposal.gift, gift_fee_bps)?;
+        proposal.gift = subtract_gift(&proposal.gift, &proposal.gift_fee)?;
+    }
+
+    // the gift fee charged on the staked gift is paid once it's unbonded
+    let gift_fee = subtract_gift(
+        &proposal.gift_fee,
+        &native_gift(staking::unbonding_gift_fee(&proposal).into_iter().collect()),
+    )?;
+    let (fee_messages, fee_attributes) = settle_fees(
+        deps.branch(),
+        &config,
+        &proposal,
+        native_gift(vec![proposal.fee.clone()]),
+        gift_fee,
+    )?;
+    messages.extend(fee_messages);
+
+    let gift_sent: Vec<Gift> = match &mut proposal.vesting {
+        Some(vesting) if !declined => {
+            vesting.started_at = Some(env.block.time);
+            proposal
+                .gift
+                .iter()
+                .filter(|part| part.amount().is_none())
+                .cloned()
+                .collect()
+        }
+        _ => proposal.gift.clone(),
+    };
+    // a vesting gift stays escrowed until it's released
+    let vesting_gift = subtract_gift(&proposal.gift, &gift_sent)?;
+    release_resolved_gift(deps.storage, &proposal, &escrowed, &vesting_gift)?;
+    // the coins swapped into the receiver's denom are paid out when the pool replies
+    let (swaps, swapped) = match &proposal.receive_as {
+        Some(receive_as) => swap_gift(deps.branch(), env, &proposal, receive_as, &gift_sent)?,
+        None => (vec![], vec![]),
+    };
+    let gift_sent: Vec<Gift> = gift_sent
+        .into_iter()
+        .filter(|part| !swapped.contains(part))
+        .collect();
+    let mut routes = route_gift(deps.storage, &proposal, &gift_sent)?;
+    let gift_received_by_receiver = routes
+        .iter()
+        .filter(|(recipient, _)| recipient == proposal.receiver)
+        .flat_map(|(_, gift)| gift.clone())
+        .collect::<Vec<_>>();
+    // the staked part of the gift is paid out once unbonded, its rewards go to the receiver
+    let receiver = proposal.receiver.clone();
+    let (staking_messages, staking_attributes) =
+        staking::undelegate(deps.branch(), env, &mut proposal, &mut routes, &receiver)?;
+    messages.extend(staking_messages);
+    messages.extend(gift_transfers_msgs(&routes)?);
+    // the counter-gift deposited by the receiver goes to the proposer in exchange
+    if !proposal.counter_gift.is_empty() {
+        messages.push(CosmosMsg::Bank(BankMsg::Send {
+            to_address: proposal.proposer.to_string(),
+            amount: proposal.counter_gift.clone(),
+        }));
+    }
+
+    let mut certificates = vec![];
+    if let Some(collection) = config.certificate_collection {
+        let (mint_messages, token_ids) = mint_certificates(&collection, &proposal)?;
+        messages.extend(mint_messages);
+        CERTIFICATES.save(deps.storage, proposal.id, &token_ids)?;
+        certificates = token_ids;
+    }
+
+    SUCCESSFUL_COUNTER.update(deps.storage, |count| -> StdResult<_> { Ok(count + 1) })?;
+    PROPOSALS.save(deps.storage, proposal.id, &proposal)?;
+    if let Some(stage) = &proposal.stage {
+        let (a, b) = pair_key(&proposal.proposer, &proposal.receiver);
+        ACCEPTED_STAGES.save(deps.storage, (a, b, stage), &proposal.id)?;
+    }
+
+    Ok(Response::new()
+        .add_submessages(swaps)
+        .add_messages(messages)
+        .add_attributes(vec![
+            ("action", action),
+            ("proposal_id", proposal.id.to_string().as_str()),
+            ("reply", proposal.reply.clone().unwrap_or_default().as_str()),
+            ("proposer", proposal.proposer.as_str()),
+            ("receiver", proposal.receiver.as_str()),
+            (
+                "gift_received_by_receiver",
+                &gift_to_string(&gift_received_by_receiver),
+            ),
+            (
+                "gift_disposition",
+                &proposal
+                    .gift_disposition
+                    .clone()
+                    .unwrap_or(GiftDisposition::Keep)
+                    .to_string(),
+            ),
+            ("gift_transfers", &routes_to_string(&routes)),
+            (
+                "gift_option",
+                &proposal
+                    .gift_option
+                    .map(|option| option.to_string())
+                    .unwrap_or_default(),
+            ),
+            ("gift_options_refunded", &gift_to_string(&unpicked)),
+            (
+                "receive_as",
+                proposal
+                    .receive_as
+                    .as_ref()
+                    .map(|receive_as| receive_as.denom.as_str())
+                    .unwrap_or_default(),
+            ),
+            ("swapped", &gift_to_string(&swapped)),
+            (
+                "counter_gift_received_by_proposer",
+                &coins_to_string(&proposal.counter_gift),
+            ),
+            ("fee", &proposal.fee.to_string()),
+            ("gift_fee", &gift_to_string(&proposal.gift_fee)),
+            ("certificates", &certificates.join(",")),
+            ("vesting", &proposal.vesting.is_some().to_string()),
+        ])
+        .add_attributes(fee_attributes)
+        .add_attributes(staking_attributes))
+}
+
+/// Pays the proposal fee and the gift fee of an accepted proposal, splitting them across the
+/// configured shares or else crediting them to the treasury. The proposal fee goes back to the
+/// proposer if the contract has no owner to withdraw it, the gift fee is credited regardless
+/// since it was taken from the receiver's gift.
+fn settle_fees(
+    deps: DepsMut,
+    config: &Config,
+    proposal: &Proposal,
+    proposal_fee: Vec<Gift>,
+    gift_fee: Vec<Gift>,
+) -> Result<(Vec<CosmosMsg>, Vec<Attribute>), ContractError> {
+    let non_zero = |fees: Vec<Gift>| -> Vec<Gift> {
+        fees.into_iter()
+            .filter(|fee| fee.amount().is_some_and(|amount| !amount.is_zero()))
+            .collect()
+    };
+    let (proposal_fee, gift_fee) = (non_zero(proposal_fee), non_zero(gift_fee));
+    let fees = aggregate_gift([proposal_fee.clone(), gift_fee.clone()].concat())?;
+    let fee_splits = split_fees(&fees, &config.fee_shares)?;
+    let has_owner = cw_ownable::get_ownership(deps.storage)?.owner.is_some();
+    let fees_credited = config.fee_shares.is_empty() && (has_owner || !gift_fee.is_empty());
+
+    let mut messages = vec![];
+    if !config.fee_shares.is_empty() {
+        for (recipient, fees) in &fee_splits {
+            messages.extend(fee_share_msgs(recipient, fees)?);
+        }
+    } else if has_owner {
+        credit_treasury(deps.storage, &fees)?;
+    } else {
+        messages.extend(gift_transfer_msgs(&proposal_fee, &proposal.proposer)?);
+        credit_treasury(deps.storage, &gift_fee)?;
+    }
+
+    Ok((
+        messages,
+        vec![
+            Attribute::new("fees_credited_to_treasury", fees_credited.to_string()),
+            Attribute::new("fee_shares", fee_shares_to_string(&fee_splits)),
+        ],
+    ))
+}
+
+/// Swaps the native coins of an accepted proposal's gift into the receiver's denom through the
+/// swap pool. Returns the swap messages, the last one replying to pay the receiver, along with
+/// the swapped part of the gift.
+fn swap_gift(
+    deps: DepsMut,
+    env: &Env,
+    proposal: &Proposal,
+    receive_as: &ReceiveAs,
+    gift: &[Gift],
+) -> Result<(Vec<SubMsg>, Vec<Gift>), ContractError> {
+    let swap_pool = CONFIG
+        .load(deps.storage)?
+        .swap_pool
+        .ok_or(ContractError::SwapNotEnabled)?;
+    let (received_directly, to_swap): (Vec<Coin>, Vec<Coin>) = native_coins(gift)
+        .into_iter()
+        .filter(|coin| !coin.amount.is_zero())
+        .partition(|coin| coin.denom == receive_as.denom);
+    let received_directly = received_directly
+        .iter()
+        .map(|coin| coin.amount)
+        .sum::<Uint128>();
+
+    if to_swap.is_empty() {
+        if received_directly < receive_as.min_out {
+            return Err(ContractError::SwapSlippageExceeded {
+                denom: receive_as.denom.clone(),
+                amount: received_directly,
+                min_out: receive_as.min_out,
+            });
+        }
+        return Ok((vec![], vec![]));
+    }
+
+    // the swaps are the first messages executed, so the balance only changes with their result
+    let balance_before = deps
+        .querier
+        .query_balance(&env.contract.address, &receive_as.denom)?
+        .amount;
+    PENDING_SWAP.save(
+        deps.storage,
+        &PendingSwap {
+            proposal_id: proposal.id,
+            recipient: proposal.receiver.clone(),
+            denom: receive_as.denom.clone(),
+            min_out: receive_as.min_out,
+            balance_before,
+            received_directly,
+        },
+    )?;
+
+    let last = to_swap.len() - 1;
+    let swaps = to_swap
+        .iter()
+        .enumerate()
+        .map(|(index, coin)| {
+            let swap = WasmMsg::Execute {
+                contract_addr: swap_pool.to_string(),
+                msg: to_json_binary(&PoolExecuteMsg::Swap {
+                    ask_denom: receive_as.denom.clone(),
+                })?,
+                funds: vec![coin.clone()],
+            };
+            Ok(match index == last {
+                true => SubMsg::reply_on_success(swap, SWAP_REPLY_ID),
+                false => SubMsg::new(swap),
+            })
+        })
+        .collect::<StdResult<Vec<_>>>()?;
+
+    Ok((swaps, native_gift(to_swap)))
+}
+
+/// Pays the receiver the result of the gift's swap, failing the acceptance if it's below the
+/// receiver's minimum.
+fn settle_gift_swap(deps: DepsMut, env: Env) -> Result<Response, ContractError> {
+    let swap = PENDING_SWAP.load(deps.storage)?;
+    PENDING_SWAP.remove(deps.storage);
+
+    let balance = deps
+        .querier
+        .query_balance(&env.contract.address, &swap.denom)?
+        .amount;
+    let swapped = balance.checked_sub(swap.balance_before)?;
+    let received = swapped.checked_add(swap.received_directly)?;
+    if received < swap.min_out {
+        return Err(ContractError::SwapSlippageExceeded {
+            denom: swap.denom,
+            amount: received,
+            min_out: swap.min_out,
+        });
+    }
+
+    let swapped = Coin::new(swapped, swap.denom.clone());
+    let mut response = Response::new();
+    if !swapped.amount.is_zero() {
+        response = response.add_message(BankMsg::Send {
+            to_address: swap.recipient.to_string(),
+            amount: vec![swapped.clone()],
+        });
+    }
+
+    Ok(response.add_attributes(vec![
+        ("action", "swap_gift"),
+        ("proposal_id", swap.proposal_id.to_string().as_str()),
+        ("receiver", swap.recipient.as_str()),
+        ("swapped_into", swapped.to_string().as_str()),
+        (
+            "received",
+            Coin::new(received, swap.denom).to_string().as_str(),
+        ),
+    ]))
+}
+
+/// Executes the transfers of a rejected proposal, refunding the fee and gift to the proposer.
+fn settle_no(
+    mut deps: DepsMut,
+    env: &Env,
+    mut proposal: Proposal,
+    action: &str,
+) -> Result<Response, ContractError> {
+    let Refund {
+        mut messages,
+        mut transfers,
+        proposer: total_refund,
+        contributors,
+    } = refund_proposer(deps.storage, &proposal)?;
+    let proposer = proposal.proposer.clone();
+    let (staking_messages, staking_attributes) =
+        staking::undelegate(deps.branch(), env, &mut proposal, &mut transfers, &proposer)?;
+    messages.extend(staking_messages);
+    messages.extend(gift_transfers_msgs(&transfers)?);
+    release_resolved_gift(deps.storage, &proposal, &proposal.gift, &[])?;
+
+    FAILED_COUNTER.update(deps.storage, |count| -> StdResult<_> { Ok(count + 1) })?;
+    PROPOSALS.save(deps.storage, proposal.id, &proposal)?;
+
+    Ok(Response::new()
+        .add_messages(messages)
+        .add_attributes(vec![
+            ("action", action),
+            ("proposal_id", proposal.id.to_string().as_str()),
+            ("reply", proposal.reply.clone().unwrap_or_default().as_str()),
+            ("proposer", proposal.proposer.as_str()),
+            ("receiver", proposal.receiver.as_str()),
+            ("total_refund_to_proposer", &gift_to_string(&total_refund)),
+            (
+                "refunded_contributors",
+                &contributions_to_string(&contributors),
+            ),
+        ])
+        .add_attributes(staking_attributes))
 }
 
 fn query_config(deps: Deps) -> Result<Binary, StdError> {
@@ -319,13 +1935,14 @@ fn query_config(deps: Deps) -> Result<Binary, StdError> {
     to_json_binary(&config)
 }
 
-fn query_proposal(deps: Deps, id: u64) -> Result<Binary, StdError> {
-    let proposal = PROPOSALS.load(deps.storage, id)?;
+fn query_proposal(deps: Deps, env: Env, id: u64) -> Result<Binary, StdError> {
+    let proposal = PROPOSALS.load(deps.storage, id)?.redacted(&env.block);
     to_json_binary(&proposal)
 }
 
 fn query_proposals(
     deps: Deps,
+    env: Env,
     limit: Option<u32>,
     filter_by: Option<ProposalBy>,
     status: Option<ProposalStatus>,
@@ -368,6 +1985,11 @@ fn query_proposals(
             .collect::<StdResult<Vec<Proposal>>>()?,
     };
 
+    let proposals = proposals
+        .into_iter()
+        .map(|proposal| proposal.redacted(&env.block))
+        .collect();
+
     to_json_binary(&ProposalsResponse { proposals })
 }
 
@@ -376,8 +1998,18 @@ fn query_status(deps: Deps) -> Result<Binary, StdError> {
     let total_proposals_yes = SUCCESSFUL_COUNTER.load(deps.storage)?;
     let total_proposals_no = FAILED_COUNTER.load(deps.storage)?;
     let total_proposals_cancelled = CANCELED_COUNTER.load(deps.storage)?;
-    let total_proposals_pending =
-        total_proposals - total_proposals_yes - total_proposals_no - total_proposals_cancelled;
+    let total_proposals_expired = EXPIRED_COUNTER.load(deps.storage)?;
+    let total_proposals_countered = COUNTERED_COUNTER.load(deps.storage)?;
+    let total_proposals_deferred = DEFERRED_COUNTER.load(deps.storage)?;
+    let total_proposals_dissolved = DISSOLVED_COUNTER.load(deps.storage)?;
+    let total_proposals_pending = total_proposals
+        - total_proposals_yes
+        - total_proposals_no
+        - total_proposals_cancelled
+        - total_proposals_expired
+        - total_proposals_countered
+        - total_proposals_deferred
+        - total_proposals_dissolved;
 
     to_json_binary(&Status {
         total_proposals,
@@ -385,6 +2017,219 @@ fn query_status(deps: Deps) -> Result<Binary, StdError> {
         total_proposals_yes,
         total_proposals_no,
         total_proposals_cancelled,
+        total_proposals_expired,
+        total_proposals_countered,
+        total_proposals_deferred,
+        total_proposals_dissolved,
+    })
+}
+
+fn query_negotiation_chain(deps: Deps, env: Env, id: u64) -> Result<Binary, StdError> {
+    // walk up to the proposal that started the negotiation
+    let mut root = PROPOSALS.load(deps.storage, id)?;
+    while let Some(parent_id) = root.parent_id {
+        root = PROPOSALS.load(deps.storage, parent_id)?;
+    }
+
+    // walk down through the counter-proposals
+    let mut next_id = COUNTER_PROPOSALS.may_load(deps.storage, root.id)?;
+    let mut proposals = vec![root];
+    while let Some(id) = next_id {
+        next_id = COUNTER_PROPOSALS.may_load(deps.storage, id)?;
+        proposals.push(PROPOSALS.load(deps.storage, id)?);
+    }
+
+    let proposals = proposals
+        .into_iter()
+        .map(|proposal| proposal.redacted(&env.block))
+        .collect();
+
+    to_json_binary(&ProposalsResponse { proposals })
+}
+
+fn query_proposal_revisions(
+    deps: Deps,
+    env: Env,
+    id: u64,
+    start_after: Option<u32>,
+    limit: Option<u32>,
+) -> Result<Binary, StdError> {
+    let revealed = PROPOSALS.load(deps.storage, id)?.is_revealed(&env.block);
+    let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_ITEMS_LIMIT) as usize;
+    let revisions = PROPOSAL_REVISIONS
+        .prefix(id)
+        .range(
+            deps.storage,
+            start_after.map(Bound::exclusive),
+            None,
+            Order::Ascending,
+        )
+        .take(limit)
+        .map(|item| {
+            let (_, mut revision) = item?;
+            // prior versions are hidden as well until the proposal is revealed
+            if !revealed {
+                revision.title = None;
+                revision.speech = None;
+                revision.gift = vec![];
+            }
+            Ok(revision)
+        })
+        .collect::<StdResult<Vec<ProposalRevision>>>()?;
+
+    to_json_binary(&ProposalRevisionsResponse { revisions })
+}
+
+fn query_stage(deps: Deps, address_a: String, address_b: String) -> Result<Binary, StdError> {
+    let address_a = deps.api.addr_validate(&address_a)?;
+    let address_b = deps.api.addr_validate(&address_b)?;
+    let (a, b) = pair_key(&address_a, &address_b);
+    let config = CONFIG.load(deps.storage)?;
+
+    // the current stage is the latest one reached between the pair
+    for stage in config.stages.iter().rev() {
+        if let Some(proposal_id) = ACCEPTED_STAGES.may_load(deps.storage, (a, b, stage))? {
+            return to_json_binary(&StageResponse {
+                stage: Some(stage.clone()),
+                proposal_id: Some(proposal_id),
+            });
+        }
+    }
+
+    to_json_binary(&StageResponse {
+        stage: None,
+        proposal_id: None,
+    })
+}
+
+fn query_gift_contributors(
+    deps: Deps,
+    env: Env,
+    id: u64,
+    start_after: Option<String>,
+    limit: Option<u32>,
+) -> Result<Binary, StdError> {
+    // contributors would give the surprise away
+    if !PROPOSALS.load(deps.storage, id)?.is_revealed(&env.block) {
+        return to_json_binary(&GiftContributorsResponse {
+            contributors: vec![],
+        });
+    }
+    let start_after = start_after
+        .map(|address| deps.api.addr_validate(&address))
+        .transpose()?;
+    let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_ITEMS_LIMIT) as usize;
+    let contributors = GIFT_CONTRIBUTIONS
+        .prefix(id)
+        .range(
+            deps.storage,
+            start_after.as_ref().map(Bound::exclusive),
+            None,
+            Order::Ascending,
+        )
+        .take(limit)
+        .map(|item| {
+            let (contributor, amount) = item?;
+            Ok(GiftContribution {
+                contributor,
+                amount,
+            })
+        })
+        .collect::<StdResult<Vec<_>>>()?;
+
+    to_json_binary(&GiftContributorsResponse { contributors })
+}
+
+fn query_treasury(
+    deps: Deps,
+    start_after: Option<String>,
+    limit: Option<u32>,
+) -> Result<Binary, StdError> {
+    let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_ITEMS_LIMIT) as usize;
+    let fees = TREASURY
+        .range(
+            deps.storage,
+            start_after.as_deref().map(Bound::exclusive),
+            None,
+            Order::Ascending,
+        )
+        .take(limit)
+        .map(|item| {
+            let (denom, accrued) = item?;
+            let accrued = accrued.amount().unwrap_or_default();
+            let withdrawn = WITHDRAWN_FEES
+                .may_load(deps.storage, &denom)?
+                .unwrap_or_default();
+            Ok(TreasuryFees {
+                available: accrued.checked_sub(withdrawn)?,
+                denom,
+                accrued,
+                withdrawn,
+            })
+        })
+        .collect::<StdResult<Vec<_>>>()?;
+
+    to_json_binary(&TreasuryResponse { fees })
+}
+
+fn query_certificates(deps: Deps, id: u64) -> Result<Binary, StdError> {
+    let token_ids = CERTIFICATES.may_load(deps.storage, id)?.unwrap_or_default();
+    to_json_binary(&CertificatesResponse { token_ids })
+}
+
+fn query_gift_fee(deps: Deps, gift: Vec<Coin>) -> Result<Binary, StdError> {
+    let gift = aggregate_coins(gift)?;
+    let fee = match CONFIG.load(deps.storage)?.gift_fee_bps {
+        Some(gift_fee_bps) => compute_gift_fee(&native_gift(gift.clone()), gift_fee_bps)
+            .map_err(|error| StdError::generic_err(error.to_string()))?,
+        None => vec![],
+    };
+    let remaining = subtract_gift(&native_gift(gift), &fee)?;
+
+    to_json_binary(&GiftFeeResponse {
+        fee: native_coins(&fee),
+        remaining: native_coins(&remaining),
+    })
+}
+
+fn query_vesting(deps: Deps, env: Env, id: u64) -> Result<Binary, StdError> {
+    // the vesting gift is hidden along with the rest of the gift until the proposal is revealed
+    let proposal = PROPOSALS.load(deps.storage, id)?.redacted(&env.block);
+    let Some(vesting) = proposal.vesting else {
+        return Err(StdError::generic_err(format!(
+            "Proposal {id} has no vesting gift"
+        )));
+    };
+
+    let mut amounts = vec![];
+    for part in &proposal.gift {
+        let (Some(denom), Some(total)) = (part.denom(), part.amount()) else {
+            continue;
+        };
+        // nothing is released after the vesting was stopped
+        let now = vesting.stopped_at.unwrap_or(env.block.time);
+        let vested = vesting
+            .started_at
+            .map(|started_at| vested_amount(&vesting.schedule, started_at, total, now))
+            .unwrap_or_default();
+        let claimed = claimed_amount(&vesting.claimed, &denom);
+        let remaining = match vesting.stopped_at {
+            Some(_) => Uint128::zero(),
+            None => total.checked_sub(claimed)?,
+        };
+        amounts.push(VestingAmount {
+            denom,
+            total,
+            vested,
+            claimed,
+            remaining,
+        });
+    }
+
+    to_json_binary(&VestingResponse {
+        schedule: vesting.schedule,
+        started_at: vesting.started_at,
+        amounts,
     })
 }
 
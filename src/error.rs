@@ -1,6 +1,7 @@
+use crate::proposal::state::ProposalStatus;
 use cosmwasm_std::{
-    CheckedMultiplyFractionError, DivideByZeroError, Instantiate2AddressError, OverflowError,
-    StdError, Uint128,
+    CheckedMultiplyFractionError, Instantiate2AddressError, OverflowError, StdError, Timestamp,
+    Uint128,
 };
 use cw_migrate_error_derive::cw_migrate_invalid_version_error;
 use cw_ownable::OwnershipError;
@@ -36,11 +37,6 @@ pub enum ContractError {
     #[error("The provided assets are both the same")]
     SameAsset,
 
-    #[error(
-        "Assertion failed; can only cancel proposal in pending status, swap amount: {current_status}"
-    )]
-    CancelProposalInvalidStatus { current_status: String },
-
     #[error("An overflow occurred when attempting to construct a decimal")]
     DecimalOverflow,
 
@@ -50,13 +46,10 @@ pub enum ContractError {
     #[error(transparent)]
     CheckedMultiplyFractionError(#[from] CheckedMultiplyFractionError),
 
-    #[error(transparent)]
-    DivideByZeroError(#[from] DivideByZeroError),
-
-    #[error("Invalid pool creation fee, expected {expected} got {amount}")]
-    InvalidProposalCreationFee { amount: Uint128, expected: Uint128 },
+    #[error("Invalid proposal creation fee, expected {expected} got {amount}")]
+    InvalidProposalCreationFee { amount: String, expected: String },
 
-    #[error("Pool creation fee was not included")]
+    #[error("The proposal creation fee was not included")]
     ProposalCreationFeeMissing,
 
     #[error("Additional funds were sent with proposal creation, expected proposal creation and gift fees only")]
@@ -65,8 +58,192 @@ pub enum ContractError {
     #[error("The gift fee was not paid.")]
     GiftFeeNotPaid,
 
+    #[error("Invalid staking config: {reason}")]
+    InvalidStakingConfig { reason: String },
+
+    #[error("Staking gifts is not enabled")]
+    StakingNotEnabled,
+
+    #[error("The gift can't be staked: {reason}")]
+    InvalidGiftStaking { reason: String },
+
+    #[error("Proposal {id} has no unbonded gift to claim")]
+    NoUnbondedGift { id: u64 },
+
+    #[error("The gift of proposal {id} is unbonding until {claimable_at}")]
+    UnbondingNotOver { id: u64, claimable_at: Timestamp },
+
+    #[error("The gift of proposal {id} is waiting for the next undelegation of its batch")]
+    UnbondingNotStarted { id: u64 },
+
+    #[error("No gift was ever delegated to {validator}")]
+    NothingToUndelegate { validator: String },
+
+    #[error("The next batch can't be undelegated from {validator} before the epoch is over")]
+    UndelegationNotDue { validator: String },
+
+    #[error("Swapping gifts is not enabled")]
+    SwapNotEnabled,
+
+    #[error("The gift can't be swapped: {reason}")]
+    InvalidSwap { reason: String },
+
+    #[error(
+        "The gift would be received as {amount}{denom}, below the minimum of {min_out}{denom}"
+    )]
+    SwapSlippageExceeded {
+        denom: String,
+        amount: Uint128,
+        min_out: Uint128,
+    },
+
+    #[error("Unknown reply id {id}")]
+    UnknownReplyId { id: u64 },
+
+    #[error("Invalid gift policy: {reason}")]
+    InvalidGiftPolicy { reason: String },
+
+    #[error("The denom {denom} is not accepted as gift")]
+    GiftDenomNotAllowed { denom: String },
+
+    #[error("The token contract {address} is not accepted as gift")]
+    GiftTokenNotAllowed { address: String },
+
+    #[error("The gift of {amount}{denom} is below the minimum of {min}{denom}")]
+    GiftAmountTooLow {
+        denom: String,
+        amount: Uint128,
+        min: Uint128,
+    },
+
+    #[error("The gift of {amount}{denom} is above the maximum of {max}{denom}")]
+    GiftAmountTooHigh {
+        denom: String,
+        amount: Uint128,
+        max: Uint128,
+    },
+
+    #[error("The gift has {count} denoms, the maximum is {max}")]
+    TooManyGiftDenoms { count: u32, max: u32 },
+
+    #[error(
+        "The gifts in escrow would total {escrowed}{denom}, above the escrow cap of {cap}{denom}"
+    )]
+    EscrowCapExceeded {
+        denom: String,
+        escrowed: Uint128,
+        cap: Uint128,
+    },
+
     #[error("The receiver cannot be the proposer")]
     InvalidReceiver,
+
+    #[error("Invalid proposal expiration: {reason}")]
+    InvalidExpiration { reason: String },
+
+    #[error("Invalid proposal transition from {from} to {to}")]
+    InvalidTransition {
+        from: ProposalStatus,
+        to: ProposalStatus,
+    },
+
+    #[error("Invalid reveal time: {reason}")]
+    InvalidRevealTime { reason: String },
+
+    #[error("Proposal {id} has not been revealed yet")]
+    ProposalNotRevealed { id: u64 },
+
+    #[error("Invalid gift disposition: {reason}")]
+    InvalidGiftDisposition { reason: String },
+
+    #[error("Invalid vesting schedule: {reason}")]
+    InvalidVestingSchedule { reason: String },
+
+    #[error("Proposal {id} has no gift being released over time")]
+    GiftNotVesting { id: u64 },
+
+    #[error("Proposal {id} has no released gift left to claim")]
+    NothingToClaim { id: u64 },
+
+    #[error("The release of proposal {id}'s gift was stopped by its dissolution")]
+    VestingStopped { id: u64 },
+
+    #[error("Invalid fee shares: {reason}")]
+    InvalidFeeShares { reason: String },
+
+    #[error("The treasury has no fees to withdraw")]
+    NoFeesToWithdraw,
+
+    #[error("Proposal {id} has expired")]
+    ProposalExpired { id: u64 },
+
+    #[error("Proposal {id} has not expired yet")]
+    ProposalNotExpired { id: u64 },
+
+    #[error("Only pending proposals can be updated, current status: {status}")]
+    ProposalNotPending { status: ProposalStatus },
+
+    #[error("Invalid stages: {reason}")]
+    InvalidStages { reason: String },
+
+    #[error("Unknown stage: {stage}")]
+    UnknownStage { stage: String },
+
+    #[error("A proposal for the {stage} stage requires an accepted {previous_stage} proposal between the same pair")]
+    PreviousStageNotAccepted {
+        stage: String,
+        previous_stage: String,
+    },
+
+    #[error("The gift return share must be at most 10000 bps, got {gift_return_bps}")]
+    InvalidGiftReturnBps { gift_return_bps: u16 },
+
+    #[error("Invalid gift options: {reason}")]
+    InvalidGiftOptions { reason: String },
+
+    #[error("Invalid gift fee: {reason}")]
+    InvalidGiftFee { reason: String },
+
+    #[error("Invalid counter-gift: {reason}")]
+    InvalidCounterGift { reason: String },
+
+    #[error("The counter-gift was not paid, expected {expected}")]
+    CounterGiftNotPaid { expected: String },
+
+    #[error("The returned share of the gift was not paid, expected {expected}")]
+    GiftReturnNotPaid { expected: String },
+
+    #[error("The dissolution of proposal {id} was already requested")]
+    DissolutionAlreadyRequested { id: u64 },
+
+    #[error("The dissolution of proposal {id} was not requested")]
+    DissolutionNotRequested { id: u64 },
+
+    #[error("Invalid deferral: {reason}")]
+    InvalidDeferral { reason: String },
+
+    #[error("The cooling-off window of proposal {id} is still open")]
+    CoolingOffWindowOpen { id: u64 },
+
+    #[error("The cooling-off window of proposal {id} is closed")]
+    CoolingOffWindowClosed { id: u64 },
+
+    #[error(
+        "The proposal has no provisional answer to finalize, current status: {current_status}"
+    )]
+    NoProvisionalAnswer { current_status: String },
+
+    #[error("Migrating from version {version} is not supported, the minimum is {min_version}")]
+    MigrationUnsupported {
+        version: String,
+        min_version: String,
+    },
+
+    #[error("The proposals are still being migrated, execute MigrateProposals until it's done")]
+    MigrationInProgress,
+
+    #[error("There are no proposals left to migrate")]
+    NothingToMigrate,
 }
 
 impl From<semver::Error> for ContractError {
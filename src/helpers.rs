@@ -1,164 +1,260 @@
-use cosmwasm_std::{ensure, Coin, MessageInfo, StdResult, Uint128};
-use std::collections::HashMap;
+use cosmwasm_std::{
+    ensure, instantiate2_address, to_json_binary, Addr, BankMsg, Binary, BlockInfo, Coin,
+    CosmosMsg, Decimal, Deps, Empty, Env, MessageInfo, Order, StdResult, Storage, Timestamp,
+    Uint128, WasmMsg,
+};
+use cw20::{Cw20CoinVerified, Cw20ExecuteMsg};
+use cw721::msg::{Cw721ExecuteMsg, Cw721InstantiateMsg, NftExtensionMsg};
+use cw721::state::Trait;
+use cw721::{
+    DefaultOptionalCollectionExtensionMsg, DefaultOptionalNftExtensionMsg,
+    EmptyOptionalCollectionExtensionMsg, EmptyOptionalNftExtensionMsg,
+};
+use cw_utils::{Duration, Expiration};
+use std::cmp::Ordering;
+use std::collections::{BTreeMap, HashMap, HashSet};
 
 use crate::error::ContractError;
 
-/// Validates that the proposal creation and gift fee are paid with the transaction.
-/// Returns the total amount of fees paid.
+/// The denominator of amounts expressed in basis points.
+pub const BASIS_POINTS_DENOMINATOR: u16 = 10_000;
+/// The instantiate2 salt of the certificate collection.
+const CERTIFICATE_COLLECTION_SALT: &[u8] = b"certificates";
+use crate::proposal::state::{
+    pair_key, Config, FeeRecipient, FeeShare, Gift, GiftDisposition, GiftPolicy, Proposal,
+    ReceiveAs, StakingConfig, VestingSchedule, ACCEPTED_STAGES, ESCROWED_GIFTS, GIFT_CONTRIBUTIONS,
+    TREASURY, WITHDRAWN_FEES,
+};
+
+/// Validates that one of the accepted proposal fees and the gift fee are paid with the
+/// transaction, and that the gift complies with the gift policy. `gift` is the proposal's
+/// native gift including `gift_fee`. Returns the proposal fee that was paid, zero if there's
+/// none, along with the total amount of fees paid.
 pub fn validate_fees_are_paid(
-    proposal_fee: &Coin,
+    deps: Deps,
+    gift_policy: &GiftPolicy,
+    proposal_fees: &[Coin],
     gift_fee: Vec<Coin>,
+    gift: &[Coin],
     info: &MessageInfo,
-) -> Result<Vec<Coin>, ContractError> {
-    let mut total_fees = vec![];
+) -> Result<(Coin, Vec<Coin>), ContractError> {
+    validate_gift_complies_with_policy(deps, gift_policy, &gift_fee, gift)?;
+
+    let non_zero = |coins: Vec<Coin>| -> StdResult<Vec<Coin>> {
+        Ok(aggregate_coins(coins)?
+            .into_iter()
+            .filter(|coin| !coin.amount.is_zero())
+            .collect())
+    };
+    let paid = non_zero(info.funds.clone())?;
+    let gift_fee = non_zero(gift_fee)?;
+
+    // the fee paid is the one making up the funds along with the gift, a proposal is free
+    // without any accepted fee
+    let free = [Coin::default()];
+    let fee_options = match proposal_fees.is_empty() {
+        true => &free[..],
+        false => proposal_fees,
+    };
+    let mut expected = vec![];
+    for fee in fee_options {
+        let total_fees = non_zero([gift_fee.clone(), vec![fee.clone()]].concat())?;
+        if paid == total_fees {
+            return Ok((fee.clone(), total_fees));
+        }
+        expected.push(coins_to_string(&total_fees));
+    }
 
-    let proposal_fee_denom = &proposal_fee.denom;
-    // Check if the proposal fee denom is found in the vector of the gift possible fee denoms
-    if let Some(gf_fee) = gift_fee.iter().find(|fee| &fee.denom == proposal_fee_denom) {
-        // If the gift fee has only one option, check if the user paid the sum of the fees
-        if gift_fee.len() == 1usize {
-            let total_fee_amount = gf_fee.amount.checked_add(proposal_fee.amount)?;
-            let paid_fee_amount = cw_utils::must_pay(info, proposal_fee_denom)?;
+    // none of the fees was paid, find out what's wrong with the funds
+    let is_fee_denom = |denom: &str| fee_options.iter().any(|fee| fee.denom == denom);
+    let is_gift_denom = |denom: &str| gift_fee.iter().any(|coin| coin.denom == denom);
+    ensure!(
+        paid.iter()
+            .all(|coin| is_fee_denom(&coin.denom) || is_gift_denom(&coin.denom)),
+        ContractError::ExtraFundsSent
+    );
+    // the gift is paid separately in the denoms no fee can be paid in
+    ensure!(
+        gift_fee
+            .iter()
+            .filter(|coin| !is_fee_denom(&coin.denom))
+            .all(|coin| paid.contains(coin)),
+        ContractError::GiftFeeNotPaid
+    );
 
+    Err(ContractError::InvalidProposalCreationFee {
+        amount: coins_to_string(&paid),
+        expected: expected.join(" or "),
+    })
+}
+
+/// Validates the coins added to a proposal's gift against the gift policy. The minimum applies
+/// to the added coins, the maximums to the whole gift and the escrow caps to the gifts escrowed
+/// across all proposals along with the added coins.
+fn validate_gift_complies_with_policy(
+    deps: Deps,
+    gift_policy: &GiftPolicy,
+    added: &[Coin],
+    gift: &[Coin],
+) -> Result<(), ContractError> {
+    validate_gift_limits(gift_policy, added, gift)?;
+
+    for coin in added {
+        if let Some(cap) = gift_policy
+            .escrow_caps
+            .iter()
+            .find(|cap| cap.denom == coin.denom)
+        {
+            let escrowed = ESCROWED_GIFTS
+                .may_load(deps.storage, &coin.denom)?
+                .unwrap_or_default()
+                .checked_add(coin.amount)?;
             ensure!(
-                paid_fee_amount == total_fee_amount,
-                ContractError::InvalidProposalCreationFee {
-                    amount: paid_fee_amount,
-                    expected: total_fee_amount,
+                escrowed <= cap.amount,
+                ContractError::EscrowCapExceeded {
+                    denom: coin.denom.clone(),
+                    escrowed,
+                    cap: cap.amount,
                 }
             );
+        }
+    }
 
-            total_fees.push(Coin {
-                denom: proposal_fee_denom.clone(),
-                amount: total_fee_amount,
-            });
-        } else {
-            // If the gift fee has multiple denom besides proposal_fee_denom, check if the user paid the proposal creation fee
-            let paid_fee_in_proposal_denom =
-                get_paid_proposal_fee_amount(info, proposal_fee_denom)?;
+    Ok(())
+}
 
+/// Validates the coins added to a gift against the allowed denoms and the limits of the gift
+/// policy, which don't depend on what the contract escrows.
+fn validate_gift_limits(
+    gift_policy: &GiftPolicy,
+    added: &[Coin],
+    gift: &[Coin],
+) -> Result<(), ContractError> {
+    for coin in added {
+        ensure!(
+            gift_policy.allowed_denoms.is_empty()
+                || gift_policy.allowed_denoms.contains(&coin.denom),
+            ContractError::GiftDenomNotAllowed {
+                denom: coin.denom.clone(),
+            }
+        );
+
+        if let Some(min) = gift_policy
+            .limits
+            .iter()
+            .find(|limit| limit.denom == coin.denom)
+            .and_then(|limit| limit.min)
+        {
             ensure!(
-                paid_fee_in_proposal_denom >= proposal_fee.amount,
-                ContractError::InvalidProposalCreationFee {
-                    amount: paid_fee_in_proposal_denom,
-                    expected: proposal_fee.amount,
+                coin.amount >= min,
+                ContractError::GiftAmountTooLow {
+                    denom: coin.denom.clone(),
+                    amount: coin.amount,
+                    min,
                 }
             );
+        }
+    }
 
-            total_fees.push(Coin {
-                denom: proposal_fee_denom.clone(),
-                amount: proposal_fee.amount,
-            });
+    for coin in gift {
+        if let Some(max) = gift_policy
+            .limits
+            .iter()
+            .find(|limit| limit.denom == coin.denom)
+            .and_then(|limit| limit.max)
+        {
+            ensure!(
+                coin.amount <= max,
+                ContractError::GiftAmountTooHigh {
+                    denom: coin.denom.clone(),
+                    amount: coin.amount,
+                    max,
+                }
+            );
+        }
+    }
 
-            // Check if the user paid the gift fee in proposal_fee_denom
-            let mut gf_fee_paid_in_proposal_fee_denom = Uint128::zero();
-            let gf_fee_paid = gift_fee
-                .iter()
-                .filter(|fee| &fee.denom == proposal_fee_denom)
-                .any(|fee| {
-                    gf_fee_paid_in_proposal_fee_denom = info
-                        .funds
-                        .iter()
-                        .filter(|fund| fund.denom == fee.denom)
-                        .map(|fund| fund.amount)
-                        .try_fold(Uint128::zero(), |acc, amount| acc.checked_add(amount))
-                        .unwrap_or(Uint128::zero());
-
-                    gf_fee_paid_in_proposal_fee_denom = gf_fee_paid_in_proposal_fee_denom
-                        .checked_sub(proposal_fee.amount)
-                        .unwrap_or(Uint128::zero());
-                    total_fees.push(Coin {
-                        denom: fee.denom.clone(),
-                        amount: gf_fee_paid_in_proposal_fee_denom,
-                    });
-
-                    gf_fee_paid_in_proposal_fee_denom == fee.amount
-                });
-
-            let remaining_funds = paid_fee_in_proposal_denom
-                .checked_sub(gf_fee_paid_in_proposal_fee_denom)?
-                .checked_sub(proposal_fee.amount)?;
-
-            ensure!(remaining_funds.is_zero(), ContractError::ExtraFundsSent);
-            ensure!(gf_fee_paid, ContractError::GiftFeeNotPaid);
-
-            // Check if the user paid the gift fee in any other of the allowed denoms
-            let gf_fee_paid = gift_fee
-                .iter()
-                .filter(|fee| &fee.denom != proposal_fee_denom)
-                .all(|fee| {
-                    let paid_fee_amount = info
-                        .funds
-                        .iter()
-                        .filter(|fund| fund.denom == fee.denom)
-                        .map(|fund| fund.amount)
-                        .try_fold(Uint128::zero(), |acc, amount| acc.checked_add(amount))
-                        .unwrap_or(Uint128::zero());
+    if let Some(max) = gift_policy.max_denoms {
+        let count = gift.len() as u32;
+        ensure!(
+            count <= max,
+            ContractError::TooManyGiftDenoms { count, max }
+        );
+    }
 
-                    total_fees.push(Coin {
-                        denom: fee.denom.clone(),
-                        amount: paid_fee_amount,
-                    });
+    Ok(())
+}
 
-                    paid_fee_amount == fee.amount
-                });
+/// Validates the staking config set by the owner, the validator must exist.
+pub(crate) fn validate_staking_config(
+    deps: Deps,
+    staking: &StakingConfig,
+) -> Result<(), ContractError> {
+    ensure!(
+        staking.unbonding_period > 0,
+        ContractError::InvalidStakingConfig {
+            reason: "the unbonding period can't be zero".to_string(),
+        }
+    );
+    ensure!(
+        deps.querier.query_validator(&staking.validator)?.is_some(),
+        ContractError::InvalidStakingConfig {
+            reason: format!("unknown validator {}", staking.validator),
+        }
+    );
 
-            ensure!(gf_fee_paid, ContractError::GiftFeeNotPaid);
+    Ok(())
+}
 
-            total_fees = aggregate_coins(total_fees)?;
-        }
-    } else {
-        // If the proposal fee denom is not found in the vector of the gift possible fee denoms,
-        // check if the user paid the proposal creation fee and the gift fee separately
-        let paid_proposal_fee_amount = get_paid_proposal_fee_amount(info, proposal_fee_denom)?;
+/// Validates the gift policy set by the owner.
+pub(crate) fn validate_gift_policy(
+    deps: Deps,
+    gift_policy: &GiftPolicy,
+) -> Result<(), ContractError> {
+    for address in gift_policy
+        .allowed_cw20_tokens
+        .iter()
+        .chain(&gift_policy.allowed_cw721_collections)
+    {
+        deps.api.addr_validate(address.as_str())?;
+    }
 
+    let mut denoms = HashSet::new();
+    for limit in &gift_policy.limits {
         ensure!(
-            paid_proposal_fee_amount == proposal_fee.amount,
-            ContractError::InvalidProposalCreationFee {
-                amount: paid_proposal_fee_amount,
-                expected: proposal_fee.amount,
+            denoms.insert(&limit.denom),
+            ContractError::InvalidGiftPolicy {
+                reason: format!("duplicate limit for {}", limit.denom),
             }
         );
+        if let (Some(min), Some(max)) = (limit.min, limit.max) {
+            ensure!(
+                min <= max,
+                ContractError::InvalidGiftPolicy {
+                    reason: format!("the minimum of {} is above its maximum", limit.denom),
+                }
+            );
+        }
+    }
 
-        total_fees.push(Coin {
-            denom: proposal_fee_denom.clone(),
-            amount: paid_proposal_fee_amount,
-        });
-
-        let gf_fee_paid = gift_fee.iter().all(|fee| {
-            let paid_fee_amount = info
-                .funds
-                .iter()
-                .filter(|fund| fund.denom == fee.denom)
-                .map(|fund| fund.amount)
-                .try_fold(Uint128::zero(), |acc, amount| acc.checked_add(amount))
-                .unwrap_or(Uint128::zero());
-
-            total_fees.push(Coin {
-                denom: fee.denom.clone(),
-                amount: paid_fee_amount,
-            });
-
-            paid_fee_amount == fee.amount
-        });
-
-        ensure!(gf_fee_paid, ContractError::GiftFeeNotPaid);
+    let mut denoms = HashSet::new();
+    for cap in &gift_policy.escrow_caps {
+        ensure!(
+            denoms.insert(&cap.denom),
+            ContractError::InvalidGiftPolicy {
+                reason: format!("duplicate escrow cap for {}", cap.denom),
+            }
+        );
     }
 
-    Ok(aggregate_coins(total_fees)?)
-}
+    ensure!(
+        gift_policy.max_denoms != Some(0),
+        ContractError::InvalidGiftPolicy {
+            reason: "the maximum number of denoms can't be zero".to_string(),
+        }
+    );
 
-/// gets the proposal creation fee paid by the user
-fn get_paid_proposal_fee_amount(
-    info: &MessageInfo,
-    proposal_fee_denom: &String,
-) -> Result<Uint128, ContractError> {
-    Ok(info
-        .funds
-        .iter()
-        .filter(|fund| &fund.denom == proposal_fee_denom)
-        .map(|fund| fund.amount)
-        .try_fold(Uint128::zero(), |acc, amount| acc.checked_add(amount))?)
+    Ok(())
 }
 
 /// Validates that no additional funds besides the fees for the proposal creation were sent with the transaction.
@@ -205,6 +301,1125 @@ pub fn aggregate_coins(coins: Vec<Coin>) -> StdResult<Vec<Coin>> {
     Ok(aggregated_coins)
 }
 
+/// Formats a list of coins as a comma separated string, to be used in attributes.
+pub(crate) fn coins_to_string(coins: &[Coin]) -> String {
+    coins
+        .iter()
+        .map(|coin| coin.to_string())
+        .collect::<Vec<_>>()
+        .join(",")
+}
+
+/// Converts native coins into the parts of a gift.
+pub(crate) fn native_gift(coins: Vec<Coin>) -> Vec<Gift> {
+    coins.into_iter().map(Gift::Native).collect()
+}
+
+/// Returns the native coins of a gift.
+pub(crate) fn native_coins(gift: &[Gift]) -> Vec<Coin> {
+    gift.iter()
+        .filter_map(|part| match part {
+            Gift::Native(coin) => Some(coin.clone()),
+            _ => None,
+        })
+        .collect()
+}
+
+/// Aggregates the parts of a gift, summing up the amounts of the same native denom or CW20
+/// token. Native coins come first, sorted by denom, followed by the CW20 tokens and the NFTs.
+pub fn aggregate_gift(gift: Vec<Gift>) -> StdResult<Vec<Gift>> {
+    let mut coins: Vec<Coin> = vec![];
+    let mut tokens: BTreeMap<Addr, Uint128> = BTreeMap::new();
+    let mut nfts: Vec<Gift> = vec![];
+    for part in gift {
+        match part {
+            Gift::Native(coin) => coins.push(coin),
+            Gift::Cw20(token) => {
+                let amount = tokens.entry(token.address).or_default();
+                *amount = amount.checked_add(token.amount)?;
+            }
+            nft @ Gift::Cw721 { .. } => nfts.push(nft),
+        }
+    }
+
+    Ok(native_gift(aggregate_coins(coins)?)
+        .into_iter()
+        .chain(
+            tokens
+                .into_iter()
+                .map(|(address, amount)| Gift::Cw20(Cw20CoinVerified { address, amount })),
+        )
+        .chain(nfts)
+        .collect())
+}
+
+/// Formats a gift as a comma separated string, to be used in attributes.
+pub(crate) fn gift_to_string(gift: &[Gift]) -> String {
+    gift.iter()
+        .map(|part| part.to_string())
+        .collect::<Vec<_>>()
+        .join(",")
+}
+
+/// Builds the messages transferring a gift to the recipient, i.e. a bank send with the native
+/// coins, a CW20 transfer per token and a CW721 transfer per NFT.
+pub(crate) fn gift_transfer_msgs(gift: &[Gift], recipient: &Addr) -> StdResult<Vec<CosmosMsg>> {
+    let mut messages: Vec<CosmosMsg> = vec![];
+    let coins = native_coins(gift)
+        .into_iter()
+        .filter(|coin| !coin.amount.is_zero())
+        .collect::<Vec<_>>();
+    if !coins.is_empty() {
+        messages.push(CosmosMsg::Bank(BankMsg::Send {
+            to_address: recipient.to_string(),
+            amount: coins,
+        }));
+    }
+
+    for part in gift {
+        match part {
+            Gift::Native(_) => {}
+            Gift::Cw20(token) => {
+                if token.amount.is_zero() {
+                    continue;
+                }
+                messages.push(CosmosMsg::Wasm(WasmMsg::Execute {
+                    contract_addr: token.address.to_string(),
+                    msg: to_json_binary(&Cw20ExecuteMsg::Transfer {
+                        recipient: recipient.to_string(),
+                        amount: token.amount,
+                    })?,
+                    funds: vec![],
+                }));
+            }
+            Gift::Cw721 { contract, token_id } => {
+                messages.push(CosmosMsg::Wasm(WasmMsg::Execute {
+                    contract_addr: contract.to_string(),
+                    msg: to_json_binary(&Cw721ExecuteMsg::<
+                        EmptyOptionalNftExtensionMsg,
+                        EmptyOptionalCollectionExtensionMsg,
+                        Empty,
+                    >::TransferNft {
+                        recipient: recipient.to_string(),
+                        token_id: token_id.clone(),
+                    })?,
+                    funds: vec![],
+                }));
+            }
+        }
+    }
+
+    Ok(messages)
+}
+
+/// Builds the messages of several gift transfers, given as `(recipient, gift)` pairs.
+pub(crate) fn gift_transfers_msgs(transfers: &[(Addr, Vec<Gift>)]) -> StdResult<Vec<CosmosMsg>> {
+    let mut messages = vec![];
+    for (recipient, gift) in transfers {
+        messages.extend(gift_transfer_msgs(gift, recipient)?);
+    }
+    Ok(messages)
+}
+
+/// The refund of a proposal's fee and gift.
+pub(crate) struct Refund {
+    /// The messages refunding the proposal fee
+    pub messages: Vec<CosmosMsg>,
+    /// The transfers refunding the gift, to be built with [gift_transfers_msgs]
+    pub transfers: Vec<(Addr, Vec<Gift>)>,
+    /// The fee and the part of the gift refunded to the proposer
+    pub proposer: Vec<Gift>,
+    /// The contributions refunded to each contributor
+    pub contributors: Vec<(Addr, Vec<Coin>)>,
+}
+
+/// Computes the refund of the proposal fee and gift. Every contributor gets their own
+/// contribution back, the proposer gets the fee and the rest of the gift.
+pub(crate) fn refund_proposer(storage: &dyn Storage, proposal: &Proposal) -> StdResult<Refund> {
+    let contributors = load_contributions(storage, proposal.id)?;
+    let proposer_gift = proposer_share(&proposal.gift, &contributors)?;
+
+    let mut total_refund: Vec<Gift> = vec![];
+    let mut messages: Vec<CosmosMsg> = vec![];
+    if !proposal.fee.amount.is_zero() {
+        total_refund.push(Gift::Native(proposal.fee.clone()));
+        messages.push(CosmosMsg::Bank(BankMsg::Send {
+            to_address: proposal.proposer.to_string(),
+            amount: vec![proposal.fee.clone()],
+        }));
+    }
+
+    total_refund.extend(proposer_gift.clone());
+    let mut transfers = vec![(proposal.proposer.clone(), proposer_gift)];
+    for (contributor, contribution) in &contributors {
+        transfers.push((contributor.clone(), native_gift(contribution.clone())));
+    }
+
+    Ok(Refund {
+        messages,
+        transfers,
+        proposer: aggregate_gift(total_refund)?,
+        contributors,
+    })
+}
+
+/// Loads the contributions made to a proposal's gift.
+fn load_contributions(storage: &dyn Storage, id: u64) -> StdResult<Vec<(Addr, Vec<Coin>)>> {
+    GIFT_CONTRIBUTIONS
+        .prefix(id)
+        .range(storage, None, None, Order::Ascending)
+        .collect()
+}
+
+/// Returns the part of a refunded gift going back to the proposer, i.e. the gift without the
+/// contributions, which go back to their contributors.
+fn proposer_share(gift: &[Gift], contributors: &[(Addr, Vec<Coin>)]) -> StdResult<Vec<Gift>> {
+    let contributed = aggregate_coins(
+        contributors
+            .iter()
+            .flat_map(|(_, contribution)| contribution.clone())
+            .collect(),
+    )?;
+
+    let mut proposer_gift = vec![];
+    for part in gift {
+        let Gift::Native(coin) = part else {
+            proposer_gift.push(part.clone());
+            continue;
+        };
+        let contributed_amount = contributed
+            .iter()
+            .find(|contribution| contribution.denom == coin.denom)
+            .map(|contribution| contribution.amount)
+            .unwrap_or_default();
+        let amount = coin.amount.checked_sub(contributed_amount)?;
+        if !amount.is_zero() {
+            proposer_gift.push(Gift::Native(Coin {
+                denom: coin.denom.clone(),
+                amount,
+            }));
+        }
+    }
+
+    Ok(proposer_gift)
+}
+
+/// Formats the contributions to a gift as a semicolon separated list of `address:coins`, to be
+/// used in attributes.
+pub(crate) fn contributions_to_string(contributions: &[(Addr, Vec<Coin>)]) -> String {
+    contributions
+        .iter()
+        .map(|(contributor, contribution)| {
+            format!("{contributor}:{}", coins_to_string(contribution))
+        })
+        .collect::<Vec<_>>()
+        .join(";")
+}
+
+/// Returns whether the given duration is zero.
+pub(crate) fn is_zero_duration(duration: &Duration) -> bool {
+    matches!(duration, Duration::Height(0) | Duration::Time(0))
+}
+
+/// Validates the proposal durations of the config, i.e. that they are not zero and that the
+/// default duration doesn't exceed the maximum one.
+pub(crate) fn validate_proposal_durations(
+    config: &Config,
+    block: &BlockInfo,
+) -> Result<(), ContractError> {
+    for duration in [
+        config.default_proposal_duration,
+        config.max_proposal_duration,
+    ]
+    .iter()
+    .flatten()
+    {
+        ensure!(
+            !is_zero_duration(duration),
+            ContractError::InvalidExpiration {
+                reason: "the proposal duration cannot be zero".to_string(),
+            }
+        );
+    }
+
+    if let (Some(default), Some(max)) = (
+        config.default_proposal_duration,
+        config.max_proposal_duration,
+    ) {
+        ensure!(
+            matches!(
+                default.after(block).partial_cmp(&max.after(block)),
+                Some(Ordering::Less | Ordering::Equal)
+            ),
+            ContractError::InvalidExpiration {
+                reason: "the default proposal duration must be of the same kind and not exceed the maximum one".to_string(),
+            }
+        );
+    }
+
+    Ok(())
+}
+
+/// Computes the expiration of a new proposal. If the proposer didn't provide one, the default
+/// proposal duration is used, falling back to the maximum one or never expiring.
+pub(crate) fn compute_proposal_expiration(
+    config: &Config,
+    expires_at: Option<Expiration>,
+    block: &BlockInfo,
+) -> Result<Expiration, ContractError> {
+    let expires_at = expires_at.unwrap_or_else(|| {
+        config
+            .default_proposal_duration
+            .or(config.max_proposal_duration)
+            .map(|duration| duration.after(block))
+            .unwrap_or_default()
+    });
+
+    ensure!(
+        !expires_at.is_expired(block),
+        ContractError::InvalidExpiration {
+            reason: "the expiration is in the past".to_string(),
+        }
+    );
+
+    if let Some(max_proposal_duration) = config.max_proposal_duration {
+        let max_expiration = max_proposal_duration.after(block);
+        ensure!(
+            matches!(
+                expires_at.partial_cmp(&max_expiration),
+                Some(Ordering::Less | Ordering::Equal)
+            ),
+            ContractError::InvalidExpiration {
+                reason: format!("the expiration must not be later than {max_expiration}"),
+            }
+        );
+    }
+
+    Ok(expires_at)
+}
+
+/// Validates the moment a new proposal is revealed to the receiver, i.e. that it is in the
+/// future and before the proposal's expiration.
+pub(crate) fn validate_reveal_at(
+    reveal_at: &Expiration,
+    expires_at: &Expiration,
+    block: &BlockInfo,
+) -> Result<(), ContractError> {
+    ensure!(
+        !reveal_at.is_expired(block) && *reveal_at != Expiration::Never {},
+        ContractError::InvalidRevealTime {
+            reason: "the reveal time must be in the future".to_string(),
+        }
+    );
+
+    ensure!(
+        matches!(reveal_at.partial_cmp(expires_at), Some(Ordering::Less)),
+        ContractError::InvalidRevealTime {
+            reason: format!("the reveal time must be before {expires_at}"),
+        }
+    );
+
+    Ok(())
+}
+
+/// Validates the decision deadline requested when deferring a proposal, i.e. that it is in the
+/// future and not later than the proposal's expiration.
+pub(crate) fn validate_deferral(
+    until: &Expiration,
+    expires_at: &Expiration,
+    block: &BlockInfo,
+) -> Result<(), ContractError> {
+    ensure!(
+        !until.is_expired(block) && *until != Expiration::Never {},
+        ContractError::InvalidDeferral {
+            reason: "the deadline must be in the future".to_string(),
+        }
+    );
+
+    ensure!(
+        matches!(
+            until.partial_cmp(expires_at),
+            Some(Ordering::Less | Ordering::Equal)
+        ),
+        ContractError::InvalidDeferral {
+            reason: format!("the deadline must not be later than {expires_at}"),
+        }
+    );
+
+    Ok(())
+}
+
+/// Validates the relationship stages of the config, i.e. that they are not empty nor duplicated.
+pub(crate) fn validate_stages(stages: &[String]) -> Result<(), ContractError> {
+    let mut seen = HashSet::new();
+    for stage in stages {
+        ensure!(
+            !stage.trim().is_empty(),
+            ContractError::InvalidStages {
+                reason: "stage names cannot be empty".to_string(),
+            }
+        );
+        ensure!(
+            seen.insert(stage),
+            ContractError::InvalidStages {
+                reason: format!("duplicated stage {stage}"),
+            }
+        );
+    }
+
+    Ok(())
+}
+
+/// Resolves the stage of a new proposal between the proposer and receiver, defaulting to the
+/// first stage. A proposal for a stage other than the first one requires an accepted proposal
+/// for the previous stage between the same pair.
+pub(crate) fn resolve_stage(
+    storage: &dyn Storage,
+    config: &Config,
+    proposer: &Addr,
+    receiver: &Addr,
+    stage: Option<String>,
+) -> Result<Option<String>, ContractError> {
+    let Some(stage) = stage.or_else(|| config.stages.first().cloned()) else {
+        return Ok(None);
+    };
+
+    let index = config
+        .stages
+        .iter()
+        .position(|s| s == &stage)
+        .ok_or_else(|| ContractError::UnknownStage {
+            stage: stage.clone(),
+        })?;
+
+    if index > 0 {
+        let previous_stage = &config.stages[index - 1];
+        let (a, b) = pair_key(proposer, receiver);
+        ensure!(
+            ACCEPTED_STAGES.has(storage, (a, b, previous_stage)),
+            ContractError::PreviousStageNotAccepted {
+                stage,
+                previous_stage: previous_stage.clone(),
+            }
+        );
+    }
+
+    Ok(Some(stage))
+}
+
+/// Computes the share of the gift, in basis points, the receiver returns to the proposer when
+/// dissolving an accepted proposal. Only the native coins of the gift are returned.
+pub(crate) fn gift_return_share(gift: &[Gift], gift_return_bps: u16) -> Vec<Coin> {
+    native_coins(gift)
+        .iter()
+        .map(|coin| Coin {
+            denom: coin.denom.clone(),
+            amount: coin
+                .amount
+                .multiply_ratio(gift_return_bps, BASIS_POINTS_DENOMINATOR),
+        })
+        .filter(|coin| !coin.amount.is_zero())
+        .collect()
+}
+
+/// Validates the receiver's choice of what happens to the gift of an accepted proposal.
+pub(crate) fn validate_gift_disposition(
+    deps: Deps,
+    disposition: &GiftDisposition,
+) -> Result<(), ContractError> {
+    match disposition {
+        GiftDisposition::Keep | GiftDisposition::Decline => {}
+        GiftDisposition::SendTo { address } => {
+            deps.api.addr_validate(address.as_str())?;
+        }
+        GiftDisposition::Split { address, share_bps } => {
+            deps.api.addr_validate(address.as_str())?;
+            ensure!(
+                *share_bps > 0 && *share_bps <= BASIS_POINTS_DENOMINATOR,
+                ContractError::InvalidGiftDisposition {
+                    reason: format!(
+                        "the split share must be between 1 and {BASIS_POINTS_DENOMINATOR} basis points"
+                    ),
+                }
+            );
+        }
+    }
+
+    Ok(())
+}
+
+/// Validates the receiver's choice of swapping the gift into another denom on acceptance.
+pub(crate) fn validate_receive_as(
+    config: &Config,
+    proposal: &Proposal,
+    receive_as: &ReceiveAs,
+) -> Result<(), ContractError> {
+    ensure!(config.swap_pool.is_some(), ContractError::SwapNotEnabled);
+
+    let reason = if receive_as.denom.is_empty() {
+        Some("the denom can't be empty")
+    } else if proposal
+        .gift_disposition
+        .as_ref()
+        .is_some_and(|disposition| disposition != &GiftDisposition::Keep)
+    {
+        Some("only a gift kept by the receiver can be swapped")
+    } else if proposal.vesting.is_some() {
+        Some("a vesting gift can't be swapped")
+    } else if proposal.staking.is_some() {
+        Some("a staked gift can't be swapped")
+    } else {
+        None
+    };
+    if let Some(reason) = reason {
+        return Err(ContractError::InvalidSwap {
+            reason: reason.to_string(),
+        });
+    }
+
+    Ok(())
+}
+
+/// Routes the gift of an accepted proposal according to the receiver's disposition. Returns
+/// the recipients along with their part of the gift, NFTs are never split. A declined gift is
+/// refunded like a rejected proposal's, each contributor getting their contribution back.
+pub(crate) fn route_gift(
+    storage: &dyn Storage,
+    proposal: &Proposal,
+    gift: &[Gift],
+) -> StdResult<Vec<(Addr, Vec<Gift>)>> {
+    let disposition = proposal
+        .gift_disposition
+        .clone()
+        .unwrap_or(GiftDisposition::Keep);
+    let routes = match disposition {
+        GiftDisposition::Keep => vec![(proposal.receiver.clone(), gift.to_vec())],
+        GiftDisposition::SendTo { address } => vec![(address, gift.to_vec())],
+        GiftDisposition::Decline => {
+            let contributors = load_contributions(storage, proposal.id)?;
+            let mut routes = vec![(
+                proposal.proposer.clone(),
+                proposer_share(gift, &contributors)?,
+            )];
+            routes.extend(
+                contributors
+                    .into_iter()
+                    .map(|(contributor, contribution)| (contributor, native_gift(contribution))),
+            );
+            routes
+        }
+        GiftDisposition::Split { address, share_bps } => {
+            let mut kept = vec![];
+            let mut shared = vec![];
+            for part in gift {
+                let Some(amount) = part.amount() else {
+                    kept.push(part.clone());
+                    continue;
+                };
+                let share = amount.multiply_ratio(share_bps, BASIS_POINTS_DENOMINATOR);
+                shared.push(part.with_amount(share));
+                kept.push(part.with_amount(amount - share));
+            }
+            vec![(proposal.receiver.clone(), kept), (address, shared)]
+        }
+    };
+
+    Ok(routes
+        .into_iter()
+        .map(|(recipient, gift)| {
+            let gift: Vec<Gift> = gift
+                .into_iter()
+                .filter(|part| part.amount().is_none_or(|amount| !amount.is_zero()))
+                .collect();
+            (recipient, gift)
+        })
+        .filter(|(_, gift)| !gift.is_empty())
+        .collect())
+}
+
+/// Formats the routed parts of a gift as a semicolon separated list of `address:gift`, to be
+/// used in attributes.
+pub(crate) fn routes_to_string(routes: &[(Addr, Vec<Gift>)]) -> String {
+    routes
+        .iter()
+        .map(|(recipient, gift)| format!("{recipient}:{}", gift_to_string(gift)))
+        .collect::<Vec<_>>()
+        .join(";")
+}
+
+/// Validates the gift options of a new proposal, returning each of them aggregated.
+pub(crate) fn validate_gift_options(
+    gift_options: Vec<Vec<Gift>>,
+) -> Result<Vec<Vec<Gift>>, ContractError> {
+    ensure!(
+        gift_options.len() != 1,
+        ContractError::InvalidGiftOptions {
+            reason: "there must be at least two options to pick from".to_string(),
+        }
+    );
+
+    let gift_options = gift_options
+        .into_iter()
+        .map(aggregate_gift)
+        .collect::<StdResult<Vec<_>>>()?;
+    ensure!(
+        gift_options.iter().all(|option| !is_empty_gift(option)),
+        ContractError::InvalidGiftOptions {
+            reason: "the options can't be empty".to_string(),
+        }
+    );
+
+    Ok(gift_options)
+}
+
+/// Returns whether a gift holds nothing, i.e. no NFT and only zero amounts.
+fn is_empty_gift(gift: &[Gift]) -> bool {
+    gift.iter()
+        .all(|part| part.amount().is_some_and(|amount| amount.is_zero()))
+}
+
+/// Validates the gift option picked by the receiver, which is required if the proposal offers
+/// gift options.
+pub(crate) fn validate_gift_option(
+    proposal: &Proposal,
+    gift_option: Option<u32>,
+) -> Result<(), ContractError> {
+    let count = proposal.gift_options.len();
+    let reason = match gift_option {
+        None if count > 0 => Some("one of the gift options must be picked".to_string()),
+        Some(_) if count == 0 => Some(format!("proposal {} has no gift options", proposal.id)),
+        Some(option) if option as usize >= count => Some(format!(
+            "option {option} doesn't exist, there are {count} options"
+        )),
+        Some(option) if is_empty_gift(&proposal.gift_options[option as usize]) => {
+            Some(format!("option {option} is empty"))
+        }
+        _ => None,
+    };
+    if let Some(reason) = reason {
+        return Err(ContractError::InvalidGiftOptions { reason });
+    }
+
+    Ok(())
+}
+
+/// Returns the gift options the receiver didn't pick, aggregated.
+pub(crate) fn unpicked_gift_options(proposal: &Proposal) -> StdResult<Vec<Gift>> {
+    aggregate_gift(
+        proposal
+            .gift_options
+            .iter()
+            .enumerate()
+            .filter(|(index, _)| proposal.gift_option != Some(*index as u32))
+            .flat_map(|(_, option)| option.clone())
+            .collect(),
+    )
+}
+
+/// Removes a part of an aggregated gift from it, e.g. the gift options the receiver didn't pick.
+pub(crate) fn subtract_gift(gift: &[Gift], removed: &[Gift]) -> StdResult<Vec<Gift>> {
+    let mut remaining = vec![];
+    for part in gift {
+        let removed_part = removed.iter().find(|removed| match (part, removed) {
+            (Gift::Native(coin), Gift::Native(removed)) => coin.denom == removed.denom,
+            (Gift::Cw20(token), Gift::Cw20(removed)) => token.address == removed.address,
+            _ => part == *removed,
+        });
+        match (part.amount(), removed_part.and_then(Gift::amount)) {
+            (Some(amount), Some(removed_amount)) => {
+                let amount = amount.checked_sub(removed_amount)?;
+                if !amount.is_zero() {
+                    remaining.push(part.with_amount(amount));
+                }
+            }
+            _ if removed_part.is_none() => remaining.push(part.clone()),
+            // a removed NFT
+            _ => {}
+        }
+    }
+
+    Ok(remaining)
+}
+
+/// Validates the gift fee set by the owner, which can't exceed the whole gift.
+pub(crate) fn validate_gift_fee(gift_fee_bps: Decimal) -> Result<(), ContractError> {
+    ensure!(
+        gift_fee_bps <= Decimal::from_ratio(BASIS_POINTS_DENOMINATOR, 1u16),
+        ContractError::InvalidGiftFee {
+            reason: format!("the fee can't exceed {BASIS_POINTS_DENOMINATOR} basis points"),
+        }
+    );
+
+    Ok(())
+}
+
+/// Computes the fee charged on each fungible part of a gift, rounded down. NFTs are free.
+pub(crate) fn compute_gift_fee(
+    gift: &[Gift],
+    gift_fee_bps: Decimal,
+) -> Result<Vec<Gift>, ContractError> {
+    // the fee is validated not to exceed the denominator, the rate is at most one
+    let rate = gift_fee_bps * Decimal::from_ratio(1u16, BASIS_POINTS_DENOMINATOR);
+
+    let mut fee = vec![];
+    for part in gift {
+        if let Some(amount) = part.amount() {
+            let amount = amount.checked_mul_floor(rate)?;
+            if !amount.is_zero() {
+                fee.push(part.with_amount(amount));
+            }
+        }
+    }
+
+    Ok(fee)
+}
+
+/// Validates the split of the fees, the weights of the shares adding up to a full share.
+pub(crate) fn validate_fee_shares(
+    deps: Deps,
+    fee_shares: &[FeeShare],
+) -> Result<(), ContractError> {
+    if fee_shares.is_empty() {
+        return Ok(());
+    }
+
+    let mut total_weight = 0u32;
+    for share in fee_shares {
+        if let FeeRecipient::Address { address } = &share.recipient {
+            deps.api.addr_validate(address.as_str())?;
+        }
+        ensure!(
+            share.weight_bps > 0,
+            ContractError::InvalidFeeShares {
+                reason: "the weights can't be zero".to_string(),
+            }
+        );
+        total_weight += u32::from(share.weight_bps);
+    }
+    ensure!(
+        total_weight == u32::from(BASIS_POINTS_DENOMINATOR),
+        ContractError::InvalidFeeShares {
+            reason: format!(
+                "the weights must add up to {BASIS_POINTS_DENOMINATOR} basis points, got {total_weight}"
+            ),
+        }
+    );
+
+    Ok(())
+}
+
+/// Splits the fungible fees across the shares, rounding down. The rounding remainder of each
+/// denom goes to the first share.
+pub(crate) fn split_fees(
+    fees: &[Gift],
+    fee_shares: &[FeeShare],
+) -> StdResult<Vec<(FeeRecipient, Vec<Gift>)>> {
+    let mut splits: Vec<(FeeRecipient, Vec<Gift>)> = fee_shares
+        .iter()
+        .map(|share| (share.recipient.clone(), vec![]))
+        .collect();
+    if splits.is_empty() {
+        return Ok(splits);
+    }
+
+    for fee in fees {
+        let Some(amount) = fee.amount() else {
+            continue;
+        };
+        let mut remainder = amount;
+        let mut amounts = vec![];
+        for share in fee_shares {
+            let part = amount.multiply_ratio(share.weight_bps, BASIS_POINTS_DENOMINATOR);
+            remainder = remainder.checked_sub(part)?;
+            amounts.push(part);
+        }
+        amounts[0] = amounts[0].checked_add(remainder)?;
+        for ((_, split), part) in splits.iter_mut().zip(amounts) {
+            if !part.is_zero() {
+                split.push(fee.with_amount(part));
+            }
+        }
+    }
+
+    Ok(splits
+        .into_iter()
+        .filter(|(_, split)| !split.is_empty())
+        .collect())
+}
+
+/// Builds the messages paying a share of the fees, burning the native coins and CW20 tokens
+/// for [FeeRecipient::Burn].
+pub(crate) fn fee_share_msgs(recipient: &FeeRecipient, fees: &[Gift]) -> StdResult<Vec<CosmosMsg>> {
+    let FeeRecipient::Address { address } = recipient else {
+        let mut messages: Vec<CosmosMsg> = vec![];
+        let coins = native_coins(fees)
+            .into_iter()
+            .filter(|coin| !coin.amount.is_zero())
+            .collect::<Vec<_>>();
+        if !coins.is_empty() {
+            messages.push(CosmosMsg::Bank(BankMsg::Burn { amount: coins }));
+        }
+        for fee in fees {
+            if let Gift::Cw20(token) = fee {
+                messages.push(CosmosMsg::Wasm(WasmMsg::Execute {
+                    contract_addr: token.address.to_string(),
+                    msg: to_json_binary(&Cw20ExecuteMsg::Burn {
+                        amount: token.amount,
+                    })?,
+                    funds: vec![],
+                }));
+            }
+        }
+        return Ok(messages);
+    };
+
+    gift_transfer_msgs(fees, address)
+}
+
+/// Formats the split fees as a semicolon separated list of `recipient:fees`, to be used in
+/// attributes.
+pub(crate) fn fee_shares_to_string(splits: &[(FeeRecipient, Vec<Gift>)]) -> String {
+    splits
+        .iter()
+        .map(|(recipient, fees)| format!("{recipient}:{}", gift_to_string(fees)))
+        .collect::<Vec<_>>()
+        .join(";")
+}
+
+/// Credits the fungible fees to the treasury, skipping zero amounts, by native denom or CW20 token address.
+pub(crate) fn credit_treasury(storage: &mut dyn Storage, fees: &[Gift]) -> StdResult<()> {
+    for fee in fees {
+        if let (Some(denom), Some(amount)) = (fee.denom(), fee.amount()) {
+            if amount.is_zero() {
+                continue;
+            }
+            TREASURY.update(storage, &denom, |accrued| -> StdResult<_> {
+                Ok(match accrued {
+                    Some(accrued) => accrued
+                        .with_amount(accrued.amount().unwrap_or_default().checked_add(amount)?),
+                    None => fee.clone(),
+                })
+            })?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Adds the native coins deposited for a proposal's gift to the escrowed totals.
+pub(crate) fn escrow_gift(storage: &mut dyn Storage, coins: &[Coin]) -> StdResult<()> {
+    for coin in coins.iter().filter(|coin| !coin.amount.is_zero()) {
+        ESCROWED_GIFTS.update(storage, &coin.denom, |escrowed| -> StdResult<_> {
+            Ok(escrowed.unwrap_or_default().checked_add(coin.amount)?)
+        })?;
+    }
+
+    Ok(())
+}
+
+/// Removes the native coins paid out of a proposal's gift from the escrowed totals.
+pub(crate) fn release_gift(storage: &mut dyn Storage, coins: &[Coin]) -> StdResult<()> {
+    for coin in coins.iter().filter(|coin| !coin.amount.is_zero()) {
+        ESCROWED_GIFTS.update(storage, &coin.denom, |escrowed| -> StdResult<_> {
+            Ok(escrowed.unwrap_or_default().checked_sub(coin.amount)?)
+        })?;
+    }
+
+    Ok(())
+}
+
+/// Removes the native coins of a resolved proposal's gift from the escrowed totals, except for
+/// the given part the contract keeps holding and the staked part, released once unbonded.
+pub(crate) fn release_resolved_gift(
+    storage: &mut dyn Storage,
+    proposal: &Proposal,
+    gift: &[Gift],
+    held: &[Gift],
+) -> StdResult<()> {
+    let mut held = held.to_vec();
+    if let Some(staking) = &proposal.staking {
+        held.push(Gift::Native(staking.amount.clone()));
+    }
+    let released = subtract_gift(gift, &aggregate_gift(held)?)?;
+    release_gift(storage, &native_coins(&released))
+}
+
+/// Returns the fees of a denom left to withdraw from the treasury, as a gift of the native coin
+/// or CW20 token they were credited in. `None` if none were ever credited.
+pub(crate) fn available_fees(storage: &dyn Storage, denom: &str) -> StdResult<Option<Gift>> {
+    let Some(accrued) = TREASURY.may_load(storage, denom)? else {
+        return Ok(None);
+    };
+    let withdrawn = WITHDRAWN_FEES.may_load(storage, denom)?.unwrap_or_default();
+    let available = accrued
+        .amount()
+        .unwrap_or_default()
+        .checked_sub(withdrawn)?;
+    Ok(Some(accrued.with_amount(available)))
+}
+
+/// Validates the counter-gift the receiver has to deposit when accepting, returning it
+/// aggregated by denom. It must comply with the gift policy so the receiver is able to deposit
+/// it, only the escrow caps are left to be checked on the deposit.
+pub(crate) fn validate_counter_gift(
+    gift_policy: &GiftPolicy,
+    counter_gift: Vec<Coin>,
+) -> Result<Vec<Coin>, ContractError> {
+    let counter_gift = aggregate_coins(counter_gift)?;
+    ensure!(
+        counter_gift.iter().all(|coin| !coin.amount.is_zero()),
+        ContractError::InvalidCounterGift {
+            reason: "the amounts can't be zero".to_string(),
+        }
+    );
+    validate_gift_limits(gift_policy, &counter_gift, &counter_gift)?;
+
+    Ok(counter_gift)
+}
+
+/// Validates that exactly the expected coins were sent with the transaction, failing with the
+/// given error built from the expected coins otherwise.
+pub(crate) fn validate_exact_funds_sent(
+    info: &MessageInfo,
+    expected: &[Coin],
+    error: impl FnOnce(String) -> ContractError,
+) -> Result<(), ContractError> {
+    let paid = aggregate_coins(info.funds.clone())?
+        .into_iter()
+        .filter(|coin| !coin.amount.is_zero())
+        .collect::<Vec<_>>();
+
+    ensure!(
+        paid == aggregate_coins(expected.to_vec())?,
+        error(coins_to_string(expected))
+    );
+
+    Ok(())
+}
+
+/// Validates a vesting schedule, i.e. that durations are not zero and that tranches share the
+/// whole gift.
+pub(crate) fn validate_vesting_schedule(schedule: &VestingSchedule) -> Result<(), ContractError> {
+    match schedule {
+        VestingSchedule::Cliff { duration } | VestingSchedule::Linear { duration } => {
+            ensure!(
+                *duration > 0,
+                ContractError::InvalidVestingSchedule {
+                    reason: "the duration cannot be zero".to_string(),
+                }
+            );
+        }
+        VestingSchedule::Tranches(tranches) => {
+            ensure!(
+                tranches.iter().all(|tranche| tranche.share_bps > 0),
+                ContractError::InvalidVestingSchedule {
+                    reason: "tranche shares cannot be zero".to_string(),
+                }
+            );
+            let total_bps = tranches
+                .iter()
+                .map(|tranche| u64::from(tranche.share_bps))
+                .sum::<u64>();
+            ensure!(
+                total_bps == u64::from(BASIS_POINTS_DENOMINATOR),
+                ContractError::InvalidVestingSchedule {
+                    reason: format!("tranche shares must add up to {BASIS_POINTS_DENOMINATOR} bps"),
+                }
+            );
+        }
+    }
+
+    Ok(())
+}
+
+/// Computes the amount of a gift released by a vesting schedule started at the given time.
+pub(crate) fn vested_amount(
+    schedule: &VestingSchedule,
+    started_at: Timestamp,
+    total: Uint128,
+    now: Timestamp,
+) -> Uint128 {
+    let elapsed = now.seconds().saturating_sub(started_at.seconds());
+    match schedule {
+        VestingSchedule::Cliff { duration } if elapsed >= *duration => total,
+        VestingSchedule::Cliff { .. } => Uint128::zero(),
+        VestingSchedule::Linear { duration } => {
+            total.multiply_ratio(elapsed.min(*duration), *duration)
+        }
+        VestingSchedule::Tranches(tranches) => {
+            let released_bps = tranches
+                .iter()
+                .filter(|tranche| tranche.unlocks_at <= now)
+                .map(|tranche| u64::from(tranche.share_bps))
+                .sum::<u64>();
+            total.multiply_ratio(released_bps, BASIS_POINTS_DENOMINATOR)
+        }
+    }
+}
+
+/// Stops the release of an accepted proposal's vesting gift. Returns the vested part the
+/// receiver didn't claim yet, now considered claimed, and the part that won't vest anymore.
+/// Both are empty if the gift isn't vesting.
+pub(crate) fn stop_vesting(
+    proposal: &mut Proposal,
+    now: Timestamp,
+) -> StdResult<(Vec<Gift>, Vec<Gift>)> {
+    let Some(vesting) = proposal.vesting.as_mut() else {
+        return Ok((vec![], vec![]));
+    };
+    let Some(started_at) = vesting.started_at.filter(|_| vesting.stopped_at.is_none()) else {
+        return Ok((vec![], vec![]));
+    };
+
+    let mut vested = vec![];
+    let mut unvested = vec![];
+    for part in &proposal.gift {
+        let (Some(denom), Some(total)) = (part.denom(), part.amount()) else {
+            continue;
+        };
+        let vested_total = vested_amount(&vesting.schedule, started_at, total, now);
+        let unclaimed = vested_total.checked_sub(claimed_amount(&vesting.claimed, &denom))?;
+        if !unclaimed.is_zero() {
+            vested.push(part.with_amount(unclaimed));
+        }
+        let rest = total.checked_sub(vested_total)?;
+        if !rest.is_zero() {
+            unvested.push(part.with_amount(rest));
+        }
+    }
+
+    vesting.claimed = aggregate_gift([vesting.claimed.clone(), vested.clone()].concat())?;
+    vesting.stopped_at = Some(now);
+
+    Ok((vested, unvested))
+}
+
+/// Returns the part of an accepted proposal's gift the receiver kept so far, only the claimed
+/// part of a vesting gift. What the receiver's disposition routed elsewhere isn't counted.
+pub(crate) fn released_gift(storage: &dyn Storage, proposal: &Proposal) -> StdResult<Vec<Gift>> {
+    let released = match &proposal.vesting {
+        Some(vesting) if vesting.started_at.is_some() => &vesting.claimed,
+        _ => &proposal.gift,
+    };
+    let kept = route_gift(storage, proposal, released)?
+        .into_iter()
+        .filter(|(recipient, _)| *recipient == proposal.receiver)
+        .flat_map(|(_, gift)| gift)
+        .collect();
+
+    aggregate_gift(kept)
+}
+
+/// Returns the amount claimed of the gift with the given native denom or CW20 token address.
+pub(crate) fn claimed_amount(claimed: &[Gift], denom: &str) -> Uint128 {
+    claimed
+        .iter()
+        .find(|part| part.denom().as_deref() == Some(denom))
+        .and_then(|part| part.amount())
+        .unwrap_or_default()
+}
+
+/// Builds the message instantiating the certificate collection with instantiate2, so its
+/// address is known upfront. Returns the message along with the collection's address.
+pub(crate) fn instantiate_certificate_collection(
+    deps: Deps,
+    env: &Env,
+    code_id: u64,
+) -> Result<(CosmosMsg, Addr), ContractError> {
+    let code_info = deps.querier.query_wasm_code_info(code_id)?;
+    let creator = deps.api.addr_canonicalize(env.contract.address.as_str())?;
+    let salt = Binary::from(CERTIFICATE_COLLECTION_SALT);
+    let collection = deps.api.addr_humanize(&instantiate2_address(
+        code_info.checksum.as_slice(),
+        &creator,
+        &salt,
+    )?)?;
+
+    let msg = CosmosMsg::Wasm(WasmMsg::Instantiate2 {
+        admin: Some(env.contract.address.to_string()),
+        code_id,
+        label: "Proposal certificates".to_string(),
+        msg: to_json_binary(
+            &Cw721InstantiateMsg::<DefaultOptionalCollectionExtensionMsg> {
+                name: "Proposal Certificates".to_string(),
+                symbol: "CERT".to_string(),
+                collection_info_extension: None,
+                minter: Some(env.contract.address.to_string()),
+                creator: Some(env.contract.address.to_string()),
+                withdraw_address: None,
+            },
+        )?,
+        funds: vec![],
+        salt,
+    });
+
+    Ok((msg, collection))
+}
+
+/// Builds the messages minting the certificates of an accepted proposal to both parties, the
+/// collection's code keeping them soulbound. Returns the messages along with the minted token
+/// ids.
+pub(crate) fn mint_certificates(
+    collection: &Addr,
+    proposal: &Proposal,
+) -> StdResult<(Vec<CosmosMsg>, Vec<String>)> {
+    let attribute = |trait_type: &str, value: String| Trait {
+        display_type: None,
+        trait_type: trait_type.to_string(),
+        value,
+    };
+    let mut attributes = vec![
+        attribute("proposal_id", proposal.id.to_string()),
+        attribute("proposer", proposal.proposer.to_string()),
+        attribute("receiver", proposal.receiver.to_string()),
+    ];
+    if let Some(title) = &proposal.title {
+        attributes.push(attribute("title", title.clone()));
+    }
+    if let Some(replied_at) = proposal.replied_at {
+        attributes.push(attribute("replied_at", replied_at.to_string()));
+    }
+
+    let mut messages: Vec<CosmosMsg> = vec![];
+    let mut token_ids = vec![];
+    for (role, owner) in [
+        ("proposer", &proposal.proposer),
+        ("receiver", &proposal.receiver),
+    ] {
+        let token_id = format!("{}-{role}", proposal.id);
+        messages.push(CosmosMsg::Wasm(WasmMsg::Execute {
+            contract_addr: collection.to_string(),
+            msg: to_json_binary(&Cw721ExecuteMsg::<
+                DefaultOptionalNftExtensionMsg,
+                DefaultOptionalCollectionExtensionMsg,
+                Empty,
+            >::Mint {
+                token_id: token_id.clone(),
+                owner: owner.to_string(),
+                token_uri: None,
+                extension: Some(NftExtensionMsg {
+                    name: Some(
+                        proposal
+                            .title
+                            .clone()
+                            .unwrap_or_else(|| format!("Proposal #{}", proposal.id)),
+                    ),
+                    description: Some(format!(
+                        "Certificate of the proposal #{} accepted by {}",
+                        proposal.id, proposal.receiver
+                    )),
+                    attributes: Some(attributes.clone()),
+                    ..Default::default()
+                }),
+            })?,
+            funds: vec![],
+        }));
+        token_ids.push(token_id);
+    }
+
+    Ok((messages, token_ids))
+}
+
 /// Validates the contract version and name.
 #[macro_export]
 macro_rules! validate_contract {
@@ -1,15 +1,48 @@
 #[allow(unused_imports)]
-use crate::proposal::state::{Config, Proposal, ProposalStatus};
+use crate::proposal::state::{
+    Config, FeeShare, GiftDisposition, GiftPolicy, Proposal, ProposalRevision, ProposalStatus,
+    ReceiveAs, StakingConfig, VestingSchedule,
+};
 use cosmwasm_schema::{cw_serde, QueryResponses};
-use cosmwasm_std::{Coin, Order};
+use cosmwasm_std::{Addr, Coin, Decimal, Order, Timestamp, Uint128};
+use cw20::Cw20ReceiveMsg;
+use cw721::receiver::Cw721ReceiveMsg;
 use cw_ownable::{cw_ownable_execute, cw_ownable_query};
+use cw_utils::{Duration, Expiration};
 
 #[cw_serde]
 pub struct InstantiateMsg {
     /// The owner of the contract.
     pub owner: Option<String>,
-    /// The fee that the owner will receive for each successful proposal
-    pub successful_proposal_fee: Coin,
+    /// The fees accepted for each proposal, any one of which is paid. It's credited to the
+    /// treasury for each successful proposal, proposals are free if empty.
+    pub successful_proposal_fees: Vec<Coin>,
+    /// The duration a proposal stays open when the proposer doesn't provide an expiration
+    pub default_proposal_duration: Option<Duration>,
+    /// The maximum duration a proposal can stay open
+    pub max_proposal_duration: Option<Duration>,
+    /// The cooling-off window during which an answer is provisional and can be revoked
+    pub finalization_delay: Option<Duration>,
+    /// The ordered relationship stages proposals go through, can be empty
+    pub stages: Vec<String>,
+    /// The code id of the cw721 contract, supporting onchain metadata, used to instantiate the
+    /// certificate collection. No certificates are minted if not set. The certificates are
+    /// minted to the parties and meant to be soulbound, so the code must be a non-transferable
+    /// cw721 variant rejecting `TransferNft` and `SendNft`.
+    pub certificate_code_id: Option<u64>,
+    /// The restrictions on the native coins given as gifts, nothing is restricted if not set
+    pub gift_policy: Option<GiftPolicy>,
+    /// The validator escrowed gifts can be delegated to, staking is disabled if not set
+    pub staking: Option<StakingConfig>,
+    /// The pool contract gifts are swapped through, implementing [PoolExecuteMsg]. Swapping is
+    /// disabled if not set.
+    pub swap_pool: Option<String>,
+    /// The fee charged on each denom of an accepted gift, in basis points, on top of the
+    /// proposal fee
+    pub gift_fee_bps: Option<Decimal>,
+    /// How the fees of accepted proposals are split across recipients, the weights adding up to
+    /// 10000 basis points. The fees are credited to the treasury if empty.
+    pub fee_shares: Vec<FeeShare>,
 }
 
 #[cw_ownable_execute]
@@ -25,6 +58,80 @@ pub enum ExecuteMsg {
         receiver: String,
         /// The amount of coins that will be sent to the partner as a gift, can be empty.
         gift: Vec<Coin>,
+        /// When the proposal expires, defaults to the contract's default proposal duration.
+        expires_at: Option<Expiration>,
+        /// The relationship stage of the proposal, defaults to the first stage.
+        stage: Option<String>,
+        /// When the proposal is revealed to the receiver, e.g. on their birthday. Until then
+        /// the proposal's title, speech and gift are hidden and it can't be answered.
+        reveal_at: Option<Expiration>,
+        /// Releases the gift over time once the proposal is accepted, instead of all at once.
+        vesting: Option<VestingSchedule>,
+        /// Delegates the part of the gift in the staking denom while the proposal is pending.
+        /// The rewards go to the receiver on yes and to the proposer otherwise, the gift is
+        /// paid out once the unbonding period is over.
+        #[serde(default)]
+        stake_gift: bool,
+        /// The coins the receiver has to deposit when saying yes, sent to the proposer in
+        /// exchange for the gift, can be empty. It must comply with the limits of the gift
+        /// policy, but its deposit isn't subject to the escrow caps. It counts towards them
+        /// while held during the cooling-off window.
+        #[serde(default)]
+        counter_gift: Vec<Coin>,
+        /// Alternative gift bundles, all escrowed up front. The receiver picks one of them when
+        /// saying yes, on top of `gift`, and the others are refunded. Either empty or at least
+        /// two options, none of them empty.
+        #[serde(default)]
+        gift_options: Vec<Vec<Coin>>,
+    },
+    /// Updates a pending proposal, keeping its previous version in the revision history.
+    /// Only the proposer can execute this message.
+    UpdateProposal {
+        /// The proposal's ID.
+        id: u64,
+        /// The new title, if it changes.
+        title: Option<String>,
+        /// The new speech, if it changes.
+        speech: Option<String>,
+        /// The coins to add to the gift, can be empty.
+        add_gift: Vec<Coin>,
+        /// The gift option to add the coins to, instead of the gift shared by all options.
+        gift_option: Option<u32>,
+    },
+    /// Adds the attached coins to the gift of a pending proposal, e.g. friends and family chipping
+    /// in. Each contributor gets their own contribution back if the proposal isn't accepted.
+    /// Anyone but the receiver can execute this message.
+    ContributeGift {
+        /// The proposal's ID.
+        id: u64,
+    },
+    /// Receives CW20 tokens used as a proposal's gift, the inner message is a [ReceiveMsg].
+    /// Only the tokens allowed by the gift policy can call this hook.
+    Receive(Cw20ReceiveMsg),
+    /// Receives a CW721 token used as a proposal's gift and escrows it, the inner message is a
+    /// [ReceiveMsg]. Only the collections allowed by the gift policy can call this hook.
+    ReceiveNft(Cw721ReceiveMsg),
+    /// Pays out the staked part of a resolved proposal's gift once the unbonding period of its
+    /// batch is over, along with the gift fee charged on it.
+    /// Anyone can execute this message.
+    ClaimUnbonded {
+        /// The proposal's ID.
+        id: u64,
+    },
+    /// Undelegates the staked gifts of the proposals resolved since the last undelegation from
+    /// the validator as a single batch, at most once per epoch. Resolving a proposal does it
+    /// already once the epoch is over. The rewards earned by the queued gifts meanwhile go to
+    /// the treasury.
+    /// Anyone can execute this message.
+    UndelegateQueued {
+        /// The validator the gifts are delegated to.
+        validator: String,
+    },
+    /// Claims the part of a vesting gift released so far, until the proposal is dissolved.
+    /// Only the receiver can execute this message.
+    ClaimGift {
+        /// The proposal's ID.
+        id: u64,
     },
     /// Cancels a proposal.
     CancelProposal {
@@ -37,6 +144,13 @@ pub enum ExecuteMsg {
         id: u64,
         /// why you say yes
         reply: Option<String>,
+        /// What to do with the gift, the receiver keeps it by default
+        gift_disposition: Option<GiftDisposition>,
+        /// Swaps the native coins of the gift into another denom through the contract's pool
+        /// before paying the receiver
+        receive_as: Option<ReceiveAs>,
+        /// The index of the picked gift option, required if the proposal offers gift options
+        gift_option: Option<u32>,
     },
     /// Say no to a proposal.
     No {
@@ -45,11 +159,157 @@ pub enum ExecuteMsg {
         /// why you say no
         reply: Option<String>,
     },
+    /// Answers a proposal with different terms, closing it and creating a counter-proposal
+    /// to the original proposer. Only the receiver can execute this message.
+    CounterPropose {
+        /// The proposal's ID.
+        id: u64,
+        /// The counter-proposal's speech.
+        speech: Option<String>,
+        /// The amount of coins that will be sent to the original proposer as a gift, can be empty.
+        gift: Vec<Coin>,
+    },
+    /// Defers the answer to a proposal until the given deadline, keeping the funds in escrow.
+    /// The proposer can't cancel the proposal before the deadline, after which the proposal is
+    /// pending again. Only the receiver can execute this message.
+    Defer {
+        /// The proposal's ID.
+        id: u64,
+        /// The requested decision deadline, can't be later than the proposal's expiration.
+        until: Expiration,
+        /// why you say not yet
+        reply: Option<String>,
+    },
+    /// Revokes a provisional answer during the cooling-off window, moving the proposal back to
+    /// pending. An acceptance swapping the gift can still be revoked after the window, in case
+    /// the swap keeps it from being finalized. Only the receiver can execute this message.
+    RevokeAnswer {
+        /// The proposal's ID.
+        id: u64,
+    },
+    /// Finalizes a provisional answer once the cooling-off window is over, executing the
+    /// transfers. The gift is paid unswapped if swapping was disabled since the acceptance.
+    /// Anyone can execute this message.
+    Finalize {
+        /// The proposal's ID.
+        id: u64,
+    },
+    /// Expires a pending proposal whose deadline has passed, refunding the proposer.
+    /// Anyone can execute this message.
+    ExpireProposal {
+        /// The proposal's ID.
+        id: u64,
+    },
+    /// Requests the dissolution of an accepted proposal, to be confirmed by the other party.
+    /// If the receiver requests it, the returned share of the gift must be attached.
+    /// Only the proposer or the receiver can execute this message.
+    RequestDissolution {
+        /// The proposal's ID.
+        id: u64,
+        /// The share of the gift, in basis points, the receiver returns to the proposer.
+        /// Only the native coins of the gift the receiver kept are returned, not the part sent
+        /// or split away, and of a vesting gift only the part claimed so far.
+        gift_return_bps: u16,
+    },
+    /// Confirms the dissolution requested by the other party, moving the proposal to dissolved.
+    /// If the receiver confirms it, the returned share of the gift must be attached. The stage
+    /// reached through the proposal is no longer held by the pair. A vesting gift stops being
+    /// released, its vested part is paid out and the rest goes back to the proposer.
+    ConfirmDissolution {
+        /// The proposal's ID.
+        id: u64,
+    },
+    /// Withdraws a dissolution request, refunding the returned share of the gift if it was
+    /// escrowed. Only the party that requested the dissolution can execute this message.
+    CancelDissolution {
+        /// The proposal's ID.
+        id: u64,
+    },
     /// update contract config
     /// Only the owner can execute this message.
     UpdateConfig {
-        /// The new fee that the owner will receive for each successful proposal
-        successful_proposal_fee: Option<Coin>,
+        /// The new fees accepted for each proposal, any one of which is paid
+        successful_proposal_fees: Option<Vec<Coin>>,
+        /// The new default duration of a proposal, a zero duration unsets it
+        default_proposal_duration: Option<Duration>,
+        /// The new maximum duration of a proposal, a zero duration unsets it
+        max_proposal_duration: Option<Duration>,
+        /// The new cooling-off window of answers, a zero duration disables it
+        finalization_delay: Option<Duration>,
+        /// The new ordered relationship stages
+        stages: Option<Vec<String>>,
+        /// The new restrictions on the native coins given as gifts
+        gift_policy: Option<GiftPolicy>,
+        /// The new staking config of the gifts, an empty validator disables staking
+        staking: Option<StakingConfig>,
+        /// The new pool contract gifts are swapped through, an empty address disables swapping
+        swap_pool: Option<String>,
+        /// The new fee charged on accepted gifts in basis points, zero disables it
+        gift_fee_bps: Option<Decimal>,
+        /// The new split of the fees, empty credits them to the treasury
+        fee_shares: Option<Vec<FeeShare>>,
+    },
+    /// Withdraws the fees available in the treasury.
+    /// Only the owner can execute this message.
+    WithdrawFees {
+        /// The native denoms or CW20 token addresses to withdraw, all of them if empty
+        denoms: Vec<String>,
+        /// The address receiving the fees, defaults to the owner
+        to: Option<String>,
+    },
+    /// Migrates the next batch of proposals left over by the migration from 0.1.x, which only
+    /// migrates the first batch. The other messages are rejected until all of them are migrated.
+    /// Anyone can execute this message.
+    MigrateProposals {
+        /// The number of proposals to migrate, defaults and is capped to 100
+        limit: Option<u32>,
+    },
+}
+
+/// The message the contract sends to the pool to swap a gift. The pool swaps the attached
+/// coins and sends the result back to the contract.
+#[cw_serde]
+pub enum PoolExecuteMsg {
+    Swap {
+        /// The denom to swap the attached coins into
+        ask_denom: String,
+    },
+}
+
+/// The messages sent along with CW20 tokens or a CW721 token, which are added to a proposal's
+/// gift.
+#[cw_serde]
+pub enum ReceiveMsg {
+    /// Creates a proposal with the received tokens or NFT as gift. The proposal fee can't be paid
+    /// along with them, so this fails if the contract charges one: create the proposal with
+    /// [ExecuteMsg::CreateProposal] and top it up with [ReceiveMsg::UpdateProposal] instead.
+    CreateProposal {
+        /// The proposal's title.
+        title: Option<String>,
+        /// The proposal's speech.
+        speech: Option<String>,
+        /// The receiver's address that will receive the proposal
+        receiver: String,
+        /// When the proposal expires, defaults to the contract's default proposal duration.
+        expires_at: Option<Expiration>,
+        /// The relationship stage of the proposal, defaults to the first stage.
+        stage: Option<String>,
+        /// When the proposal is revealed to the receiver.
+        reveal_at: Option<Expiration>,
+        /// Releases the gift over time once the proposal is accepted.
+        vesting: Option<VestingSchedule>,
+    },
+    /// Adds the received tokens or NFT to the gift of a pending proposal, keeping its previous version
+    /// in the revision history. Only the proposer can send this message.
+    UpdateProposal {
+        /// The proposal's ID.
+        id: u64,
+        /// The new title, if it changes.
+        title: Option<String>,
+        /// The new speech, if it changes.
+        speech: Option<String>,
+        /// The gift option to add the tokens or NFT to, instead of the gift shared by all options.
+        gift_option: Option<u32>,
     },
 }
 
@@ -75,6 +335,54 @@ pub enum QueryMsg {
 
     #[returns(Status)]
     Status {},
+
+    /// Retrieves the whole negotiation chain a proposal belongs to, from the original proposal
+    /// to the latest counter-proposal
+    #[returns(ProposalsResponse)]
+    NegotiationChain { id: u64 },
+
+    /// Retrieves the prior versions of a proposal
+    #[returns(ProposalRevisionsResponse)]
+    ProposalRevisions {
+        id: u64,
+        start_after: Option<u32>,
+        limit: Option<u32>,
+    },
+
+    /// Retrieves the latest stage accepted between two addresses
+    #[returns(StageResponse)]
+    Stage {
+        address_a: String,
+        address_b: String,
+    },
+
+    /// Retrieves the contributors to a proposal's gift, hidden until the proposal is revealed
+    #[returns(GiftContributorsResponse)]
+    GiftContributors {
+        id: u64,
+        start_after: Option<String>,
+        limit: Option<u32>,
+    },
+
+    /// Retrieves the certificate token ids minted for an accepted proposal
+    #[returns(CertificatesResponse)]
+    Certificates { id: u64 },
+
+    /// Retrieves the vested, claimed and remaining amounts of a vesting gift, hidden until the
+    /// proposal is revealed
+    #[returns(VestingResponse)]
+    Vesting { id: u64 },
+
+    /// Previews the fee charged on the given gift when it's accepted
+    #[returns(GiftFeeResponse)]
+    GiftFee { gift: Vec<Coin> },
+
+    /// Retrieves the fees accrued in and withdrawn from the treasury per denom
+    #[returns(TreasuryResponse)]
+    Treasury {
+        start_after: Option<String>,
+        limit: Option<u32>,
+    },
 }
 
 #[cw_serde]
@@ -92,6 +400,89 @@ pub struct Status {
     pub total_proposals_yes: u64,
     pub total_proposals_no: u64,
     pub total_proposals_cancelled: u64,
+    pub total_proposals_expired: u64,
+    pub total_proposals_countered: u64,
+    pub total_proposals_deferred: u64,
+    pub total_proposals_dissolved: u64,
+}
+
+#[cw_serde]
+pub struct ProposalRevisionsResponse {
+    pub revisions: Vec<ProposalRevision>,
+}
+
+#[cw_serde]
+pub struct StageResponse {
+    /// The latest accepted stage, if any
+    pub stage: Option<String>,
+    /// The accepted proposal that reached the stage
+    pub proposal_id: Option<u64>,
+}
+
+#[cw_serde]
+pub struct GiftContributorsResponse {
+    pub contributors: Vec<GiftContribution>,
+}
+
+#[cw_serde]
+pub struct GiftContribution {
+    pub contributor: Addr,
+    /// The coins the contributor added to the gift
+    pub amount: Vec<Coin>,
+}
+
+#[cw_serde]
+pub struct CertificatesResponse {
+    /// The token ids in the certificate collection, empty if none were minted
+    pub token_ids: Vec<String>,
+}
+
+#[cw_serde]
+pub struct GiftFeeResponse {
+    /// The fee charged per denom, CW20 tokens are charged the same
+    pub fee: Vec<Coin>,
+    /// What is left of the gift once the fee is charged
+    pub remaining: Vec<Coin>,
+}
+
+#[cw_serde]
+pub struct TreasuryResponse {
+    pub fees: Vec<TreasuryFees>,
+}
+
+/// The fees of a denom collected by the treasury.
+#[cw_serde]
+pub struct TreasuryFees {
+    /// The native denom or the CW20 token address
+    pub denom: String,
+    /// The fees credited to the treasury
+    pub accrued: Uint128,
+    /// The fees withdrawn by the owner
+    pub withdrawn: Uint128,
+    /// The fees left to withdraw
+    pub available: Uint128,
+}
+
+#[cw_serde]
+pub struct VestingResponse {
+    pub schedule: VestingSchedule,
+    /// When the schedule started, if the proposal was accepted
+    pub started_at: Option<Timestamp>,
+    pub amounts: Vec<VestingAmount>,
+}
+
+/// The release state of a fungible part of a vesting gift.
+#[cw_serde]
+pub struct VestingAmount {
+    /// The native denom or the CW20 token address
+    pub denom: String,
+    pub total: Uint128,
+    /// The amount released so far
+    pub vested: Uint128,
+    /// The amount claimed by the receiver
+    pub claimed: Uint128,
+    /// The amount still held by the contract
+    pub remaining: Uint128,
 }
 
 /// Filter proposals by proposer or receiver
@@ -0,0 +1,88 @@
+use cosmwasm_std::{BlockInfo, StdResult, Storage};
+
+use crate::error::ContractError;
+use crate::proposal::state::{Proposal, ProposalStatus, DEFERRED_COUNTER};
+
+/// The transitions a proposal is allowed to go through, as `(from, to)` pairs.
+/// Any transition not listed here is rejected.
+pub const TRANSITIONS: &[(ProposalStatus, ProposalStatus)] = &[
+    (ProposalStatus::Pending, ProposalStatus::Yes),
+    (ProposalStatus::Pending, ProposalStatus::No),
+    (ProposalStatus::Pending, ProposalStatus::Expired),
+    (ProposalStatus::Pending, ProposalStatus::Canceled),
+    (ProposalStatus::Pending, ProposalStatus::ProvisionalYes),
+    (ProposalStatus::Pending, ProposalStatus::ProvisionalNo),
+    (ProposalStatus::Pending, ProposalStatus::Countered),
+    (ProposalStatus::Pending, ProposalStatus::Deferred),
+    (ProposalStatus::Deferred, ProposalStatus::Yes),
+    (ProposalStatus::Deferred, ProposalStatus::No),
+    (ProposalStatus::Deferred, ProposalStatus::ProvisionalYes),
+    (ProposalStatus::Deferred, ProposalStatus::ProvisionalNo),
+    (ProposalStatus::Deferred, ProposalStatus::Countered),
+    (ProposalStatus::Deferred, ProposalStatus::Pending),
+    (ProposalStatus::Yes, ProposalStatus::Dissolved),
+    (ProposalStatus::ProvisionalYes, ProposalStatus::Yes),
+    (ProposalStatus::ProvisionalYes, ProposalStatus::Pending),
+    (ProposalStatus::ProvisionalNo, ProposalStatus::No),
+    (ProposalStatus::ProvisionalNo, ProposalStatus::Pending),
+];
+
+/// Returns whether a proposal can move from the `from` status to the `to` status.
+pub fn can_transition(from: &ProposalStatus, to: &ProposalStatus) -> bool {
+    TRANSITIONS
+        .iter()
+        .any(|(allowed_from, allowed_to)| allowed_from == from && allowed_to == to)
+}
+
+/// Returns the status a provisional answer becomes once finalized, if any.
+pub fn final_status(status: &ProposalStatus) -> Option<ProposalStatus> {
+    match status {
+        ProposalStatus::ProvisionalYes => Some(ProposalStatus::Yes),
+        ProposalStatus::ProvisionalNo => Some(ProposalStatus::No),
+        _ => None,
+    }
+}
+
+/// Moves the proposal to the given status, if the transition is allowed.
+/// Keeps track of the number of deferred proposals.
+pub fn transition(
+    storage: &mut dyn Storage,
+    proposal: &mut Proposal,
+    to: ProposalStatus,
+) -> Result<(), ContractError> {
+    if !can_transition(&proposal.status, &to) {
+        return Err(ContractError::InvalidTransition {
+            from: proposal.status.clone(),
+            to,
+        });
+    }
+
+    if proposal.status == ProposalStatus::Deferred {
+        DEFERRED_COUNTER.update(storage, |count| -> StdResult<_> { Ok(count - 1) })?;
+        proposal.deferred_until = None;
+    }
+    if to == ProposalStatus::Deferred {
+        DEFERRED_COUNTER.update(storage, |count| -> StdResult<_> { Ok(count + 1) })?;
+    }
+
+    proposal.status = to;
+
+    Ok(())
+}
+
+/// Moves a deferred proposal back to pending once the requested decision deadline has passed.
+pub fn resume_if_deferral_over(
+    storage: &mut dyn Storage,
+    proposal: &mut Proposal,
+    block: &BlockInfo,
+) -> Result<(), ContractError> {
+    if proposal.status == ProposalStatus::Deferred
+        && proposal
+            .deferred_until
+            .is_some_and(|deferred_until| deferred_until.is_expired(block))
+    {
+        transition(storage, proposal, ProposalStatus::Pending)?;
+    }
+
+    Ok(())
+}
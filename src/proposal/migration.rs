@@ -0,0 +1,188 @@
+use cosmwasm_schema::cw_serde;
+use cosmwasm_std::{Addr, Coin, Order, StdResult, Storage};
+use cw_storage_plus::{index_list, Bound, IndexedMap, Item, MultiIndex};
+use cw_utils::Expiration;
+
+use crate::helpers::{escrow_gift, native_gift};
+use crate::proposal::state::{
+    Config, GiftPolicy, Proposal, ProposalStatus, CONFIG, COUNTERED_COUNTER, DEFERRED_COUNTER,
+    DISSOLVED_COUNTER, EXPIRED_COUNTER, PROPOSALS,
+};
+
+/// The config stored by the 0.1.x versions.
+#[cw_serde]
+pub struct LegacyConfig {
+    pub successful_proposal_fee: Coin,
+}
+
+/// A proposal stored by the 0.1.x versions, which were only ever pending, accepted or rejected.
+#[cw_serde]
+pub struct LegacyProposal {
+    pub id: u64,
+    pub proposer: Addr,
+    pub receiver: Addr,
+    pub gift: Vec<Coin>,
+    pub fee: Coin,
+    pub title: Option<String>,
+    pub speech: Option<String>,
+    pub reply: Option<String>,
+    pub status: ProposalStatus,
+    pub created_at: u64,
+    pub replied_at: Option<u64>,
+}
+
+pub const LEGACY_CONFIG: Item<LegacyConfig> = Item::new("config");
+/// The proposals of the 0.1.x versions, whose `status_by_proposer` index was keyed by the
+/// receiver.
+pub const LEGACY_PROPOSALS: IndexedMap<u64, LegacyProposal, LegacyProposalIndexes> =
+    IndexedMap::new(
+        "proposals",
+        LegacyProposalIndexes {
+            proposer: MultiIndex::new(
+                |_pk, p| p.proposer.to_string(),
+                "proposals",
+                "proposals__proposer",
+            ),
+            status_by_proposer: MultiIndex::new(
+                |_pk, p| (p.receiver.as_bytes().to_vec(), p.status.clone().into()),
+                "proposals",
+                "proposals__status_by_proposer",
+            ),
+            receiver: MultiIndex::new(
+                |_pk, p| p.receiver.to_string(),
+                "proposals",
+                "proposals__receiver",
+            ),
+            status_by_receiver: MultiIndex::new(
+                |_pk, p| (p.receiver.as_bytes().to_vec(), p.status.clone().into()),
+                "proposals",
+                "proposals__status_by_receiver",
+            ),
+        },
+    );
+
+/// The id of the last legacy proposal migrated so far, only stored while some are left to
+/// migrate. `None` if none was migrated yet.
+pub const PROPOSALS_MIGRATION: Item<Option<u64>> = Item::new("proposals_migration");
+
+#[index_list(LegacyProposal)]
+pub struct LegacyProposalIndexes<'a> {
+    pub proposer: MultiIndex<'a, String, LegacyProposal, u64>,
+    pub status_by_proposer: MultiIndex<'a, (Vec<u8>, u8), LegacyProposal, u64>,
+    pub receiver: MultiIndex<'a, String, LegacyProposal, u64>,
+    pub status_by_receiver: MultiIndex<'a, (Vec<u8>, u8), LegacyProposal, u64>,
+}
+
+/// Migrates the state of a 0.1.x deployment. The config gets the new settings at their
+/// defaults and the counters added since start at zero. The proposals are migrated in batches
+/// of `limit`, starting with the first one, see [migrate_legacy_proposals].
+pub fn migrate_from_v0_1(storage: &mut dyn Storage, limit: u32) -> StdResult<()> {
+    let legacy_config = LEGACY_CONFIG.load(storage)?;
+    CONFIG.save(
+        storage,
+        &Config {
+            successful_proposal_fees: vec![legacy_config.successful_proposal_fee]
+                .into_iter()
+                .filter(|fee| !fee.amount.is_zero())
+                .collect(),
+            default_proposal_duration: None,
+            max_proposal_duration: None,
+            finalization_delay: None,
+            stages: vec![],
+            certificate_collection: None,
+            gift_policy: GiftPolicy::default(),
+            staking: None,
+            swap_pool: None,
+            gift_fee_bps: None,
+            fee_shares: vec![],
+        },
+    )?;
+
+    for counter in [
+        EXPIRED_COUNTER,
+        COUNTERED_COUNTER,
+        DEFERRED_COUNTER,
+        DISSOLVED_COUNTER,
+    ] {
+        if !counter.exists(storage) {
+            counter.save(storage, &0)?;
+        }
+    }
+
+    PROPOSALS_MIGRATION.save(storage, &None)?;
+    migrate_legacy_proposals(storage, limit)?;
+
+    Ok(())
+}
+
+/// Migrates the next `limit` legacy proposals, returning how many were migrated. The proposals
+/// never expire and get the new fields at their defaults. Each one is removed along with its
+/// index entries and saved again, rebuilding the indexes, and the gifts of the pending ones are
+/// counted as escrowed. The migration is over once a batch comes up short.
+pub fn migrate_legacy_proposals(storage: &mut dyn Storage, limit: u32) -> StdResult<u32> {
+    let after = PROPOSALS_MIGRATION.load(storage)?;
+    let legacy_proposals = LEGACY_PROPOSALS
+        .range(storage, after.map(Bound::exclusive), None, Order::Ascending)
+        .take(limit as usize)
+        .map(|item| item.map(|(_, proposal)| proposal))
+        .collect::<StdResult<Vec<_>>>()?;
+    let migrated = legacy_proposals.len() as u32;
+    let last_id = legacy_proposals.last().map(|proposal| proposal.id);
+    for legacy in legacy_proposals {
+        migrate_legacy_proposal(storage, legacy)?;
+    }
+
+    if migrated < limit {
+        PROPOSALS_MIGRATION.remove(storage);
+    } else if last_id.is_some() {
+        PROPOSALS_MIGRATION.save(storage, &last_id)?;
+    }
+
+    Ok(migrated)
+}
+
+/// Whether legacy proposals are left to migrate, during which the contract can't be used.
+pub fn is_migrating(storage: &dyn Storage) -> bool {
+    PROPOSALS_MIGRATION.exists(storage)
+}
+
+fn migrate_legacy_proposal(storage: &mut dyn Storage, legacy: LegacyProposal) -> StdResult<()> {
+    LEGACY_PROPOSALS.remove(storage, legacy.id)?;
+    if legacy.status == ProposalStatus::Pending {
+        escrow_gift(storage, &legacy.gift)?;
+    }
+    PROPOSALS.save(
+        storage,
+        legacy.id,
+        &Proposal {
+            id: legacy.id,
+            proposer: legacy.proposer,
+            receiver: legacy.receiver,
+            gift: native_gift(legacy.gift),
+            fee: legacy.fee,
+            title: legacy.title,
+            speech: legacy.speech,
+            reply: legacy.reply,
+            status: legacy.status,
+            created_at: legacy.created_at,
+            replied_at: legacy.replied_at,
+            canceled_at: None,
+            expires_at: Expiration::Never {},
+            finalizes_at: None,
+            deferred_until: None,
+            parent_id: None,
+            stage: None,
+            dissolution: None,
+            revision: 0,
+            reveal_at: None,
+            vesting: None,
+            gift_disposition: None,
+            staking: None,
+            receive_as: None,
+            counter_gift: vec![],
+            gift_options: vec![],
+            gift_option: None,
+            gift_fee: vec![],
+        },
+    )
+}
@@ -1 +1,4 @@
+pub mod lifecycle;
+pub mod migration;
+pub mod staking;
 pub mod state;
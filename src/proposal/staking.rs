@@ -0,0 +1,358 @@
+use cosmwasm_std::{
+    Addr, Attribute, BankMsg, Coin, CosmosMsg, Decimal256, Deps, DepsMut, DistributionMsg, Env,
+    StakingMsg, StdResult, Storage, Uint128, Uint256,
+};
+
+use crate::helpers::{credit_treasury, native_coins};
+
+use crate::proposal::state::{
+    Gift, GiftStaking, Proposal, StakingPool, Unbonding, UnbondingPayout, STAKING_POOLS,
+    UNBONDING_BATCHES,
+};
+
+/// The number of unbonding entries the chain allows between a delegator and a validator.
+const MAX_UNBONDING_ENTRIES: u64 = 7;
+
+/// Accrues the rewards earned by the pool since it was last updated, withdrawing them to the
+/// contract. Every proposal staking with the validator earns its share through the index.
+fn accrue_pool_rewards(
+    deps: DepsMut,
+    env: &Env,
+    validator: &str,
+    pool: &mut StakingPool,
+) -> StdResult<Vec<CosmosMsg>> {
+    let bonded_denom = deps.querier.query_bonded_denom()?;
+    let Some(delegation) = deps
+        .querier
+        .query_delegation(&env.contract.address, validator)?
+    else {
+        return Ok(vec![]);
+    };
+    let rewards = delegation
+        .accumulated_rewards
+        .iter()
+        .filter(|reward| reward.denom == bonded_denom)
+        .map(|reward| reward.amount)
+        .sum::<Uint128>();
+    if rewards.is_zero() {
+        return Ok(vec![]);
+    }
+
+    // the queued tokens keep earning until they're undelegated, their rewards belong to no
+    // proposal and go to the treasury, as do the ones earned while no proposal is staking
+    let delegated = delegation.amount.amount;
+    let unowned = if pool.total_shares.is_zero() || delegated.is_zero() {
+        rewards
+    } else {
+        rewards.multiply_ratio(pool.queued.min(delegated), delegated)
+    };
+    credit_treasury(
+        deps.storage,
+        &[Gift::Native(Coin::new(unowned, bonded_denom))],
+    )?;
+    let earned = rewards - unowned;
+    if !earned.is_zero() {
+        pool.reward_index += Decimal256::from_ratio(earned, pool.total_shares);
+    }
+
+    Ok(vec![CosmosMsg::Distribution(
+        DistributionMsg::WithdrawDelegatorReward {
+            validator: validator.to_string(),
+        },
+    )])
+}
+
+/// Returns the tokens delegated to the validator by the proposals still staking, which only
+/// excludes the queued ones if the validator was slashed.
+fn active_delegation(
+    deps: Deps,
+    env: &Env,
+    validator: &str,
+    pool: &StakingPool,
+) -> StdResult<Uint128> {
+    let delegated = deps
+        .querier
+        .query_delegation(&env.contract.address, validator)?
+        .map(|delegation| delegation.amount.amount)
+        .unwrap_or_default();
+    Ok(delegated.saturating_sub(pool.queued))
+}
+
+/// Adds the rewards earned by the gift since they were last accrued.
+fn accrue_gift_rewards(staking: &mut GiftStaking, pool: &StakingPool) -> StdResult<()> {
+    let earned = Uint256::from(staking.shares).mul_floor(pool.reward_index - staking.reward_index);
+    staking.rewards = staking.rewards.checked_add(Uint128::try_from(earned)?)?;
+    staking.reward_index = pool.reward_index;
+    Ok(())
+}
+
+/// Delegates the coins in the staking denom added to a staked proposal's gift.
+pub fn delegate(
+    mut deps: DepsMut,
+    env: &Env,
+    proposal: &mut Proposal,
+    added: &[Coin],
+) -> StdResult<Vec<CosmosMsg>> {
+    let Some(staking) = proposal.staking.as_mut() else {
+        return Ok(vec![]);
+    };
+    let amount = added
+        .iter()
+        .filter(|coin| coin.denom == staking.amount.denom)
+        .map(|coin| coin.amount)
+        .sum::<Uint128>();
+    if amount.is_zero() {
+        return Ok(vec![]);
+    }
+
+    let mut pool = STAKING_POOLS
+        .may_load(deps.storage, &staking.validator)?
+        .unwrap_or_default();
+    let mut messages = accrue_pool_rewards(deps.branch(), env, &staking.validator, &mut pool)?;
+    accrue_gift_rewards(staking, &pool)?;
+
+    // the shares are worth less than their tokens once the validator is slashed
+    let active = active_delegation(deps.as_ref(), env, &staking.validator, &pool)?;
+    let shares = if pool.total_shares.is_zero() || active.is_zero() {
+        amount
+    } else {
+        amount.multiply_ratio(pool.total_shares, active)
+    };
+
+    staking.amount.amount = staking.amount.amount.checked_add(amount)?;
+    staking.shares = staking.shares.checked_add(shares)?;
+    pool.total_shares = pool.total_shares.checked_add(shares)?;
+    pool.unbonding_period = staking.unbonding_period;
+    STAKING_POOLS.save(deps.storage, &staking.validator, &pool)?;
+
+    messages.push(CosmosMsg::Staking(StakingMsg::Delegate {
+        validator: staking.validator.clone(),
+        amount: Coin {
+            denom: staking.amount.denom.clone(),
+            amount,
+        },
+    }));
+    Ok(messages)
+}
+
+/// Undelegates the gift of a resolved proposal, paying its rewards to the given recipient.
+/// The delegated part of the gift is taken out of the given transfers and becomes claimable
+/// once the unbonding period of its batch is over. The gift's share of the delegation is queued
+/// and undelegated along with the other gifts of its batch, see [undelegate_queued].
+pub fn undelegate(
+    mut deps: DepsMut,
+    env: &Env,
+    proposal: &mut Proposal,
+    transfers: &mut Vec<(Addr, Vec<Gift>)>,
+    rewards_recipient: &Addr,
+) -> StdResult<(Vec<CosmosMsg>, Vec<Attribute>)> {
+    let mut fee = unbonding_gift_fee(proposal);
+    let Some(staking) = proposal.staking.as_mut() else {
+        return Ok((vec![], vec![]));
+    };
+
+    let mut pool = STAKING_POOLS.load(deps.storage, &staking.validator)?;
+    let mut messages = accrue_pool_rewards(deps.branch(), env, &staking.validator, &mut pool)?;
+    accrue_gift_rewards(staking, &pool)?;
+
+    let active = active_delegation(deps.as_ref(), env, &staking.validator, &pool)?;
+    let unbonding = staking
+        .shares
+        .multiply_ratio(active, pool.total_shares)
+        .min(staking.amount.amount);
+    pool.total_shares = pool.total_shares.checked_sub(staking.shares)?;
+    pool.queued = pool.queued.checked_add(unbonding)?;
+    let batch = pool.batch;
+
+    // the whole gift in the staking denom is delegated, so each recipient's part of it is paid
+    // out once unbonded
+    let mut payouts: Vec<UnbondingPayout> = vec![];
+    for (recipient, gift) in transfers.iter_mut() {
+        let Some(amount) = native_coins(gift)
+            .into_iter()
+            .find(|coin| coin.denom == staking.amount.denom)
+        else {
+            continue;
+        };
+        gift.retain(|part| !matches!(part, Gift::Native(coin) if coin.denom == amount.denom));
+        match payouts
+            .iter_mut()
+            .find(|payout| payout.recipient == *recipient)
+        {
+            Some(payout) => {
+                payout.amount.amount = payout.amount.amount.checked_add(amount.amount)?
+            }
+            None => payouts.push(UnbondingPayout {
+                recipient: recipient.clone(),
+                amount,
+            }),
+        }
+    }
+    transfers.retain(|(_, gift)| !gift.is_empty());
+
+    // a slashed delegation unbonds less than the staked gift, every payout bearing its share
+    // of the loss
+    if unbonding < staking.amount.amount {
+        for payout in payouts.iter_mut() {
+            payout.amount.amount = payout
+                .amount
+                .amount
+                .multiply_ratio(unbonding, staking.amount.amount);
+        }
+        fee = fee
+            .map(|fee| Coin {
+                amount: fee.amount.multiply_ratio(unbonding, staking.amount.amount),
+                denom: fee.denom,
+            })
+            .filter(|fee| !fee.amount.is_zero());
+    }
+    payouts.retain(|payout| !payout.amount.amount.is_zero());
+
+    if !staking.rewards.is_zero() {
+        messages.push(CosmosMsg::Bank(BankMsg::Send {
+            to_address: rewards_recipient.to_string(),
+            amount: vec![Coin {
+                denom: staking.amount.denom.clone(),
+                amount: staking.rewards,
+            }],
+        }));
+    }
+
+    staking.unbonding = Some(Unbonding {
+        payouts,
+        fee: fee.clone(),
+        batch,
+        claimed: false,
+    });
+
+    let mut attributes = vec![
+        Attribute::new(
+            "staking_rewards",
+            Coin {
+                denom: staking.amount.denom.clone(),
+                amount: staking.rewards,
+            }
+            .to_string(),
+        ),
+        Attribute::new("staking_rewards_recipient", rewards_recipient.as_str()),
+        Attribute::new(
+            "unbonding",
+            Coin {
+                denom: staking.amount.denom.clone(),
+                amount: unbonding,
+            }
+            .to_string(),
+        ),
+        Attribute::new(
+            "unbonding_gift_fee",
+            fee.map(|fee| fee.to_string()).unwrap_or_default(),
+        ),
+        Attribute::new("unbonding_batch", batch.to_string()),
+    ];
+
+    // the pool's rewards were just accrued, the batch can be undelegated right away
+    if undelegation_due(env, &pool) {
+        let (undelegation, undelegation_attributes) = undelegate_batch(
+            deps.storage,
+            env,
+            &staking.validator,
+            &staking.amount.denom,
+            &mut pool,
+        )?;
+        messages.extend(undelegation);
+        attributes.extend(undelegation_attributes);
+    }
+    STAKING_POOLS.save(deps.storage, &staking.validator, &pool)?;
+
+    Ok((messages, attributes))
+}
+
+/// Undelegates the tokens queued in the validator's pool as a single batch. The chain caps the
+/// unbonding entries between a delegator and a validator, so a batch is undelegated at most once
+/// per epoch, a fraction of the unbonding period keeping the entries under the cap. Returns
+/// `None` while the epoch lasts.
+pub fn undelegate_queued(
+    mut deps: DepsMut,
+    env: &Env,
+    validator: &str,
+    denom: &str,
+) -> StdResult<Option<(Vec<CosmosMsg>, Vec<Attribute>)>> {
+    let mut pool = STAKING_POOLS.load(deps.storage, validator)?;
+    if !undelegation_due(env, &pool) {
+        return Ok(None);
+    }
+
+    // undelegating withdraws the pending rewards, they're accrued beforehand to be accounted for
+    let mut messages = accrue_pool_rewards(deps.branch(), env, validator, &mut pool)?;
+    let (undelegation, attributes) =
+        undelegate_batch(deps.storage, env, validator, denom, &mut pool)?;
+    messages.extend(undelegation);
+    STAKING_POOLS.save(deps.storage, validator, &pool)?;
+
+    Ok(Some((messages, attributes)))
+}
+
+/// Returns whether the epoch of the pool's last undelegation is over.
+fn undelegation_due(env: &Env, pool: &StakingPool) -> bool {
+    let epoch = pool.unbonding_period / (MAX_UNBONDING_ENTRIES - 1);
+    pool.undelegated_at
+        .is_none_or(|undelegated_at| env.block.time >= undelegated_at.plus_seconds(epoch))
+}
+
+/// Undelegates the pool's queued tokens and starts its next batch.
+fn undelegate_batch(
+    storage: &mut dyn Storage,
+    env: &Env,
+    validator: &str,
+    denom: &str,
+    pool: &mut StakingPool,
+) -> StdResult<(Vec<CosmosMsg>, Vec<Attribute>)> {
+    let mut messages = vec![];
+    if !pool.queued.is_zero() {
+        messages.push(CosmosMsg::Staking(StakingMsg::Undelegate {
+            validator: validator.to_string(),
+            amount: Coin {
+                denom: denom.to_string(),
+                amount: pool.queued,
+            },
+        }));
+    }
+
+    let claimable_at = env.block.time.plus_seconds(pool.unbonding_period);
+    UNBONDING_BATCHES.save(storage, (validator, pool.batch), &claimable_at)?;
+    let attributes = vec![
+        Attribute::new("undelegated_batch", pool.batch.to_string()),
+        Attribute::new(
+            "undelegated",
+            Coin {
+                denom: denom.to_string(),
+                amount: pool.queued,
+            }
+            .to_string(),
+        ),
+        Attribute::new("unbonding_claimable_at", claimable_at.to_string()),
+    ];
+
+    pool.batch += 1;
+    pool.queued = Uint128::zero();
+    pool.undelegated_at = Some(env.block.time);
+
+    Ok((messages, attributes))
+}
+
+/// Returns the part of an accepted proposal's gift fee charged on its staked gift, which is held
+/// back along with the gift until the unbonding period is over.
+pub fn unbonding_gift_fee(proposal: &Proposal) -> Option<Coin> {
+    let staking = proposal.staking.as_ref()?;
+    proposal
+        .gift_fee
+        .iter()
+        .find_map(|fee| match fee {
+            Gift::Native(coin) if coin.denom == staking.amount.denom => Some(Coin {
+                denom: coin.denom.clone(),
+                amount: coin.amount.min(staking.amount.amount),
+            }),
+            _ => None,
+        })
+        .filter(|fee| !fee.amount.is_zero())
+}
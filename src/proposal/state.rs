@@ -1,6 +1,8 @@
 use cosmwasm_schema::cw_serde;
-use cosmwasm_std::{Addr, Coin};
-use cw_storage_plus::{index_list, IndexedMap, Item, MultiIndex};
+use cosmwasm_std::{Addr, BlockInfo, Coin, Decimal, Decimal256, Timestamp, Uint128};
+use cw20::Cw20CoinVerified;
+use cw_storage_plus::{index_list, IndexedMap, Item, Map, MultiIndex};
+use cw_utils::{Duration, Expiration};
 use std::fmt;
 
 pub const MAX_ITEMS_LIMIT: u32 = 100;
@@ -10,6 +12,35 @@ pub const PROPOSAL_COUNTER: Item<u64> = Item::new("proposal_count");
 pub const SUCCESSFUL_COUNTER: Item<u64> = Item::new("successful_proposals");
 pub const FAILED_COUNTER: Item<u64> = Item::new("failed_proposals");
 pub const CANCELED_COUNTER: Item<u64> = Item::new("canceled_proposals");
+pub const EXPIRED_COUNTER: Item<u64> = Item::new("expired_proposals");
+pub const COUNTERED_COUNTER: Item<u64> = Item::new("countered_proposals");
+/// The number of proposals currently deferred by their receiver
+pub const DEFERRED_COUNTER: Item<u64> = Item::new("deferred_proposals");
+pub const DISSOLVED_COUNTER: Item<u64> = Item::new("dissolved_proposals");
+/// Maps a countered proposal id to the id of its counter-proposal
+pub const COUNTER_PROPOSALS: Map<u64, u64> = Map::new("counter_proposals");
+/// Maps an address pair, as returned by [pair_key], and a stage to the id of the accepted
+/// proposal that reached it
+pub const ACCEPTED_STAGES: Map<(&Addr, &Addr, &str), u64> = Map::new("accepted_stages");
+/// The swap of an accepted proposal's gift awaiting the pool's reply
+pub const PENDING_SWAP: Item<PendingSwap> = Item::new("pending_swap");
+/// The staking accounting of each validator gifts are delegated to
+pub const STAKING_POOLS: Map<&str, StakingPool> = Map::new("staking_pools");
+/// The time each batch of undelegated gifts can be claimed at, by validator and batch
+pub const UNBONDING_BATCHES: Map<(&str, u64), Timestamp> = Map::new("unbonding_batches");
+/// The native coins each contributor added to a proposal's gift, by proposal id and contributor
+pub const GIFT_CONTRIBUTIONS: Map<(u64, &Addr), Vec<Coin>> = Map::new("gift_contributions");
+/// The native coins escrowed for the proposals' gifts, by denom, checked against the escrow caps
+pub const ESCROWED_GIFTS: Map<&str, Uint128> = Map::new("escrowed_gifts");
+/// The fees accrued in the treasury as a gift of their native coin or CW20 token, by native
+/// denom or CW20 token address
+pub const TREASURY: Map<&str, Gift> = Map::new("treasury");
+/// The fees withdrawn from the treasury by the owner, by native denom or CW20 token address
+pub const WITHDRAWN_FEES: Map<&str, Uint128> = Map::new("withdrawn_fees");
+/// The certificate token ids minted for each accepted proposal
+pub const CERTIFICATES: Map<u64, Vec<String>> = Map::new("certificates");
+/// The prior versions of the proposals, by proposal id and revision number
+pub const PROPOSAL_REVISIONS: Map<(u64, u32), ProposalRevision> = Map::new("proposal_revisions");
 pub const PROPOSALS: IndexedMap<u64, Proposal, ProposalIndexes> = IndexedMap::new(
     "proposals",
     ProposalIndexes {
@@ -19,7 +50,7 @@ pub const PROPOSALS: IndexedMap<u64, Proposal, ProposalIndexes> = IndexedMap::ne
             "proposals__proposer",
         ),
         status_by_proposer: MultiIndex::new(
-            |_pk, p| (p.receiver.as_bytes().to_vec(), p.status.clone().into()),
+            |_pk, p| (p.proposer.as_bytes().to_vec(), p.status.clone().into()),
             "proposals",
             "proposals__status_by_proposer",
         ),
@@ -47,8 +78,123 @@ pub struct ProposalIndexes<'a> {
 /// The contract configuration.
 #[cw_serde]
 pub struct Config {
-    /// The fee that the owner will receive for each successful proposal
-    pub successful_proposal_fee: Coin,
+    /// The fees accepted for each proposal, any one of which is paid. It's credited to the
+    /// treasury for each successful proposal, proposals are free if empty.
+    pub successful_proposal_fees: Vec<Coin>,
+    /// The duration a proposal stays open when the proposer doesn't provide an expiration
+    pub default_proposal_duration: Option<Duration>,
+    /// The maximum duration a proposal can stay open
+    pub max_proposal_duration: Option<Duration>,
+    /// The cooling-off window during which an answer is provisional and can be revoked
+    pub finalization_delay: Option<Duration>,
+    /// The ordered relationship stages proposals go through, e.g. engagement then wedding.
+    /// A proposal for a stage requires an accepted proposal for the previous stage between
+    /// the same pair.
+    pub stages: Vec<String>,
+    /// The cw721 collection, instantiated by the contract, in which a certificate is minted to
+    /// both parties of every accepted proposal
+    pub certificate_collection: Option<Addr>,
+    /// The restrictions on the native coins given as gifts
+    #[serde(default)]
+    pub gift_policy: GiftPolicy,
+    /// The validator the escrowed gifts can be delegated to, staking is disabled if not set
+    pub staking: Option<StakingConfig>,
+    /// The pool contract gifts are swapped through when the receiver asks for another denom,
+    /// swapping is disabled if not set
+    pub swap_pool: Option<Addr>,
+    /// The fee charged on each denom of an accepted gift, in basis points. It's credited to
+    /// the treasury along with the proposal fee.
+    pub gift_fee_bps: Option<Decimal>,
+    /// How the fees of accepted proposals are split across recipients, they're credited to the
+    /// treasury if empty
+    #[serde(default)]
+    pub fee_shares: Vec<FeeShare>,
+}
+
+/// A share of the fees of accepted proposals.
+#[cw_serde]
+pub struct FeeShare {
+    pub recipient: FeeRecipient,
+    /// The share of the fees, in basis points. The weights of all the shares add up to 10000.
+    pub weight_bps: u16,
+}
+
+/// Where a share of the fees goes.
+#[cw_serde]
+pub enum FeeRecipient {
+    /// The share is sent to an address, e.g. the operator or a community pool
+    Address { address: Addr },
+    /// The share is burned
+    Burn,
+}
+
+impl fmt::Display for FeeRecipient {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            FeeRecipient::Address { address } => write!(f, "{address}"),
+            FeeRecipient::Burn => write!(f, "burn"),
+        }
+    }
+}
+
+/// The staking of escrowed gifts, set by the owner.
+#[cw_serde]
+pub struct StakingConfig {
+    /// The validator the gifts are delegated to
+    pub validator: String,
+    /// The chain's unbonding period, in seconds
+    pub unbonding_period: u64,
+}
+
+/// The delegation shared by all the proposals staking their gift with a validator.
+#[cw_serde]
+#[derive(Default)]
+pub struct StakingPool {
+    /// The shares of the proposals still staking, each proposal owning its share of the
+    /// delegation. They're minted 1:1 with the delegated tokens until the validator is slashed.
+    pub total_shares: Uint128,
+    /// The rewards earned per share since the pool was created
+    pub reward_index: Decimal256,
+    /// The tokens of the gifts resolved since the last undelegation, undelegated together with
+    /// the next batch
+    pub queued: Uint128,
+    /// The batch the gifts resolved since the last undelegation belong to
+    pub batch: u64,
+    /// When the contract last undelegated from the validator
+    pub undelegated_at: Option<Timestamp>,
+    /// The chain's unbonding period when the gifts were last delegated, in seconds
+    pub unbonding_period: u64,
+}
+
+/// The restrictions on the native coins given as gifts, keeping spam denoms and dust out of
+/// the contract. Nothing is restricted by default.
+#[cw_serde]
+#[derive(Default)]
+pub struct GiftPolicy {
+    /// The denoms accepted as gift, any denom is accepted if empty
+    pub allowed_denoms: Vec<String>,
+    /// The minimum amount of each deposit and maximum amount of a proposal's gift, per denom
+    pub limits: Vec<DenomLimit>,
+    /// The maximum number of denoms in a proposal's gift
+    pub max_denoms: Option<u32>,
+    /// The maximum amount of each denom escrowed for the gifts of all proposals
+    pub escrow_caps: Vec<Coin>,
+    /// The CW20 token contracts whose `Receive` hook is accepted, none if empty
+    #[serde(default)]
+    pub allowed_cw20_tokens: Vec<Addr>,
+    /// The CW721 collections whose `ReceiveNft` hook is accepted, none if empty
+    #[serde(default)]
+    pub allowed_cw721_collections: Vec<Addr>,
+}
+
+/// The amounts of a denom accepted as gift.
+#[cw_serde]
+pub struct DenomLimit {
+    pub denom: String,
+    /// The minimum amount added to a gift at once
+    pub min: Option<Uint128>,
+    /// The maximum amount in a proposal's gift
+    pub max: Option<Uint128>,
 }
 
 #[cw_serde]
@@ -56,7 +202,8 @@ pub struct Proposal {
     pub id: u64,
     pub proposer: Addr,
     pub receiver: Addr,
-    pub gift: Vec<Coin>,
+    pub gift: Vec<Gift>,
+    /// The proposal fee paid by the proposer, zero if the proposal was free
     pub fee: Coin,
     pub title: Option<String>,
     pub speech: Option<String>,
@@ -64,6 +211,284 @@ pub struct Proposal {
     pub status: ProposalStatus,
     pub created_at: u64,
     pub replied_at: Option<u64>,
+    pub canceled_at: Option<u64>,
+    /// After this moment the proposal can no longer be answered and the proposer can be refunded
+    pub expires_at: Expiration,
+    /// When a provisional answer can be finalized, if the contract has a finalization delay
+    pub finalizes_at: Option<Expiration>,
+    /// The decision deadline requested by the receiver when deferring the proposal
+    pub deferred_until: Option<Expiration>,
+    /// The proposal this one is a counter-proposal to, if any
+    pub parent_id: Option<u64>,
+    /// The relationship stage of the proposal, if the contract has stages
+    pub stage: Option<String>,
+    /// The dissolution of the accepted proposal, once requested by either party
+    pub dissolution: Option<Dissolution>,
+    /// The number of times the proposer updated the proposal
+    pub revision: u32,
+    /// When the proposal is revealed to the receiver, until then its title, speech and gift
+    /// are hidden and it can't be answered
+    pub reveal_at: Option<Expiration>,
+    /// The release of the gift over time once the proposal is accepted, if any
+    pub vesting: Option<Vesting>,
+    /// What the receiver chose to do with the gift when accepting, the receiver keeps it if
+    /// not set
+    pub gift_disposition: Option<GiftDisposition>,
+    /// The delegation of the gift while the proposal is pending, if the proposer opted in
+    pub staking: Option<GiftStaking>,
+    /// The denom the receiver chose to receive the native coins of the gift in when accepting
+    pub receive_as: Option<ReceiveAs>,
+    /// The coins the receiver deposits when accepting, sent to the proposer in exchange for
+    /// the gift
+    #[serde(default)]
+    pub counter_gift: Vec<Coin>,
+    /// The alternative gift bundles the receiver picks one of when accepting. They are part of
+    /// `gift`, which holds everything in escrow.
+    #[serde(default)]
+    pub gift_options: Vec<Vec<Gift>>,
+    /// The index of the gift option picked by the receiver when accepting
+    pub gift_option: Option<u32>,
+    /// The fee charged on the gift when accepted, no longer part of `gift`
+    #[serde(default)]
+    pub gift_fee: Vec<Gift>,
+}
+
+impl Proposal {
+    /// Returns whether the proposal was revealed to the receiver.
+    pub fn is_revealed(&self, block: &BlockInfo) -> bool {
+        self.reveal_at
+            .is_none_or(|reveal_at| reveal_at.is_expired(block))
+    }
+
+    /// Hides the title, speech, gifts, counter-gift and gift delegation of the proposal if it
+    /// wasn't revealed yet.
+    pub fn redacted(mut self, block: &BlockInfo) -> Self {
+        if !self.is_revealed(block) {
+            self.title = None;
+            self.speech = None;
+            self.gift = vec![];
+            self.vesting = None;
+            self.counter_gift = vec![];
+            self.gift_options = vec![];
+            self.staking = None;
+        }
+        self
+    }
+}
+
+/// A part of a proposal's gift, held in escrow by the contract.
+#[cw_serde]
+pub enum Gift {
+    /// Native coins sent along with the proposal
+    Native(Coin),
+    /// CW20 tokens sent through the token's `Send` message
+    Cw20(Cw20CoinVerified),
+    /// A CW721 token, e.g. a ring, sent through the collection's `SendNft` message
+    Cw721 { contract: Addr, token_id: String },
+}
+
+impl Gift {
+    /// Returns the native denom or the CW20 token address of a fungible gift, or `None` for
+    /// an NFT.
+    pub fn denom(&self) -> Option<String> {
+        match self {
+            Gift::Native(coin) => Some(coin.denom.clone()),
+            Gift::Cw20(coin) => Some(coin.address.to_string()),
+            Gift::Cw721 { .. } => None,
+        }
+    }
+
+    /// Returns the amount of a fungible gift, or `None` for an NFT.
+    pub fn amount(&self) -> Option<Uint128> {
+        match self {
+            Gift::Native(coin) => Some(coin.amount),
+            Gift::Cw20(coin) => Some(coin.amount),
+            Gift::Cw721 { .. } => None,
+        }
+    }
+
+    /// Returns the same fungible gift with the given amount. NFTs are returned as is.
+    pub fn with_amount(&self, amount: Uint128) -> Gift {
+        match self {
+            Gift::Native(coin) => Gift::Native(Coin {
+                denom: coin.denom.clone(),
+                amount,
+            }),
+            Gift::Cw20(coin) => Gift::Cw20(Cw20CoinVerified {
+                address: coin.address.clone(),
+                amount,
+            }),
+            Gift::Cw721 { .. } => self.clone(),
+        }
+    }
+}
+
+impl fmt::Display for Gift {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Gift::Native(coin) => write!(f, "{coin}"),
+            Gift::Cw20(coin) => write!(f, "{}:{}", coin.address, coin.amount),
+            Gift::Cw721 { contract, token_id } => write!(f, "{contract}/{token_id}"),
+        }
+    }
+}
+
+/// How the fungible part of a gift is released to the receiver once the proposal is accepted.
+/// NFTs are transferred on acceptance.
+#[cw_serde]
+pub enum VestingSchedule {
+    /// The whole gift is released once the duration, in seconds, passed since the acceptance
+    Cliff { duration: u64 },
+    /// The gift is released linearly over the duration, in seconds, since the acceptance
+    Linear { duration: u64 },
+    /// Shares of the gift are released on the given dates, e.g. on anniversaries
+    Tranches(Vec<Tranche>),
+}
+
+/// A share of a gift released on a given date.
+#[cw_serde]
+pub struct Tranche {
+    pub unlocks_at: Timestamp,
+    /// The share of the gift, in basis points
+    pub share_bps: u16,
+}
+
+/// The release of a gift over time.
+#[cw_serde]
+pub struct Vesting {
+    pub schedule: VestingSchedule,
+    /// When the schedule started, i.e. when the proposal was accepted
+    pub started_at: Option<Timestamp>,
+    /// The part of the gift already claimed by the receiver
+    pub claimed: Vec<Gift>,
+    /// When the release was stopped by the dissolution of the proposal, the part of the gift
+    /// not vested by then went back to the proposer
+    #[serde(default)]
+    pub stopped_at: Option<Timestamp>,
+}
+
+/// The denom the native coins of a gift are swapped into on acceptance.
+#[cw_serde]
+pub struct ReceiveAs {
+    pub denom: String,
+    /// The minimum amount of the denom the receiver gets in total, the acceptance fails
+    /// otherwise
+    pub min_out: Uint128,
+}
+
+/// A swap of a gift in flight, settled when the pool replies.
+#[cw_serde]
+pub struct PendingSwap {
+    pub proposal_id: u64,
+    pub recipient: Addr,
+    pub denom: String,
+    pub min_out: Uint128,
+    /// The contract's balance of the denom before the swap
+    pub balance_before: Uint128,
+    /// The part of the gift already in the denom, sent without swapping
+    pub received_directly: Uint128,
+}
+
+/// The delegation of a proposal's gift in the staking denom.
+#[cw_serde]
+pub struct GiftStaking {
+    pub validator: String,
+    /// The delegated part of the gift
+    pub amount: Coin,
+    /// The gift's shares of the pool's delegation
+    pub shares: Uint128,
+    /// The pool's reward index when the rewards of the gift were last accrued
+    pub reward_index: Decimal256,
+    /// The rewards earned by the gift so far
+    pub rewards: Uint128,
+    /// The unbonding period when the gift was staked, in seconds
+    pub unbonding_period: u64,
+    /// The undelegated gift, set once the proposal is resolved
+    pub unbonding: Option<Unbonding>,
+}
+
+/// The undelegation of a staked gift, paid out once the unbonding period of its batch is over.
+#[cw_serde]
+pub struct Unbonding {
+    /// The parts of the gift waiting for the unbonding period, by recipient. They're reduced in
+    /// proportion if the validator was slashed.
+    pub payouts: Vec<UnbondingPayout>,
+    /// The part of the gift fee charged on the staked gift, paid once unbonded
+    #[serde(default)]
+    pub fee: Option<Coin>,
+    /// The batch of the validator's pool the gift is undelegated with
+    pub batch: u64,
+    pub claimed: bool,
+}
+
+#[cw_serde]
+pub struct UnbondingPayout {
+    pub recipient: Addr,
+    pub amount: Coin,
+}
+
+/// What happens to the gift of an accepted proposal, chosen by the receiver.
+#[cw_serde]
+pub enum GiftDisposition {
+    /// The receiver keeps the gift
+    Keep,
+    /// The gift is sent to another address, e.g. a cold wallet
+    SendTo { address: Addr },
+    /// A share of the fungible part of the gift, in basis points, is sent to another address,
+    /// e.g. a charity, the receiver keeps the rest
+    Split { address: Addr, share_bps: u16 },
+    /// The gift is returned to the proposer, contributions to their contributors
+    Decline,
+}
+
+impl fmt::Display for GiftDisposition {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            GiftDisposition::Keep => write!(f, "keep"),
+            GiftDisposition::SendTo { address } => write!(f, "send_to:{address}"),
+            GiftDisposition::Split { address, share_bps } => {
+                write!(f, "split:{address}:{share_bps}")
+            }
+            GiftDisposition::Decline => write!(f, "decline"),
+        }
+    }
+}
+
+/// A prior version of a proposal, stored when the proposer updates it.
+#[cw_serde]
+pub struct ProposalRevision {
+    pub revision: u32,
+    pub title: Option<String>,
+    pub speech: Option<String>,
+    pub gift: Vec<Gift>,
+    /// Block height at which this version was replaced
+    pub replaced_at: u64,
+}
+
+/// The mutual dissolution of an accepted proposal.
+#[cw_serde]
+pub struct Dissolution {
+    /// The party that requested the dissolution
+    pub initiator: Addr,
+    /// The share of the gift, in basis points, the receiver returns to the proposer
+    pub gift_return_bps: u16,
+    /// The coins the receiver returns to the proposer, the share of the gift released to the
+    /// receiver when the dissolution was requested
+    #[serde(default)]
+    pub gift_return: Vec<Coin>,
+    /// Block height at request
+    pub requested_at: u64,
+    /// Block height at confirmation by the other party
+    pub dissolved_at: Option<u64>,
+}
+
+/// Returns the key of an address pair, regardless of which address is the proposer.
+pub fn pair_key<'a>(a: &'a Addr, b: &'a Addr) -> (&'a Addr, &'a Addr) {
+    if a <= b {
+        (a, b)
+    } else {
+        (b, a)
+    }
 }
 
 #[cw_serde]
@@ -71,6 +496,13 @@ pub enum ProposalStatus {
     Pending,
     Yes,
     No,
+    Expired,
+    Canceled,
+    ProvisionalYes,
+    ProvisionalNo,
+    Countered,
+    Deferred,
+    Dissolved,
 }
 
 impl From<ProposalStatus> for u8 {
@@ -79,6 +511,13 @@ impl From<ProposalStatus> for u8 {
             ProposalStatus::Pending => 0,
             ProposalStatus::Yes => 1,
             ProposalStatus::No => 2,
+            ProposalStatus::Expired => 3,
+            ProposalStatus::Canceled => 4,
+            ProposalStatus::ProvisionalYes => 5,
+            ProposalStatus::ProvisionalNo => 6,
+            ProposalStatus::Countered => 7,
+            ProposalStatus::Deferred => 8,
+            ProposalStatus::Dissolved => 9,
         }
     }
 }
@@ -89,6 +528,13 @@ impl fmt::Display for ProposalStatus {
             ProposalStatus::Pending => write!(f, "Pending"),
             ProposalStatus::Yes => write!(f, "Yes"),
             ProposalStatus::No => write!(f, "No"),
+            ProposalStatus::Expired => write!(f, "Expired"),
+            ProposalStatus::Canceled => write!(f, "Canceled"),
+            ProposalStatus::ProvisionalYes => write!(f, "ProvisionalYes"),
+            ProposalStatus::ProvisionalNo => write!(f, "ProvisionalNo"),
+            ProposalStatus::Countered => write!(f, "Countered"),
+            ProposalStatus::Deferred => write!(f, "Deferred"),
+            ProposalStatus::Dissolved => write!(f, "Dissolved"),
         }
     }
 }
@@ -1,13 +1,39 @@
-use cosmwasm_std::{coin, Addr, Coin, Empty, Order, StdResult, Timestamp, Uint128};
+use cosmwasm_schema::cw_serde;
+use cosmwasm_std::{
+    coin, to_json_binary, Addr, BankMsg, Binary, Coin, Decimal, Empty, Order, Response, StdError,
+    StdResult, Timestamp, Uint128, Validator,
+};
 use cw_multi_test::{
-    App, AppBuilder, AppResponse, BankKeeper, Contract, ContractWrapper, Executor, MockApiBech32,
-    WasmKeeper,
+    App, AppBuilder, AppResponse, BankKeeper, BankSudo, Contract, ContractWrapper, Executor,
+    MockApiBech32, StakingInfo, StakingSudo, SudoMsg, WasmKeeper,
 };
+use cw_storage_plus::Item;
 
+use cw20::{BalanceResponse, Cw20Coin, Cw20ExecuteMsg, Cw20QueryMsg};
+use cw721::extension::Cw721OnchainExtensions;
+use cw721::msg::{
+    Cw721ExecuteMsg, Cw721InstantiateMsg, Cw721QueryMsg, NftInfoResponse, OwnerOfResponse,
+};
+use cw721::traits::{Cw721Execute, Cw721Query};
+use cw721::{
+    DefaultOptionalCollectionExtension, DefaultOptionalCollectionExtensionMsg,
+    DefaultOptionalNftExtension, DefaultOptionalNftExtensionMsg,
+};
+use cw_utils::{Duration, Expiration};
+use proposal_manager::helpers::aggregate_coins;
 use proposal_manager::msg::{
-    ExecuteMsg, InstantiateMsg, ProposalBy, ProposalsResponse, QueryMsg, Status,
+    CertificatesResponse, ExecuteMsg, GiftContributorsResponse, GiftFeeResponse, InstantiateMsg,
+    MigrateMsg, PoolExecuteMsg, ProposalBy, ProposalRevisionsResponse, ProposalsResponse, QueryMsg,
+    ReceiveMsg, StageResponse, Status, TreasuryResponse, VestingResponse,
+};
+use proposal_manager::proposal::migration::{
+    LegacyConfig, LegacyProposal, LEGACY_CONFIG, LEGACY_PROPOSALS,
+};
+use proposal_manager::proposal::state::{
+    Config, FeeShare, GiftDisposition, GiftPolicy, Proposal, ProposalStatus, ReceiveAs,
+    StakingConfig, VestingSchedule, COUNTERED_COUNTER, DEFERRED_COUNTER, DISSOLVED_COUNTER,
+    EXPIRED_COUNTER, FAILED_COUNTER, PROPOSAL_COUNTER, SUCCESSFUL_COUNTER,
 };
-use proposal_manager::proposal::state::{Config, Proposal, ProposalStatus};
 
 type WasmApp = App<BankKeeper, MockApiBech32>;
 
@@ -17,7 +43,94 @@ pub fn proposal_manager_contract() -> Box<dyn Contract<Empty>> {
         proposal_manager::contract::instantiate,
         proposal_manager::contract::query,
     )
-    .with_migrate(proposal_manager::contract::migrate);
+    .with_migrate(proposal_manager::contract::migrate)
+    .with_reply(proposal_manager::contract::reply);
+
+    Box::new(contract)
+}
+
+pub fn cw20_base_contract() -> Box<dyn Contract<Empty>> {
+    let contract = ContractWrapper::new(
+        cw20_base::contract::execute,
+        cw20_base::contract::instantiate,
+        cw20_base::contract::query,
+    );
+
+    Box::new(contract)
+}
+
+pub fn cw721_base_contract() -> Box<dyn Contract<Empty>> {
+    let contract = ContractWrapper::new(
+        cw721_base::entry::execute,
+        cw721_base::entry::instantiate,
+        cw721_base::entry::query,
+    );
+
+    Box::new(contract)
+}
+
+/// A non-transferable cw721 collection with onchain metadata, as used for the certificates.
+pub fn cw721_soulbound_contract() -> Box<dyn Contract<Empty>> {
+    let contract = ContractWrapper::new(
+        |deps,
+         env,
+         info,
+         msg: Cw721ExecuteMsg<
+            DefaultOptionalNftExtensionMsg,
+            DefaultOptionalCollectionExtensionMsg,
+            Empty,
+        >| match msg {
+            Cw721ExecuteMsg::TransferNft { .. } | Cw721ExecuteMsg::SendNft { .. } => {
+                Err(StdError::generic_err("certificates are soulbound").into())
+            }
+            msg => Cw721OnchainExtensions::default().execute(deps, &env, &info, msg),
+        },
+        |deps, env, info, msg: Cw721InstantiateMsg<DefaultOptionalCollectionExtensionMsg>| {
+            Cw721OnchainExtensions::default().instantiate(deps, &env, &info, msg)
+        },
+        |deps,
+         env,
+         msg: Cw721QueryMsg<
+            DefaultOptionalNftExtension,
+            DefaultOptionalCollectionExtension,
+            Empty,
+        >| { Cw721OnchainExtensions::default().query(deps, &env, msg) },
+    );
+
+    Box::new(contract)
+}
+
+#[cw_serde]
+pub struct MockPoolInstantiateMsg {
+    /// The amount of ask denom paid for each offered coin
+    pub rate: Decimal,
+}
+
+const MOCK_POOL_RATE: Item<Decimal> = Item::new("rate");
+
+/// A pool swapping any offered coins into the ask denom at a fixed rate.
+pub fn mock_pool_contract() -> Box<dyn Contract<Empty>> {
+    let contract = ContractWrapper::new(
+        |deps, _env, info, msg: PoolExecuteMsg| -> StdResult<Response> {
+            let PoolExecuteMsg::Swap { ask_denom } = msg;
+            let rate = MOCK_POOL_RATE.load(deps.storage)?;
+            let amount = info
+                .funds
+                .iter()
+                .map(|offer| offer.amount.mul_floor(rate))
+                .sum::<Uint128>();
+
+            Ok(Response::new().add_message(BankMsg::Send {
+                to_address: info.sender.to_string(),
+                amount: vec![Coin::new(amount, ask_denom)],
+            }))
+        },
+        |deps, _env, _info, msg: MockPoolInstantiateMsg| -> StdResult<Response> {
+            MOCK_POOL_RATE.save(deps.storage, &msg.rate)?;
+            Ok(Response::new())
+        },
+        |_deps, _env, _msg: Empty| -> StdResult<Binary> { to_json_binary(&Empty {}) },
+    );
 
     Box::new(contract)
 }
@@ -26,6 +139,9 @@ pub struct TestingSuite {
     app: WasmApp,
     pub senders: [Addr; 4],
     pub proposal_manager_contract_addr: Addr,
+    pub cw20_contract_addr: Addr,
+    pub cw721_contract_addr: Addr,
+    pub swap_pool_addr: Addr,
 }
 
 // helpers
@@ -53,6 +169,15 @@ impl TestingSuite {
 
         self
     }
+
+    #[track_caller]
+    pub fn add_seconds(&mut self, seconds: u64) -> &mut Self {
+        let mut block_info = self.app.block_info();
+        block_info.time = block_info.time.plus_seconds(seconds);
+        self.app.set_block(block_info);
+
+        self
+    }
 }
 
 // instantiate
@@ -92,16 +217,36 @@ impl TestingSuite {
             app,
             senders: senders.try_into().unwrap(),
             proposal_manager_contract_addr: Addr::unchecked(""),
+            cw20_contract_addr: Addr::unchecked(""),
+            cw721_contract_addr: Addr::unchecked(""),
+            swap_pool_addr: Addr::unchecked(""),
         }
     }
 
     #[track_caller]
     pub fn instantiate_proposal_contract(&mut self, owner: Option<String>) -> &mut Self {
-        let msg = InstantiateMsg {
+        self.instantiate_proposal_contract_with_msg(Self::default_instantiate_msg(owner))
+    }
+
+    pub fn default_instantiate_msg(owner: Option<String>) -> InstantiateMsg {
+        InstantiateMsg {
             owner,
-            successful_proposal_fee: coin(100, "uom"),
-        };
+            successful_proposal_fees: vec![coin(100, "uom")],
+            default_proposal_duration: None,
+            max_proposal_duration: None,
+            finalization_delay: None,
+            stages: vec![],
+            certificate_code_id: None,
+            gift_policy: None,
+            staking: None,
+            swap_pool: None,
+            gift_fee_bps: None,
+            fee_shares: vec![],
+        }
+    }
 
+    #[track_caller]
+    pub fn instantiate_proposal_contract_with_msg(&mut self, msg: InstantiateMsg) -> &mut Self {
         let proposal_contract_code_id = self.app.store_code(proposal_manager_contract());
         let admin = self.admin();
 
@@ -119,6 +264,288 @@ impl TestingSuite {
 
         self
     }
+
+    /// Instantiates a CW20 token, giving each sender the given balance.
+    #[track_caller]
+    pub fn instantiate_cw20_contract(&mut self, initial_balance: u128) -> &mut Self {
+        let cw20_code_id = self.app.store_code(cw20_base_contract());
+        let admin = self.admin();
+
+        self.cw20_contract_addr = self
+            .app
+            .instantiate_contract(
+                cw20_code_id,
+                admin.clone(),
+                &cw20_base::msg::InstantiateMsg {
+                    name: "Love Token".to_string(),
+                    symbol: "LOVE".to_string(),
+                    decimals: 6,
+                    initial_balances: self
+                        .senders
+                        .iter()
+                        .map(|sender| Cw20Coin {
+                            address: sender.to_string(),
+                            amount: Uint128::new(initial_balance),
+                        })
+                        .collect(),
+                    mint: None,
+                    marketing: None,
+                },
+                &[],
+                "cw20-token",
+                Some(admin.into_string()),
+            )
+            .unwrap();
+
+        self
+    }
+
+    /// Sets up the staking module with the contract's staking denom and adds a validator.
+    #[track_caller]
+    pub fn add_validator(&mut self, validator: &str, unbonding_time: u64) -> &mut Self {
+        let block = self.app.block_info();
+        self.app.init_modules(|router, api, storage| {
+            router
+                .staking
+                .setup(
+                    storage,
+                    StakingInfo {
+                        bonded_denom: "uom".to_string(),
+                        unbonding_time,
+                        apr: Decimal::percent(10),
+                    },
+                )
+                .unwrap();
+            router
+                .staking
+                .add_validator(
+                    api,
+                    storage,
+                    &block,
+                    Validator::create(
+                        validator.to_string(),
+                        Decimal::zero(),
+                        Decimal::one(),
+                        Decimal::one(),
+                    ),
+                )
+                .unwrap();
+        });
+
+        self
+    }
+
+    /// Slashes the given share of the validator's stake.
+    #[track_caller]
+    pub fn slash_validator(&mut self, validator: &str, percentage: Decimal) -> &mut Self {
+        self.app
+            .sudo(SudoMsg::Staking(StakingSudo::Slash {
+                validator: validator.to_string(),
+                percentage,
+            }))
+            .unwrap();
+
+        self
+    }
+
+    /// Instantiates a mock pool swapping at the given rate, funded with the given liquidity.
+    #[track_caller]
+    pub fn instantiate_swap_pool(&mut self, rate: Decimal, liquidity: Vec<Coin>) -> &mut Self {
+        let pool_code_id = self.app.store_code(mock_pool_contract());
+        let admin = self.admin();
+
+        self.swap_pool_addr = self
+            .app
+            .instantiate_contract(
+                pool_code_id,
+                admin,
+                &MockPoolInstantiateMsg { rate },
+                &[],
+                "swap-pool",
+                None,
+            )
+            .unwrap();
+        self.app
+            .sudo(SudoMsg::Bank(BankSudo::Mint {
+                to_address: self.swap_pool_addr.to_string(),
+                amount: liquidity,
+            }))
+            .unwrap();
+
+        self
+    }
+
+    /// Stores the code of the certificate collection, returning its code id.
+    #[track_caller]
+    pub fn store_certificate_code(&mut self) -> u64 {
+        self.app.store_code(cw721_soulbound_contract())
+    }
+
+    /// Adds the suite's CW20 token and CW721 collection, once instantiated, to the tokens the
+    /// contract accepts as gift.
+    #[track_caller]
+    pub fn allow_gift_tokens(&mut self) -> &mut Self {
+        let mut gift_policy = self
+            .app
+            .wrap()
+            .query_wasm_smart::<Config>(&self.proposal_manager_contract_addr, &QueryMsg::Config {})
+            .unwrap()
+            .gift_policy;
+        if !self.cw20_contract_addr.as_str().is_empty() {
+            gift_policy
+                .allowed_cw20_tokens
+                .push(self.cw20_contract_addr.clone());
+        }
+        if !self.cw721_contract_addr.as_str().is_empty() {
+            gift_policy
+                .allowed_cw721_collections
+                .push(self.cw721_contract_addr.clone());
+        }
+
+        let admin = self.admin();
+        self.update_config(
+            &admin,
+            ConfigUpdate {
+                gift_policy: Some(gift_policy),
+                ..Default::default()
+            },
+            |r: Result<AppResponse, anyhow::Error>| {
+                r.unwrap();
+            },
+        )
+    }
+
+    /// Instantiates a CW721 collection, minting the given tokens to their owners.
+    #[track_caller]
+    pub fn instantiate_cw721_contract(&mut self, tokens: &[(&str, &Addr)]) -> &mut Self {
+        let cw721_code_id = self.app.store_code(cw721_base_contract());
+        let admin = self.admin();
+
+        self.cw721_contract_addr = self
+            .app
+            .instantiate_contract(
+                cw721_code_id,
+                admin.clone(),
+                &cw721_base::msg::InstantiateMsg {
+                    name: "Rings".to_string(),
+                    symbol: "RING".to_string(),
+                    collection_info_extension: None,
+                    minter: None,
+                    creator: None,
+                    withdraw_address: None,
+                },
+                &[],
+                "cw721-collection",
+                Some(admin.to_string()),
+            )
+            .unwrap();
+
+        for (token_id, owner) in tokens {
+            self.app
+                .execute_contract(
+                    admin.clone(),
+                    self.cw721_contract_addr.clone(),
+                    &cw721_base::msg::ExecuteMsg::Mint {
+                        token_id: token_id.to_string(),
+                        owner: owner.to_string(),
+                        token_uri: None,
+                        extension: None,
+                    },
+                    &[],
+                )
+                .unwrap();
+        }
+
+        self
+    }
+}
+
+/// The fields of [ExecuteMsg::CreateProposal], defaulting to a proposal without gift or options.
+#[derive(Default)]
+pub struct NewProposal {
+    pub title: Option<String>,
+    pub speech: Option<String>,
+    pub receiver: String,
+    pub gift: Vec<Coin>,
+    pub expires_at: Option<Expiration>,
+    pub stage: Option<String>,
+    pub reveal_at: Option<Expiration>,
+    pub vesting: Option<VestingSchedule>,
+    pub stake_gift: bool,
+    pub counter_gift: Vec<Coin>,
+    pub gift_options: Vec<Vec<Coin>>,
+}
+
+impl From<NewProposal> for ExecuteMsg {
+    fn from(proposal: NewProposal) -> Self {
+        ExecuteMsg::CreateProposal {
+            title: proposal.title,
+            speech: proposal.speech,
+            receiver: proposal.receiver,
+            gift: proposal.gift,
+            expires_at: proposal.expires_at,
+            stage: proposal.stage,
+            reveal_at: proposal.reveal_at,
+            vesting: proposal.vesting,
+            stake_gift: proposal.stake_gift,
+            counter_gift: proposal.counter_gift,
+            gift_options: proposal.gift_options,
+        }
+    }
+}
+
+/// The fields of [ExecuteMsg::Yes], defaulting to keeping the gift as is.
+#[derive(Default)]
+pub struct YesAnswer {
+    pub id: u64,
+    pub reply: Option<String>,
+    pub gift_disposition: Option<GiftDisposition>,
+    pub receive_as: Option<ReceiveAs>,
+    pub gift_option: Option<u32>,
+}
+
+impl From<YesAnswer> for ExecuteMsg {
+    fn from(answer: YesAnswer) -> Self {
+        ExecuteMsg::Yes {
+            id: answer.id,
+            reply: answer.reply,
+            gift_disposition: answer.gift_disposition,
+            receive_as: answer.receive_as,
+            gift_option: answer.gift_option,
+        }
+    }
+}
+
+/// The fields of [ExecuteMsg::UpdateConfig], defaulting to no updates.
+#[derive(Default)]
+pub struct ConfigUpdate {
+    pub successful_proposal_fees: Option<Vec<Coin>>,
+    pub default_proposal_duration: Option<Duration>,
+    pub max_proposal_duration: Option<Duration>,
+    pub finalization_delay: Option<Duration>,
+    pub stages: Option<Vec<String>>,
+    pub gift_policy: Option<GiftPolicy>,
+    pub staking: Option<StakingConfig>,
+    pub swap_pool: Option<String>,
+    pub gift_fee_bps: Option<Decimal>,
+    pub fee_shares: Option<Vec<FeeShare>>,
+}
+
+impl From<ConfigUpdate> for ExecuteMsg {
+    fn from(update: ConfigUpdate) -> Self {
+        ExecuteMsg::UpdateConfig {
+            successful_proposal_fees: update.successful_proposal_fees,
+            default_proposal_duration: update.default_proposal_duration,
+            max_proposal_duration: update.max_proposal_duration,
+            finalization_delay: update.finalization_delay,
+            stages: update.stages,
+            gift_policy: update.gift_policy,
+            staking: update.staking,
+            swap_pool: update.swap_pool,
+            gift_fee_bps: update.gift_fee_bps,
+            fee_shares: update.fee_shares,
+        }
+    }
 }
 
 pub trait ResultHandler {
@@ -153,6 +580,7 @@ impl TestingSuite {
         self
     }
 
+    #[allow(clippy::too_many_arguments)]
     #[track_caller]
     pub fn create_proposal(
         &mut self,
@@ -164,13 +592,196 @@ impl TestingSuite {
         funds: &[Coin],
         result: impl ResultHandler,
     ) -> &mut Self {
-        self.execute_contract(
+        self.create_proposal_with(
             sender,
-            ExecuteMsg::CreateProposal {
+            NewProposal {
                 title,
                 speech,
                 receiver,
                 gift,
+                ..Default::default()
+            },
+            funds,
+            result,
+        )
+    }
+
+    #[track_caller]
+    pub fn create_proposal_with(
+        &mut self,
+        sender: &Addr,
+        proposal: NewProposal,
+        funds: &[Coin],
+        result: impl ResultHandler,
+    ) -> &mut Self {
+        self.execute_contract(sender, proposal.into(), funds, result)
+    }
+
+    /// Executes any message on the proposal manager, e.g. a receive hook called directly.
+    #[track_caller]
+    pub fn execute(
+        &mut self,
+        sender: &Addr,
+        msg: ExecuteMsg,
+        funds: &[Coin],
+        result: impl ResultHandler,
+    ) -> &mut Self {
+        self.execute_contract(sender, msg, funds, result)
+    }
+
+    /// Sends CW20 tokens to the proposal manager along with the given message.
+    #[track_caller]
+    pub fn send_cw20(
+        &mut self,
+        sender: &Addr,
+        amount: u128,
+        msg: ReceiveMsg,
+        result: impl ResultHandler,
+    ) -> &mut Self {
+        result.handle_result(self.app.execute_contract(
+            sender.clone(),
+            self.cw20_contract_addr.clone(),
+            &Cw20ExecuteMsg::Send {
+                contract: self.proposal_manager_contract_addr.to_string(),
+                amount: Uint128::new(amount),
+                msg: to_json_binary(&msg).unwrap(),
+            },
+            &[],
+        ));
+
+        self
+    }
+
+    /// Sends a CW721 token to the proposal manager along with the given message.
+    #[track_caller]
+    pub fn send_nft(
+        &mut self,
+        sender: &Addr,
+        token_id: &str,
+        msg: ReceiveMsg,
+        result: impl ResultHandler,
+    ) -> &mut Self {
+        result.handle_result(self.app.execute_contract(
+            sender.clone(),
+            self.cw721_contract_addr.clone(),
+            &cw721_base::msg::ExecuteMsg::SendNft {
+                contract: self.proposal_manager_contract_addr.to_string(),
+                token_id: token_id.to_string(),
+                msg: to_json_binary(&msg).unwrap(),
+            },
+            &[],
+        ));
+
+        self
+    }
+
+    /// Transfers a CW721 token of the given collection to the recipient.
+    #[track_caller]
+    pub fn transfer_nft(
+        &mut self,
+        sender: &Addr,
+        collection: &Addr,
+        token_id: &str,
+        recipient: &Addr,
+        result: impl ResultHandler,
+    ) -> &mut Self {
+        result.handle_result(self.app.execute_contract(
+            sender.clone(),
+            collection.clone(),
+            &cw721_base::msg::ExecuteMsg::TransferNft {
+                recipient: recipient.to_string(),
+                token_id: token_id.to_string(),
+            },
+            &[],
+        ));
+
+        self
+    }
+
+    #[track_caller]
+    pub fn contribute_gift(
+        &mut self,
+        sender: &Addr,
+        id: u64,
+        funds: &[Coin],
+        result: impl ResultHandler,
+    ) -> &mut Self {
+        self.execute_contract(sender, ExecuteMsg::ContributeGift { id }, funds, result)
+    }
+
+    #[track_caller]
+    pub fn claim_unbonded(
+        &mut self,
+        sender: &Addr,
+        id: u64,
+        result: impl ResultHandler,
+    ) -> &mut Self {
+        self.execute_contract(sender, ExecuteMsg::ClaimUnbonded { id }, &[], result)
+    }
+
+    #[track_caller]
+    pub fn undelegate_queued(
+        &mut self,
+        sender: &Addr,
+        validator: &str,
+        result: impl ResultHandler,
+    ) -> &mut Self {
+        self.execute_contract(
+            sender,
+            ExecuteMsg::UndelegateQueued {
+                validator: validator.to_string(),
+            },
+            &[],
+            result,
+        )
+    }
+
+    #[track_caller]
+    pub fn claim_gift(&mut self, sender: &Addr, id: u64, result: impl ResultHandler) -> &mut Self {
+        self.execute_contract(sender, ExecuteMsg::ClaimGift { id }, &[], result)
+    }
+
+    #[track_caller]
+    pub fn withdraw_fees(
+        &mut self,
+        sender: &Addr,
+        denoms: Vec<String>,
+        to: Option<String>,
+        result: impl ResultHandler,
+    ) -> &mut Self {
+        self.execute_contract(sender, ExecuteMsg::WithdrawFees { denoms, to }, &[], result)
+    }
+
+    #[track_caller]
+    pub fn migrate_proposals(
+        &mut self,
+        sender: &Addr,
+        limit: Option<u32>,
+        result: impl ResultHandler,
+    ) -> &mut Self {
+        self.execute_contract(sender, ExecuteMsg::MigrateProposals { limit }, &[], result)
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    #[track_caller]
+    pub fn update_proposal(
+        &mut self,
+        sender: &Addr,
+        id: u64,
+        title: Option<String>,
+        speech: Option<String>,
+        add_gift: Vec<Coin>,
+        funds: &[Coin],
+        result: impl ResultHandler,
+    ) -> &mut Self {
+        self.execute_contract(
+            sender,
+            ExecuteMsg::UpdateProposal {
+                id,
+                title,
+                speech,
+                add_gift,
+                gift_option: None,
             },
             funds,
             result,
@@ -195,7 +806,27 @@ impl TestingSuite {
         reply: Option<String>,
         result: impl ResultHandler,
     ) -> &mut Self {
-        self.execute_contract(sender, ExecuteMsg::Yes { id, reply }, &[], result)
+        self.say_yes_with(
+            sender,
+            YesAnswer {
+                id,
+                reply,
+                ..Default::default()
+            },
+            &[],
+            result,
+        )
+    }
+
+    #[track_caller]
+    pub fn say_yes_with(
+        &mut self,
+        sender: &Addr,
+        answer: YesAnswer,
+        funds: &[Coin],
+        result: impl ResultHandler,
+    ) -> &mut Self {
+        self.execute_contract(sender, answer.into(), funds, result)
     }
 
     #[track_caller]
@@ -210,22 +841,111 @@ impl TestingSuite {
     }
 
     #[track_caller]
-    pub fn update_config(
+    pub fn counter_propose(
         &mut self,
         sender: &Addr,
-        successful_proposal_fee: Option<Coin>,
+        id: u64,
+        speech: Option<String>,
+        gift: Vec<Coin>,
+        funds: &[Coin],
         result: impl ResultHandler,
     ) -> &mut Self {
         self.execute_contract(
             sender,
-            ExecuteMsg::UpdateConfig {
-                successful_proposal_fee,
+            ExecuteMsg::CounterPropose { id, speech, gift },
+            funds,
+            result,
+        )
+    }
+
+    #[track_caller]
+    pub fn defer(
+        &mut self,
+        sender: &Addr,
+        id: u64,
+        until: Expiration,
+        reply: Option<String>,
+        result: impl ResultHandler,
+    ) -> &mut Self {
+        self.execute_contract(sender, ExecuteMsg::Defer { id, until, reply }, &[], result)
+    }
+
+    #[track_caller]
+    pub fn revoke_answer(
+        &mut self,
+        sender: &Addr,
+        id: u64,
+        result: impl ResultHandler,
+    ) -> &mut Self {
+        self.execute_contract(sender, ExecuteMsg::RevokeAnswer { id }, &[], result)
+    }
+
+    #[track_caller]
+    pub fn finalize(&mut self, sender: &Addr, id: u64, result: impl ResultHandler) -> &mut Self {
+        self.execute_contract(sender, ExecuteMsg::Finalize { id }, &[], result)
+    }
+
+    #[track_caller]
+    pub fn expire_proposal(
+        &mut self,
+        sender: &Addr,
+        id: u64,
+        result: impl ResultHandler,
+    ) -> &mut Self {
+        self.execute_contract(sender, ExecuteMsg::ExpireProposal { id }, &[], result)
+    }
+
+    #[track_caller]
+    pub fn request_dissolution(
+        &mut self,
+        sender: &Addr,
+        id: u64,
+        gift_return_bps: u16,
+        funds: &[Coin],
+        result: impl ResultHandler,
+    ) -> &mut Self {
+        self.execute_contract(
+            sender,
+            ExecuteMsg::RequestDissolution {
+                id,
+                gift_return_bps,
             },
-            &[],
+            funds,
             result,
         )
     }
 
+    #[track_caller]
+    pub fn confirm_dissolution(
+        &mut self,
+        sender: &Addr,
+        id: u64,
+        funds: &[Coin],
+        result: impl ResultHandler,
+    ) -> &mut Self {
+        self.execute_contract(sender, ExecuteMsg::ConfirmDissolution { id }, funds, result)
+    }
+
+    #[track_caller]
+    pub fn cancel_dissolution(
+        &mut self,
+        sender: &Addr,
+        id: u64,
+        result: impl ResultHandler,
+    ) -> &mut Self {
+        self.execute_contract(sender, ExecuteMsg::CancelDissolution { id }, &[], result)
+    }
+
+    #[track_caller]
+    pub fn update_config(
+        &mut self,
+        sender: &Addr,
+        update: ConfigUpdate,
+        result: impl ResultHandler,
+    ) -> &mut Self {
+        self.execute_contract(sender, update.into(), &[], result)
+    }
+
     #[track_caller]
     pub fn update_ownership(
         &mut self,
@@ -235,11 +955,113 @@ impl TestingSuite {
     ) -> &mut Self {
         self.execute_contract(sender, ExecuteMsg::UpdateOwnership(action), &[], result)
     }
+
+    /// Overwrites the contract version stored by the proposal manager, as if an earlier version
+    /// had been deployed.
+    #[track_caller]
+    pub fn set_stored_contract_version(&mut self, version: &str) -> &mut Self {
+        let contract_addr = self.proposal_manager_contract_addr.clone();
+        let mut storage = self.app.contract_storage_mut(&contract_addr);
+        cw2::set_contract_version(storage.as_mut(), "proposal-manager", version).unwrap();
+        drop(storage);
+
+        self
+    }
+
+    /// Overwrites the state of the proposal manager with the given 0.1.x config and proposals,
+    /// as if 0.1.1 had been deployed. The fees and gifts of the pending proposals are minted to
+    /// the contract.
+    #[track_caller]
+    pub fn store_legacy_state(
+        &mut self,
+        config: LegacyConfig,
+        proposals: Vec<LegacyProposal>,
+    ) -> &mut Self {
+        let contract_addr = self.proposal_manager_contract_addr.clone();
+        let mut escrow = vec![];
+        let mut storage = self.app.contract_storage_mut(&contract_addr);
+        LEGACY_CONFIG.save(storage.as_mut(), &config).unwrap();
+        for proposal in &proposals {
+            LEGACY_PROPOSALS
+                .save(storage.as_mut(), proposal.id, proposal)
+                .unwrap();
+            if proposal.status == ProposalStatus::Pending {
+                escrow.push(proposal.fee.clone());
+                escrow.extend(proposal.gift.clone());
+            }
+        }
+        let count_status = |status: ProposalStatus| {
+            proposals
+                .iter()
+                .filter(|proposal| proposal.status == status)
+                .count() as u64
+        };
+        PROPOSAL_COUNTER
+            .save(storage.as_mut(), &(proposals.len() as u64))
+            .unwrap();
+        SUCCESSFUL_COUNTER
+            .save(storage.as_mut(), &count_status(ProposalStatus::Yes))
+            .unwrap();
+        FAILED_COUNTER
+            .save(storage.as_mut(), &count_status(ProposalStatus::No))
+            .unwrap();
+        // the counters added in 0.2.0 didn't exist yet
+        for counter in [
+            EXPIRED_COUNTER,
+            COUNTERED_COUNTER,
+            DEFERRED_COUNTER,
+            DISSOLVED_COUNTER,
+        ] {
+            counter.remove(storage.as_mut());
+        }
+        cw2::set_contract_version(storage.as_mut(), "proposal-manager", "0.1.1").unwrap();
+        drop(storage);
+
+        let escrow = aggregate_coins(escrow)
+            .unwrap()
+            .into_iter()
+            .filter(|coin| !coin.amount.is_zero())
+            .collect::<Vec<_>>();
+        if !escrow.is_empty() {
+            self.app
+                .sudo(SudoMsg::Bank(BankSudo::Mint {
+                    to_address: contract_addr.to_string(),
+                    amount: escrow,
+                }))
+                .unwrap();
+        }
+
+        self
+    }
+
+    #[track_caller]
+    pub fn migrate_contract(&mut self, sender: &Addr, result: impl ResultHandler) -> &mut Self {
+        let contract_addr = self.proposal_manager_contract_addr.clone();
+        let code_id = self
+            .app
+            .wrap()
+            .query_wasm_contract_info(&contract_addr)
+            .unwrap()
+            .code_id;
+
+        result.handle_result(self.app.migrate_contract(
+            sender.clone(),
+            contract_addr,
+            &MigrateMsg {},
+            code_id,
+        ));
+
+        self
+    }
 }
 
 // queries
 impl TestingSuite {
-    fn query_contract<T>(&mut self, msg: QueryMsg, result: impl Fn(StdResult<T>)) -> &mut Self
+    fn query_contract<T>(
+        &mut self,
+        msg: QueryMsg,
+        mut result: impl FnMut(StdResult<T>),
+    ) -> &mut Self
     where
         T: serde::de::DeserializeOwned,
     {
@@ -254,7 +1076,7 @@ impl TestingSuite {
     }
 
     #[track_caller]
-    pub fn query_config(&mut self, result: impl Fn(StdResult<Config>)) -> &mut Self {
+    pub fn query_config(&mut self, result: impl FnMut(StdResult<Config>)) -> &mut Self {
         self.query_contract(QueryMsg::Config {}, result)
     }
 
@@ -288,6 +1110,49 @@ impl TestingSuite {
         self.query_contract(QueryMsg::Status {}, result)
     }
 
+    #[track_caller]
+    pub fn query_negotiation_chain(
+        &mut self,
+        id: u64,
+        result: impl Fn(StdResult<ProposalsResponse>),
+    ) -> &mut Self {
+        self.query_contract(QueryMsg::NegotiationChain { id }, result)
+    }
+
+    #[track_caller]
+    pub fn query_proposal_revisions(
+        &mut self,
+        id: u64,
+        start_after: Option<u32>,
+        limit: Option<u32>,
+        result: impl Fn(StdResult<ProposalRevisionsResponse>),
+    ) -> &mut Self {
+        self.query_contract(
+            QueryMsg::ProposalRevisions {
+                id,
+                start_after,
+                limit,
+            },
+            result,
+        )
+    }
+
+    #[track_caller]
+    pub fn query_stage(
+        &mut self,
+        address_a: &Addr,
+        address_b: &Addr,
+        result: impl Fn(StdResult<StageResponse>),
+    ) -> &mut Self {
+        self.query_contract(
+            QueryMsg::Stage {
+                address_a: address_a.to_string(),
+                address_b: address_b.to_string(),
+            },
+            result,
+        )
+    }
+
     #[track_caller]
     pub fn _query_ownership(
         &mut self,
@@ -307,4 +1172,128 @@ impl TestingSuite {
         result(balance_response.unwrap_or(coin(0, denom)).amount);
         self
     }
+
+    #[track_caller]
+    pub fn query_supply(&mut self, denom: &str, result: impl Fn(Uint128)) -> &mut Self {
+        result(self.app.wrap().query_supply(denom).unwrap().amount);
+        self
+    }
+
+    #[track_caller]
+    pub fn query_cw20_balance(&mut self, address: &Addr, result: impl Fn(Uint128)) -> &mut Self {
+        let balance_response: BalanceResponse = self
+            .app
+            .wrap()
+            .query_wasm_smart(
+                &self.cw20_contract_addr,
+                &Cw20QueryMsg::Balance {
+                    address: address.to_string(),
+                },
+            )
+            .unwrap();
+        result(balance_response.balance);
+        self
+    }
+
+    #[track_caller]
+    pub fn query_vesting(
+        &mut self,
+        id: u64,
+        result: impl Fn(StdResult<VestingResponse>),
+    ) -> &mut Self {
+        self.query_contract(QueryMsg::Vesting { id }, result)
+    }
+
+    #[track_caller]
+    pub fn query_gift_fee(
+        &mut self,
+        gift: Vec<Coin>,
+        result: impl Fn(StdResult<GiftFeeResponse>),
+    ) -> &mut Self {
+        self.query_contract(QueryMsg::GiftFee { gift }, result)
+    }
+
+    #[track_caller]
+    pub fn query_gift_contributors(
+        &mut self,
+        id: u64,
+        start_after: Option<String>,
+        limit: Option<u32>,
+        result: impl Fn(StdResult<GiftContributorsResponse>),
+    ) -> &mut Self {
+        self.query_contract(
+            QueryMsg::GiftContributors {
+                id,
+                start_after,
+                limit,
+            },
+            result,
+        )
+    }
+
+    #[track_caller]
+    pub fn query_treasury(
+        &mut self,
+        start_after: Option<String>,
+        limit: Option<u32>,
+        result: impl Fn(StdResult<TreasuryResponse>),
+    ) -> &mut Self {
+        self.query_contract(QueryMsg::Treasury { start_after, limit }, result)
+    }
+
+    #[track_caller]
+    pub fn query_certificates(
+        &mut self,
+        id: u64,
+        result: impl Fn(StdResult<CertificatesResponse>),
+    ) -> &mut Self {
+        self.query_contract(QueryMsg::Certificates { id }, result)
+    }
+
+    #[track_caller]
+    pub fn query_nft_owner(
+        &mut self,
+        collection: &Addr,
+        token_id: &str,
+        result: impl Fn(Addr),
+    ) -> &mut Self {
+        let owner_response: OwnerOfResponse = self
+            .app
+            .wrap()
+            .query_wasm_smart(
+                collection,
+                &cw721_base::msg::QueryMsg::OwnerOf {
+                    token_id: token_id.to_string(),
+                    include_expired: None,
+                },
+            )
+            .unwrap();
+        result(Addr::unchecked(owner_response.owner));
+        self
+    }
+
+    #[track_caller]
+    pub fn query_nft_info(
+        &mut self,
+        collection: &Addr,
+        token_id: &str,
+        result: impl Fn(NftInfoResponse<DefaultOptionalNftExtension>),
+    ) -> &mut Self {
+        let nft_info_response = self
+            .app
+            .wrap()
+            .query_wasm_smart(
+                collection,
+                &Cw721QueryMsg::<
+                    DefaultOptionalNftExtension,
+                    DefaultOptionalCollectionExtension,
+                    Empty,
+                >::NftInfo {
+                    token_id: token_id.to_string(),
+                },
+            )
+            .unwrap();
+        result(nft_info_response);
+        self
+    }
 }
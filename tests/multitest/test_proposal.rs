@@ -1,10 +1,19 @@
-use cosmwasm_std::{coin, Order, Uint128};
-use cw_multi_test::{next_block, AppResponse};
+use cosmwasm_std::{coin, to_json_binary, Addr, Decimal, Order, Uint128};
+use cw20::{Cw20CoinVerified, Cw20ReceiveMsg};
+use cw721::receiver::Cw721ReceiveMsg;
+use cw_multi_test::AppResponse;
+use cw_utils::{Duration, Expiration};
 
-use crate::multitest::suite::TestingSuite;
+use crate::multitest::suite::{ConfigUpdate, NewProposal, TestingSuite, YesAnswer};
 use proposal_manager::error::ContractError;
-use proposal_manager::msg::{ProposalBy, ProposalsResponse};
-use proposal_manager::proposal::state::ProposalStatus;
+use proposal_manager::msg::{
+    ExecuteMsg, InstantiateMsg, ProposalBy, ProposalsResponse, ReceiveMsg, VestingAmount,
+};
+use proposal_manager::proposal::migration::{LegacyConfig, LegacyProposal};
+use proposal_manager::proposal::state::{
+    DenomLimit, FeeRecipient, FeeShare, Gift, GiftDisposition, GiftPolicy, ProposalStatus,
+    ReceiveAs, StakingConfig, Tranche, VestingSchedule,
+};
 
 const INITIAL_BALANCE: u128 = 1_000_000;
 
@@ -26,7 +35,7 @@ fn test_proposal_creation() {
             receiver.to_string(),
             vec![],
             &[],
-            |r| assert!(matches!(r, Err(_))),
+            |r: Result<AppResponse, anyhow::Error>| assert!(r.is_err()),
         );
 
     // Test successful proposal creation
@@ -93,14 +102,60 @@ fn test_cancel_proposal() {
         assert!(r.is_ok())
     });
 
-    // Verify proposal was deleted
+    // Verify proposal was kept as canceled
+    let block = suite.get_block();
     suite.query_proposal(
         0,
         |r: Result<proposal_manager::proposal::state::Proposal, cosmwasm_std::StdError>| {
-            assert!(r.is_err())
+            let proposal = r.unwrap();
+            assert_eq!(proposal.status, ProposalStatus::Canceled);
+            assert_eq!(proposal.canceled_at, Some(block));
         },
     );
 
+    // Test canceling twice
+    suite.cancel_proposal(&proposer, 0, |r: Result<AppResponse, anyhow::Error>| {
+        assert_eq!(
+            r.unwrap_err().downcast::<ContractError>().unwrap(),
+            ContractError::InvalidTransition {
+                from: ProposalStatus::Canceled,
+                to: ProposalStatus::Canceled,
+            }
+        )
+    });
+
+    // Verify canceled proposals can be filtered by status
+    suite
+        .query_proposals(
+            None,
+            Some(ProposalBy::Proposer(proposer.to_string())),
+            Some(ProposalStatus::Canceled),
+            None,
+            |r: Result<ProposalsResponse, cosmwasm_std::StdError>| {
+                let proposals = r.unwrap().proposals;
+                assert_eq!(proposals.len(), 1);
+                assert_eq!(proposals[0].id, 0);
+            },
+        )
+        .query_proposals(
+            None,
+            Some(ProposalBy::Receiver(receiver.to_string())),
+            Some(ProposalStatus::Canceled),
+            None,
+            |r: Result<ProposalsResponse, cosmwasm_std::StdError>| {
+                assert_eq!(r.unwrap().proposals.len(), 1);
+            },
+        )
+        .query_proposals(
+            None,
+            Some(ProposalBy::Proposer(proposer.to_string())),
+            Some(ProposalStatus::Pending),
+            None,
+            |r: Result<ProposalsResponse, cosmwasm_std::StdError>| {
+                assert!(r.unwrap().proposals.is_empty());
+            },
+        );
+
     // Verify proposal manager status
     suite.query_status(|r| {
         let status = r.unwrap();
@@ -350,14 +405,20 @@ fn test_update_config() {
         .instantiate_proposal_contract(Some(admin.to_string()))
         .update_config(
             &non_admin,
-            Some(coin(200, "uom")),
+            ConfigUpdate {
+                successful_proposal_fees: Some(vec![coin(200, "uom")]),
+                ..Default::default()
+            },
             |r: Result<AppResponse, anyhow::Error>| assert!(r.is_err()),
         );
 
     // Test successful update
     suite.update_config(
         &admin,
-        Some(coin(200, "uom")),
+        ConfigUpdate {
+            successful_proposal_fees: Some(vec![coin(200, "uom")]),
+            ..Default::default()
+        },
         |r: Result<AppResponse, anyhow::Error>| assert!(r.is_ok()),
     );
 
@@ -365,7 +426,5258 @@ fn test_update_config() {
     suite.query_config(
         |r: Result<proposal_manager::proposal::state::Config, cosmwasm_std::StdError>| {
             let config = r.unwrap();
-            assert_eq!(config.successful_proposal_fee, coin(200, "uom"));
+            assert_eq!(config.successful_proposal_fees, vec![coin(200, "uom")]);
+        },
+    );
+}
+
+#[test]
+fn test_expire_proposal() {
+    let mut suite = TestingSuite::default_with_balances(vec![coin(INITIAL_BALANCE, "uom")]);
+
+    let admin = &suite.admin();
+    let proposer = suite.senders[1].clone();
+    let receiver = suite.senders[2].clone();
+    let anyone = suite.senders[3].clone();
+
+    // Proposals expire 5 blocks after creation by default
+    suite
+        .instantiate_proposal_contract_with_msg(InstantiateMsg {
+            default_proposal_duration: Some(Duration::Height(5)),
+            ..TestingSuite::default_instantiate_msg(Some(admin.to_string()))
+        })
+        .create_proposal(
+            &proposer,
+            None,
+            None,
+            receiver.to_string(),
+            vec![coin(500, "uom")],
+            &[coin(600, "uom")],
+            |r: Result<AppResponse, anyhow::Error>| assert!(r.is_ok()),
+        );
+
+    let block = suite.get_block();
+    suite.query_proposal(0, |r| {
+        let proposal = r.unwrap();
+        assert_eq!(proposal.expires_at, Expiration::AtHeight(block + 5));
+    });
+
+    // Can't expire a proposal before its deadline
+    suite.expire_proposal(&anyone, 0, |r: Result<AppResponse, anyhow::Error>| {
+        assert_eq!(
+            r.unwrap_err().downcast::<ContractError>().unwrap(),
+            ContractError::ProposalNotExpired { id: 0 }
+        )
+    });
+
+    suite.add_100_block();
+
+    // The receiver can no longer answer
+    suite
+        .say_yes(
+            &receiver,
+            0,
+            None,
+            |r: Result<AppResponse, anyhow::Error>| {
+                assert_eq!(
+                    r.unwrap_err().downcast::<ContractError>().unwrap(),
+                    ContractError::ProposalExpired { id: 0 }
+                )
+            },
+        )
+        .say_no(
+            &receiver,
+            0,
+            None,
+            |r: Result<AppResponse, anyhow::Error>| {
+                assert_eq!(
+                    r.unwrap_err().downcast::<ContractError>().unwrap(),
+                    ContractError::ProposalExpired { id: 0 }
+                )
+            },
+        );
+
+    // Anyone can expire the proposal, the proposer gets the fee and gift back
+    suite
+        .expire_proposal(&anyone, 0, |r: Result<AppResponse, anyhow::Error>| {
+            assert!(r.is_ok())
+        })
+        .query_balance("uom", &proposer, |balance| {
+            assert_eq!(balance, Uint128::new(INITIAL_BALANCE))
+        })
+        .query_proposal(0, |r| {
+            assert_eq!(r.unwrap().status, ProposalStatus::Expired);
+        })
+        .expire_proposal(&anyone, 0, |r: Result<AppResponse, anyhow::Error>| {
+            assert_eq!(
+                r.unwrap_err().downcast::<ContractError>().unwrap(),
+                ContractError::InvalidTransition {
+                    from: ProposalStatus::Expired,
+                    to: ProposalStatus::Expired,
+                }
+            )
+        })
+        .query_proposals(
+            None,
+            Some(ProposalBy::Receiver(receiver.to_string())),
+            Some(ProposalStatus::Expired),
+            None,
+            |r| assert_eq!(r.unwrap().proposals.len(), 1),
+        );
+
+    suite.query_status(|r| {
+        let status = r.unwrap();
+        assert_eq!(status.total_proposals, 1);
+        assert_eq!(status.total_proposals_expired, 1);
+        assert_eq!(status.total_proposals_pending, 0);
+    });
+}
+
+#[test]
+fn test_proposal_expiration_validation() {
+    let mut suite = TestingSuite::default_with_balances(vec![coin(INITIAL_BALANCE, "uom")]);
+
+    let admin = &suite.admin();
+    let proposer = suite.senders[1].clone();
+    let receiver = suite.senders[2].clone();
+
+    // The default duration can't exceed the max duration
+    suite.instantiate_proposal_contract(Some(admin.to_string()));
+    suite.update_config(
+        admin,
+        ConfigUpdate {
+            default_proposal_duration: Some(Duration::Time(7200)),
+            max_proposal_duration: Some(Duration::Time(3600)),
+            ..Default::default()
+        },
+        |r: Result<AppResponse, anyhow::Error>| {
+            assert!(matches!(
+                r.unwrap_err().downcast::<ContractError>().unwrap(),
+                ContractError::InvalidExpiration { .. }
+            ))
+        },
+    );
+
+    suite.update_config(
+        admin,
+        ConfigUpdate {
+            max_proposal_duration: Some(Duration::Time(3600)),
+            ..Default::default()
         },
+        |r: Result<AppResponse, anyhow::Error>| assert!(r.is_ok()),
     );
+
+    let now = suite.get_time();
+    suite
+        // never expiring proposals exceed the max duration
+        .create_proposal_with(
+            &proposer,
+            NewProposal {
+                receiver: receiver.to_string(),
+                expires_at: Some(Expiration::Never {}),
+                ..Default::default()
+            },
+            &[coin(100, "uom")],
+            |r: Result<AppResponse, anyhow::Error>| {
+                assert!(matches!(
+                    r.unwrap_err().downcast::<ContractError>().unwrap(),
+                    ContractError::InvalidExpiration { .. }
+                ))
+            },
+        )
+        // expirations in the past are rejected
+        .create_proposal_with(
+            &proposer,
+            NewProposal {
+                receiver: receiver.to_string(),
+                expires_at: Some(Expiration::AtTime(now)),
+                ..Default::default()
+            },
+            &[coin(100, "uom")],
+            |r: Result<AppResponse, anyhow::Error>| {
+                assert!(matches!(
+                    r.unwrap_err().downcast::<ContractError>().unwrap(),
+                    ContractError::InvalidExpiration { .. }
+                ))
+            },
+        )
+        // height based expirations can't be compared with the time based max duration
+        .create_proposal_with(
+            &proposer,
+            NewProposal {
+                receiver: receiver.to_string(),
+                expires_at: Some(Expiration::AtHeight(1_000_000)),
+                ..Default::default()
+            },
+            &[coin(100, "uom")],
+            |r: Result<AppResponse, anyhow::Error>| {
+                assert!(matches!(
+                    r.unwrap_err().downcast::<ContractError>().unwrap(),
+                    ContractError::InvalidExpiration { .. }
+                ))
+            },
+        )
+        .create_proposal_with(
+            &proposer,
+            NewProposal {
+                receiver: receiver.to_string(),
+                expires_at: Some(Expiration::AtTime(now.plus_seconds(100))),
+                ..Default::default()
+            },
+            &[coin(100, "uom")],
+            |r: Result<AppResponse, anyhow::Error>| assert!(r.is_ok()),
+        )
+        // without an expiration, the max duration is used
+        .create_proposal_with(
+            &proposer,
+            NewProposal {
+                receiver: receiver.to_string(),
+                ..Default::default()
+            },
+            &[coin(100, "uom")],
+            |r: Result<AppResponse, anyhow::Error>| assert!(r.is_ok()),
+        )
+        .query_proposal(1, |r| {
+            assert_eq!(
+                r.unwrap().expires_at,
+                Expiration::AtTime(now.plus_seconds(3600))
+            );
+        });
+
+    suite
+        .add_seconds(100)
+        .say_yes(
+            &receiver,
+            1,
+            None,
+            |r: Result<AppResponse, anyhow::Error>| assert!(r.is_ok()),
+        )
+        .expire_proposal(&proposer, 0, |r: Result<AppResponse, anyhow::Error>| {
+            assert!(r.is_ok())
+        });
+
+    // a zero duration unsets the durations
+    suite
+        .update_config(
+            admin,
+            ConfigUpdate {
+                default_proposal_duration: Some(Duration::Height(0)),
+                max_proposal_duration: Some(Duration::Time(0)),
+                ..Default::default()
+            },
+            |r: Result<AppResponse, anyhow::Error>| assert!(r.is_ok()),
+        )
+        .query_config(|r| {
+            let config = r.unwrap();
+            assert_eq!(config.default_proposal_duration, None);
+            assert_eq!(config.max_proposal_duration, None);
+        })
+        .create_proposal_with(
+            &proposer,
+            NewProposal {
+                receiver: receiver.to_string(),
+                expires_at: Some(Expiration::Never {}),
+                ..Default::default()
+            },
+            &[coin(100, "uom")],
+            |r: Result<AppResponse, anyhow::Error>| assert!(r.is_ok()),
+        )
+        .query_proposal(2, |r| {
+            assert_eq!(r.unwrap().expires_at, Expiration::Never {});
+        });
+}
+
+#[test]
+fn test_answered_proposals_cannot_pay_out_twice() {
+    let mut suite = TestingSuite::default_with_balances(vec![coin(INITIAL_BALANCE, "uom")]);
+
+    let admin = suite.admin();
+    let proposer = suite.senders[1].clone();
+    let receiver = suite.senders[2].clone();
+    let gift = vec![coin(500, "uom")];
+
+    suite
+        .instantiate_proposal_contract(Some(admin.to_string()))
+        .create_proposal(
+            &proposer,
+            None,
+            None,
+            receiver.to_string(),
+            gift.clone(),
+            &[coin(600, "uom")],
+            |r: Result<AppResponse, anyhow::Error>| assert!(r.is_ok()),
+        )
+        .create_proposal(
+            &proposer,
+            None,
+            None,
+            receiver.to_string(),
+            gift.clone(),
+            &[coin(600, "uom")],
+            |r: Result<AppResponse, anyhow::Error>| assert!(r.is_ok()),
+        )
+        .say_yes(
+            &receiver,
+            0,
+            None,
+            |r: Result<AppResponse, anyhow::Error>| assert!(r.is_ok()),
+        )
+        .say_no(
+            &receiver,
+            1,
+            None,
+            |r: Result<AppResponse, anyhow::Error>| assert!(r.is_ok()),
+        );
+
+    let contract = suite.proposal_manager_contract_addr.clone();
+    suite
+        .query_balance("uom", &receiver, |balance| {
+            assert_eq!(balance, Uint128::new(INITIAL_BALANCE + 500))
+        })
+        .query_balance("uom", &proposer, |balance| {
+            assert_eq!(balance, Uint128::new(INITIAL_BALANCE - 600))
+        })
+        .withdraw_fees(
+            &admin,
+            vec![],
+            None,
+            |r: Result<AppResponse, anyhow::Error>| assert!(r.is_ok()),
+        )
+        .query_balance("uom", &admin, |balance| {
+            assert_eq!(balance, Uint128::new(INITIAL_BALANCE + 100))
+        })
+        .query_balance("uom", &contract, |balance| {
+            assert_eq!(balance, Uint128::zero())
+        });
+
+    // Answering an accepted proposal again is rejected
+    suite
+        .say_yes(
+            &receiver,
+            0,
+            None,
+            |r: Result<AppResponse, anyhow::Error>| {
+                assert_eq!(
+                    r.unwrap_err().downcast::<ContractError>().unwrap(),
+                    ContractError::InvalidTransition {
+                        from: ProposalStatus::Yes,
+                        to: ProposalStatus::Yes,
+                    }
+                )
+            },
+        )
+        .say_no(
+            &receiver,
+            0,
+            None,
+            |r: Result<AppResponse, anyhow::Error>| {
+                assert_eq!(
+                    r.unwrap_err().downcast::<ContractError>().unwrap(),
+                    ContractError::InvalidTransition {
+                        from: ProposalStatus::Yes,
+                        to: ProposalStatus::No,
+                    }
+                )
+            },
+        )
+        .cancel_proposal(&proposer, 0, |r: Result<AppResponse, anyhow::Error>| {
+            assert_eq!(
+                r.unwrap_err().downcast::<ContractError>().unwrap(),
+                ContractError::InvalidTransition {
+                    from: ProposalStatus::Yes,
+                    to: ProposalStatus::Canceled,
+                }
+            )
+        });
+
+    // Answering a rejected proposal again is rejected
+    suite
+        .say_yes(
+            &receiver,
+            1,
+            None,
+            |r: Result<AppResponse, anyhow::Error>| {
+                assert_eq!(
+                    r.unwrap_err().downcast::<ContractError>().unwrap(),
+                    ContractError::InvalidTransition {
+                        from: ProposalStatus::No,
+                        to: ProposalStatus::Yes,
+                    }
+                )
+            },
+        )
+        .say_no(
+            &receiver,
+            1,
+            None,
+            |r: Result<AppResponse, anyhow::Error>| {
+                assert_eq!(
+                    r.unwrap_err().downcast::<ContractError>().unwrap(),
+                    ContractError::InvalidTransition {
+                        from: ProposalStatus::No,
+                        to: ProposalStatus::No,
+                    }
+                )
+            },
+        )
+        .expire_proposal(&admin, 1, |r: Result<AppResponse, anyhow::Error>| {
+            assert_eq!(
+                r.unwrap_err().downcast::<ContractError>().unwrap(),
+                ContractError::InvalidTransition {
+                    from: ProposalStatus::No,
+                    to: ProposalStatus::Expired,
+                }
+            )
+        });
+
+    // No funds moved after the first answers
+    suite
+        .query_balance("uom", &receiver, |balance| {
+            assert_eq!(balance, Uint128::new(INITIAL_BALANCE + 500))
+        })
+        .query_balance("uom", &proposer, |balance| {
+            assert_eq!(balance, Uint128::new(INITIAL_BALANCE - 600))
+        })
+        .query_balance("uom", &admin, |balance| {
+            assert_eq!(balance, Uint128::new(INITIAL_BALANCE + 100))
+        })
+        .query_status(|r| {
+            let status = r.unwrap();
+            assert_eq!(status.total_proposals_yes, 1);
+            assert_eq!(status.total_proposals_no, 1);
+        });
+}
+
+#[test]
+fn test_cooling_off_window() {
+    let mut suite = TestingSuite::default_with_balances(vec![coin(INITIAL_BALANCE, "uom")]);
+
+    let admin = suite.admin();
+    let proposer = suite.senders[1].clone();
+    let receiver = suite.senders[2].clone();
+    let anyone = suite.senders[3].clone();
+
+    suite
+        .instantiate_proposal_contract_with_msg(InstantiateMsg {
+            finalization_delay: Some(Duration::Height(5)),
+            ..TestingSuite::default_instantiate_msg(Some(admin.to_string()))
+        })
+        .create_proposal(
+            &proposer,
+            None,
+            None,
+            receiver.to_string(),
+            vec![coin(500, "uom")],
+            &[coin(600, "uom")],
+            |r: Result<AppResponse, anyhow::Error>| assert!(r.is_ok()),
+        )
+        .create_proposal(
+            &proposer,
+            None,
+            None,
+            receiver.to_string(),
+            vec![coin(500, "uom")],
+            &[coin(600, "uom")],
+            |r: Result<AppResponse, anyhow::Error>| assert!(r.is_ok()),
+        );
+
+    // The answer is provisional and the funds stay in escrow
+    let block = suite.get_block();
+    let contract = suite.proposal_manager_contract_addr.clone();
+    suite
+        .say_yes(
+            &receiver,
+            0,
+            Some("Oops".to_string()),
+            |r: Result<AppResponse, anyhow::Error>| assert!(r.is_ok()),
+        )
+        .query_proposal(0, |r| {
+            let proposal = r.unwrap();
+            assert_eq!(proposal.status, ProposalStatus::ProvisionalYes);
+            assert_eq!(proposal.finalizes_at, Some(Expiration::AtHeight(block + 5)));
+        })
+        .query_balance("uom", &contract, |balance| {
+            assert_eq!(balance, Uint128::new(1_200))
+        })
+        .finalize(&anyone, 0, |r: Result<AppResponse, anyhow::Error>| {
+            assert_eq!(
+                r.unwrap_err().downcast::<ContractError>().unwrap(),
+                ContractError::CoolingOffWindowOpen { id: 0 }
+            )
+        })
+        // the proposer can't cancel while the answer is provisional
+        .cancel_proposal(&proposer, 0, |r: Result<AppResponse, anyhow::Error>| {
+            assert_eq!(
+                r.unwrap_err().downcast::<ContractError>().unwrap(),
+                ContractError::InvalidTransition {
+                    from: ProposalStatus::ProvisionalYes,
+                    to: ProposalStatus::Canceled,
+                }
+            )
+        });
+
+    // The receiver revokes the misclick and answers no instead
+    suite
+        .revoke_answer(&anyone, 0, |r: Result<AppResponse, anyhow::Error>| {
+            assert_eq!(
+                r.unwrap_err().downcast::<ContractError>().unwrap(),
+                ContractError::Unauthorized
+            )
+        })
+        .revoke_answer(&receiver, 0, |r: Result<AppResponse, anyhow::Error>| {
+            assert!(r.is_ok())
+        })
+        .query_proposal(0, |r| {
+            let proposal = r.unwrap();
+            assert_eq!(proposal.status, ProposalStatus::Pending);
+            assert_eq!(proposal.reply, None);
+            assert_eq!(proposal.finalizes_at, None);
+        })
+        .revoke_answer(&receiver, 0, |r: Result<AppResponse, anyhow::Error>| {
+            assert_eq!(
+                r.unwrap_err().downcast::<ContractError>().unwrap(),
+                ContractError::InvalidTransition {
+                    from: ProposalStatus::Pending,
+                    to: ProposalStatus::Pending,
+                }
+            )
+        })
+        .say_no(
+            &receiver,
+            0,
+            None,
+            |r: Result<AppResponse, anyhow::Error>| assert!(r.is_ok()),
+        )
+        .say_yes(
+            &receiver,
+            1,
+            None,
+            |r: Result<AppResponse, anyhow::Error>| assert!(r.is_ok()),
+        );
+
+    // Once the window is over the answers can't be revoked and anyone can finalize them
+    suite
+        .add_100_block()
+        .revoke_answer(&receiver, 1, |r: Result<AppResponse, anyhow::Error>| {
+            assert_eq!(
+                r.unwrap_err().downcast::<ContractError>().unwrap(),
+                ContractError::CoolingOffWindowClosed { id: 1 }
+            )
+        })
+        .finalize(&anyone, 0, |r: Result<AppResponse, anyhow::Error>| {
+            assert!(r.is_ok())
+        })
+        .finalize(&anyone, 1, |r: Result<AppResponse, anyhow::Error>| {
+            assert!(r.is_ok())
+        })
+        .finalize(&anyone, 1, |r: Result<AppResponse, anyhow::Error>| {
+            assert_eq!(
+                r.unwrap_err().downcast::<ContractError>().unwrap(),
+                ContractError::NoProvisionalAnswer {
+                    current_status: "Yes".to_string()
+                }
+            )
+        })
+        .query_proposal(0, |r| assert_eq!(r.unwrap().status, ProposalStatus::No))
+        .query_proposal(1, |r| assert_eq!(r.unwrap().status, ProposalStatus::Yes))
+        .query_balance("uom", &proposer, |balance| {
+            assert_eq!(balance, Uint128::new(INITIAL_BALANCE - 600))
+        })
+        .query_balance("uom", &receiver, |balance| {
+            assert_eq!(balance, Uint128::new(INITIAL_BALANCE + 500))
+        })
+        .withdraw_fees(
+            &admin,
+            vec![],
+            None,
+            |r: Result<AppResponse, anyhow::Error>| assert!(r.is_ok()),
+        )
+        .query_balance("uom", &admin, |balance| {
+            assert_eq!(balance, Uint128::new(INITIAL_BALANCE + 100))
+        })
+        .query_status(|r| {
+            let status = r.unwrap();
+            assert_eq!(status.total_proposals_yes, 1);
+            assert_eq!(status.total_proposals_no, 1);
+            assert_eq!(status.total_proposals_pending, 0);
+        });
+
+    // A zero delay disables the cooling-off window
+    suite
+        .update_config(
+            &admin,
+            ConfigUpdate {
+                finalization_delay: Some(Duration::Height(0)),
+                ..Default::default()
+            },
+            |r: Result<AppResponse, anyhow::Error>| assert!(r.is_ok()),
+        )
+        .query_config(|r| assert_eq!(r.unwrap().finalization_delay, None));
+}
+
+#[test]
+fn test_counter_proposals() {
+    let mut suite = TestingSuite::default_with_balances(vec![coin(INITIAL_BALANCE, "uom")]);
+
+    let admin = suite.admin();
+    let alice = suite.senders[1].clone();
+    let bob = suite.senders[2].clone();
+
+    suite
+        .instantiate_proposal_contract(Some(admin.to_string()))
+        .create_proposal(
+            &alice,
+            Some("Title".to_string()),
+            Some("Speech".to_string()),
+            bob.to_string(),
+            vec![coin(500, "uom")],
+            &[coin(600, "uom")],
+            |r: Result<AppResponse, anyhow::Error>| assert!(r.is_ok()),
+        );
+
+    // Only the receiver can counter-propose, paying the fee and their gift
+    suite
+        .counter_propose(
+            &alice,
+            0,
+            None,
+            vec![],
+            &[coin(100, "uom")],
+            |r: Result<AppResponse, anyhow::Error>| {
+                assert_eq!(
+                    r.unwrap_err().downcast::<ContractError>().unwrap(),
+                    ContractError::Unauthorized
+                )
+            },
+        )
+        .counter_propose(
+            &bob,
+            0,
+            Some("Let's wait a bit".to_string()),
+            vec![coin(200, "uom")],
+            &[coin(100, "uom")],
+            |r: Result<AppResponse, anyhow::Error>| {
+                assert!(matches!(
+                    r.unwrap_err().downcast::<ContractError>().unwrap(),
+                    ContractError::InvalidProposalCreationFee { .. }
+                ))
+            },
+        )
+        .counter_propose(
+            &bob,
+            0,
+            Some("Let's wait a bit".to_string()),
+            vec![coin(200, "uom")],
+            &[coin(300, "uom")],
+            |r: Result<AppResponse, anyhow::Error>| assert!(r.is_ok()),
+        )
+        .query_proposal(0, |r| {
+            assert_eq!(r.unwrap().status, ProposalStatus::Countered);
+        })
+        .query_proposal(1, |r| {
+            let proposal = r.unwrap();
+            assert_eq!(proposal.status, ProposalStatus::Pending);
+            assert_eq!(proposal.proposer, bob);
+            assert_eq!(proposal.receiver, alice);
+            assert_eq!(proposal.parent_id, Some(0));
+            assert_eq!(proposal.title, Some("Title".to_string()));
+            assert_eq!(proposal.speech, Some("Let's wait a bit".to_string()));
+            assert_eq!(proposal.gift, vec![Gift::Native(coin(200, "uom"))]);
+        })
+        // the original proposal got refunded
+        .query_balance("uom", &alice, |balance| {
+            assert_eq!(balance, Uint128::new(INITIAL_BALANCE))
+        })
+        // a countered proposal can't be answered anymore
+        .say_yes(&bob, 0, None, |r: Result<AppResponse, anyhow::Error>| {
+            assert_eq!(
+                r.unwrap_err().downcast::<ContractError>().unwrap(),
+                ContractError::InvalidTransition {
+                    from: ProposalStatus::Countered,
+                    to: ProposalStatus::Yes,
+                }
+            )
+        });
+
+    // Alice counters back and Bob accepts
+    suite
+        .counter_propose(
+            &alice,
+            1,
+            None,
+            vec![],
+            &[coin(100, "uom")],
+            |r: Result<AppResponse, anyhow::Error>| assert!(r.is_ok()),
+        )
+        .say_yes(&bob, 2, None, |r: Result<AppResponse, anyhow::Error>| {
+            assert!(r.is_ok())
+        });
+
+    // The whole chain is returned from any of its proposals
+    for id in 0..3 {
+        suite.query_negotiation_chain(id, |r| {
+            let proposals = r.unwrap().proposals;
+            assert_eq!(
+                proposals.iter().map(|p| p.id).collect::<Vec<_>>(),
+                vec![0, 1, 2]
+            );
+            assert_eq!(proposals[2].status, ProposalStatus::Yes);
+        });
+    }
+
+    suite.query_status(|r| {
+        let status = r.unwrap();
+        assert_eq!(status.total_proposals, 3);
+        assert_eq!(status.total_proposals_countered, 2);
+        assert_eq!(status.total_proposals_yes, 1);
+        assert_eq!(status.total_proposals_pending, 0);
+    });
+}
+
+#[test]
+fn test_defer_proposal() {
+    let mut suite = TestingSuite::default_with_balances(vec![coin(INITIAL_BALANCE, "uom")]);
+
+    let admin = suite.admin();
+    let proposer = suite.senders[1].clone();
+    let receiver = suite.senders[2].clone();
+
+    suite.instantiate_proposal_contract(Some(admin.to_string()));
+
+    let block = suite.get_block();
+    suite
+        .create_proposal(
+            &proposer,
+            None,
+            None,
+            receiver.to_string(),
+            vec![coin(500, "uom")],
+            &[coin(600, "uom")],
+            |r: Result<AppResponse, anyhow::Error>| assert!(r.is_ok()),
+        )
+        .create_proposal_with(
+            &proposer,
+            NewProposal {
+                receiver: receiver.to_string(),
+                expires_at: Some(Expiration::AtHeight(block + 3)),
+                ..Default::default()
+            },
+            &[coin(100, "uom")],
+            |r: Result<AppResponse, anyhow::Error>| assert!(r.is_ok()),
+        )
+        .create_proposal(
+            &proposer,
+            None,
+            None,
+            receiver.to_string(),
+            vec![],
+            &[coin(100, "uom")],
+            |r: Result<AppResponse, anyhow::Error>| assert!(r.is_ok()),
+        );
+
+    // The deadline must be in the future and not after the proposal's expiration
+    suite
+        .defer(
+            &receiver,
+            0,
+            Expiration::AtHeight(block),
+            None,
+            |r: Result<AppResponse, anyhow::Error>| {
+                assert!(matches!(
+                    r.unwrap_err().downcast::<ContractError>().unwrap(),
+                    ContractError::InvalidDeferral { .. }
+                ))
+            },
+        )
+        .defer(
+            &receiver,
+            1,
+            Expiration::AtHeight(block + 5),
+            None,
+            |r: Result<AppResponse, anyhow::Error>| {
+                assert!(matches!(
+                    r.unwrap_err().downcast::<ContractError>().unwrap(),
+                    ContractError::InvalidDeferral { .. }
+                ))
+            },
+        )
+        .defer(
+            &proposer,
+            0,
+            Expiration::AtHeight(block + 5),
+            None,
+            |r: Result<AppResponse, anyhow::Error>| {
+                assert_eq!(
+                    r.unwrap_err().downcast::<ContractError>().unwrap(),
+                    ContractError::Unauthorized
+                )
+            },
+        )
+        .defer(
+            &receiver,
+            0,
+            Expiration::AtHeight(block + 5),
+            Some("Not yet".to_string()),
+            |r: Result<AppResponse, anyhow::Error>| assert!(r.is_ok()),
+        )
+        .defer(
+            &receiver,
+            2,
+            Expiration::AtHeight(block + 5),
+            None,
+            |r: Result<AppResponse, anyhow::Error>| assert!(r.is_ok()),
+        );
+
+    // The proposer can't cancel before the deadline, and the funds stay in escrow
+    let contract = suite.proposal_manager_contract_addr.clone();
+    suite
+        .cancel_proposal(&proposer, 0, |r: Result<AppResponse, anyhow::Error>| {
+            assert_eq!(
+                r.unwrap_err().downcast::<ContractError>().unwrap(),
+                ContractError::InvalidTransition {
+                    from: ProposalStatus::Deferred,
+                    to: ProposalStatus::Canceled,
+                }
+            )
+        })
+        .query_balance("uom", &contract, |balance| {
+            assert_eq!(balance, Uint128::new(800))
+        })
+        .query_proposals(
+            None,
+            Some(ProposalBy::Receiver(receiver.to_string())),
+            Some(ProposalStatus::Deferred),
+            None,
+            |r: Result<ProposalsResponse, cosmwasm_std::StdError>| {
+                let proposals = r.unwrap().proposals;
+                assert_eq!(proposals.len(), 2);
+                assert_eq!(
+                    proposals[0].deferred_until,
+                    Some(Expiration::AtHeight(block + 5))
+                );
+                assert_eq!(proposals[0].reply, Some("Not yet".to_string()));
+            },
+        )
+        .query_status(|r| {
+            let status = r.unwrap();
+            assert_eq!(status.total_proposals_deferred, 2);
+            assert_eq!(status.total_proposals_pending, 1);
+        });
+
+    // The receiver can still answer during the deferral
+    suite.say_yes(
+        &receiver,
+        2,
+        None,
+        |r: Result<AppResponse, anyhow::Error>| assert!(r.is_ok()),
+    );
+
+    // After the deadline the proposal is pending again and can be cancelled
+    suite
+        .add_100_block()
+        .cancel_proposal(&proposer, 0, |r: Result<AppResponse, anyhow::Error>| {
+            assert!(r.is_ok())
+        })
+        .query_proposal(0, |r| {
+            let proposal = r.unwrap();
+            assert_eq!(proposal.status, ProposalStatus::Canceled);
+            assert_eq!(proposal.deferred_until, None);
+        })
+        .query_balance("uom", &proposer, |balance| {
+            assert_eq!(balance, Uint128::new(INITIAL_BALANCE - 200))
+        })
+        .query_status(|r| {
+            let status = r.unwrap();
+            assert_eq!(status.total_proposals_deferred, 0);
+            assert_eq!(status.total_proposals_cancelled, 1);
+            assert_eq!(status.total_proposals_yes, 1);
+            assert_eq!(status.total_proposals_pending, 1);
+        });
+
+    // The gift can only be topped up once the deferral is over
+    let friend = suite.senders[3].clone();
+    let block = suite.get_block();
+    suite
+        .create_proposal(
+            &proposer,
+            None,
+            None,
+            receiver.to_string(),
+            vec![],
+            &[coin(100, "uom")],
+            |r: Result<AppResponse, anyhow::Error>| assert!(r.is_ok()),
+        )
+        .defer(
+            &receiver,
+            3,
+            Expiration::AtHeight(block + 5),
+            None,
+            |r: Result<AppResponse, anyhow::Error>| assert!(r.is_ok()),
+        )
+        .contribute_gift(
+            &friend,
+            3,
+            &[coin(100, "uom")],
+            |r: Result<AppResponse, anyhow::Error>| {
+                assert_eq!(
+                    r.unwrap_err().downcast::<ContractError>().unwrap(),
+                    ContractError::ProposalNotPending {
+                        status: ProposalStatus::Deferred,
+                    }
+                )
+            },
+        )
+        .add_100_block()
+        .update_proposal(
+            &proposer,
+            3,
+            None,
+            None,
+            vec![coin(100, "uom")],
+            &[coin(100, "uom")],
+            |r: Result<AppResponse, anyhow::Error>| assert!(r.is_ok()),
+        )
+        .contribute_gift(
+            &friend,
+            3,
+            &[coin(100, "uom")],
+            |r: Result<AppResponse, anyhow::Error>| assert!(r.is_ok()),
+        )
+        .query_proposal(3, |r| {
+            let proposal = r.unwrap();
+            assert_eq!(proposal.status, ProposalStatus::Pending);
+            assert_eq!(proposal.gift, vec![Gift::Native(coin(200, "uom"))]);
+        })
+        .query_status(|r| {
+            let status = r.unwrap();
+            assert_eq!(status.total_proposals_deferred, 0);
+            assert_eq!(status.total_proposals_pending, 2);
+        });
+}
+
+#[test]
+fn test_multi_stage_proposals() {
+    let mut suite = TestingSuite::default_with_balances(vec![coin(INITIAL_BALANCE, "uom")]);
+
+    let admin = suite.admin();
+    let alice = suite.senders[1].clone();
+    let bob = suite.senders[2].clone();
+    let carol = suite.senders[3].clone();
+    let engagement = "engagement".to_string();
+    let wedding = "wedding".to_string();
+
+    suite
+        .instantiate_proposal_contract_with_msg(InstantiateMsg {
+            stages: vec![engagement.clone(), wedding.clone()],
+            ..TestingSuite::default_instantiate_msg(Some(admin.to_string()))
+        })
+        .update_config(
+            &admin,
+            ConfigUpdate {
+                stages: Some(vec![engagement.clone(), engagement.clone()]),
+                ..Default::default()
+            },
+            |r: Result<AppResponse, anyhow::Error>| {
+                assert!(matches!(
+                    r.unwrap_err().downcast::<ContractError>().unwrap(),
+                    ContractError::InvalidStages { .. }
+                ))
+            },
+        )
+        .query_stage(&alice, &bob, |r| {
+            assert_eq!(r.unwrap().stage, None);
+        })
+        // unknown stages are rejected
+        .create_proposal_with(
+            &alice,
+            NewProposal {
+                receiver: bob.to_string(),
+                stage: Some("honeymoon".to_string()),
+                ..Default::default()
+            },
+            &[coin(100, "uom")],
+            |r: Result<AppResponse, anyhow::Error>| {
+                assert_eq!(
+                    r.unwrap_err().downcast::<ContractError>().unwrap(),
+                    ContractError::UnknownStage {
+                        stage: "honeymoon".to_string()
+                    }
+                )
+            },
+        )
+        // the wedding requires an accepted engagement
+        .create_proposal_with(
+            &alice,
+            NewProposal {
+                receiver: bob.to_string(),
+                stage: Some(wedding.clone()),
+                ..Default::default()
+            },
+            &[coin(100, "uom")],
+            |r: Result<AppResponse, anyhow::Error>| {
+                assert_eq!(
+                    r.unwrap_err().downcast::<ContractError>().unwrap(),
+                    ContractError::PreviousStageNotAccepted {
+                        stage: wedding.clone(),
+                        previous_stage: engagement.clone(),
+                    }
+                )
+            },
+        )
+        // proposals default to the first stage
+        .create_proposal_with(
+            &alice,
+            NewProposal {
+                receiver: bob.to_string(),
+                ..Default::default()
+            },
+            &[coin(100, "uom")],
+            |r: Result<AppResponse, anyhow::Error>| assert!(r.is_ok()),
+        )
+        .query_proposal(0, |r| {
+            assert_eq!(r.unwrap().stage, Some(engagement.clone()));
+        })
+        .say_yes(&bob, 0, None, |r: Result<AppResponse, anyhow::Error>| {
+            assert!(r.is_ok())
+        })
+        .query_stage(&bob, &alice, |r| {
+            let response = r.unwrap();
+            assert_eq!(response.stage, Some(engagement.clone()));
+            assert_eq!(response.proposal_id, Some(0));
+        });
+
+    // the engagement is only between alice and bob
+    suite
+        .create_proposal_with(
+            &carol,
+            NewProposal {
+                receiver: bob.to_string(),
+                stage: Some(wedding.clone()),
+                ..Default::default()
+            },
+            &[coin(100, "uom")],
+            |r: Result<AppResponse, anyhow::Error>| {
+                assert!(matches!(
+                    r.unwrap_err().downcast::<ContractError>().unwrap(),
+                    ContractError::PreviousStageNotAccepted { .. }
+                ))
+            },
+        )
+        // either of them can propose the wedding
+        .create_proposal_with(
+            &bob,
+            NewProposal {
+                receiver: alice.to_string(),
+                stage: Some(wedding.clone()),
+                ..Default::default()
+            },
+            &[coin(100, "uom")],
+            |r: Result<AppResponse, anyhow::Error>| assert!(r.is_ok()),
+        )
+        .say_yes(&alice, 1, None, |r: Result<AppResponse, anyhow::Error>| {
+            assert!(r.is_ok())
+        })
+        .query_stage(&alice, &bob, |r| {
+            let response = r.unwrap();
+            assert_eq!(response.stage, Some(wedding.clone()));
+            assert_eq!(response.proposal_id, Some(1));
+        })
+        .query_stage(&alice, &carol, |r| {
+            assert_eq!(r.unwrap().stage, None);
+        });
+
+    // dissolving the wedding takes the pair back to the engagement
+    suite
+        .request_dissolution(
+            &alice,
+            1,
+            0,
+            &[],
+            |r: Result<AppResponse, anyhow::Error>| assert!(r.is_ok()),
+        )
+        .confirm_dissolution(&bob, 1, &[], |r: Result<AppResponse, anyhow::Error>| {
+            assert!(r.is_ok())
+        })
+        .query_stage(&alice, &bob, |r| {
+            let response = r.unwrap();
+            assert_eq!(response.stage, Some(engagement.clone()));
+            assert_eq!(response.proposal_id, Some(0));
+        });
+
+    // and dissolving the engagement leaves them without a stage
+    suite
+        .request_dissolution(&bob, 0, 0, &[], |r: Result<AppResponse, anyhow::Error>| {
+            assert!(r.is_ok())
+        })
+        .confirm_dissolution(&alice, 0, &[], |r: Result<AppResponse, anyhow::Error>| {
+            assert!(r.is_ok())
+        })
+        .query_stage(&alice, &bob, |r| {
+            let response = r.unwrap();
+            assert_eq!(response.stage, None);
+            assert_eq!(response.proposal_id, None);
+        })
+        .create_proposal_with(
+            &alice,
+            NewProposal {
+                receiver: bob.to_string(),
+                stage: Some(wedding.clone()),
+                ..Default::default()
+            },
+            &[coin(100, "uom")],
+            |r: Result<AppResponse, anyhow::Error>| {
+                assert_eq!(
+                    r.unwrap_err().downcast::<ContractError>().unwrap(),
+                    ContractError::PreviousStageNotAccepted {
+                        stage: wedding.clone(),
+                        previous_stage: engagement.clone(),
+                    }
+                )
+            },
+        );
+}
+
+#[test]
+fn test_mutual_dissolution() {
+    let mut suite = TestingSuite::default_with_balances(vec![coin(INITIAL_BALANCE, "uom")]);
+
+    let admin = suite.admin();
+    let proposer = suite.senders[1].clone();
+    let receiver = suite.senders[2].clone();
+    let stranger = suite.senders[3].clone();
+
+    suite.instantiate_proposal_contract(Some(admin.to_string()));
+    for _ in 0..3 {
+        suite.create_proposal(
+            &proposer,
+            None,
+            None,
+            receiver.to_string(),
+            vec![coin(1_000, "uom")],
+            &[coin(1_100, "uom")],
+            |r: Result<AppResponse, anyhow::Error>| assert!(r.is_ok()),
+        );
+    }
+    suite
+        .say_yes(
+            &receiver,
+            0,
+            None,
+            |r: Result<AppResponse, anyhow::Error>| assert!(r.is_ok()),
+        )
+        .say_yes(
+            &receiver,
+            1,
+            None,
+            |r: Result<AppResponse, anyhow::Error>| assert!(r.is_ok()),
+        );
+
+    // Only accepted proposals can be dissolved, by one of the parties
+    suite
+        .request_dissolution(
+            &stranger,
+            0,
+            5_000,
+            &[],
+            |r: Result<AppResponse, anyhow::Error>| {
+                assert_eq!(
+                    r.unwrap_err().downcast::<ContractError>().unwrap(),
+                    ContractError::Unauthorized
+                )
+            },
+        )
+        .request_dissolution(
+            &proposer,
+            2,
+            5_000,
+            &[],
+            |r: Result<AppResponse, anyhow::Error>| {
+                assert_eq!(
+                    r.unwrap_err().downcast::<ContractError>().unwrap(),
+                    ContractError::InvalidTransition {
+                        from: ProposalStatus::Pending,
+                        to: ProposalStatus::Dissolved,
+                    }
+                )
+            },
+        )
+        .request_dissolution(
+            &proposer,
+            0,
+            10_001,
+            &[],
+            |r: Result<AppResponse, anyhow::Error>| {
+                assert_eq!(
+                    r.unwrap_err().downcast::<ContractError>().unwrap(),
+                    ContractError::InvalidGiftReturnBps {
+                        gift_return_bps: 10_001
+                    }
+                )
+            },
+        )
+        .request_dissolution(
+            &proposer,
+            0,
+            5_000,
+            &[],
+            |r: Result<AppResponse, anyhow::Error>| assert!(r.is_ok()),
+        )
+        .request_dissolution(
+            &receiver,
+            0,
+            5_000,
+            &[coin(500, "uom")],
+            |r: Result<AppResponse, anyhow::Error>| {
+                assert_eq!(
+                    r.unwrap_err().downcast::<ContractError>().unwrap(),
+                    ContractError::DissolutionAlreadyRequested { id: 0 }
+                )
+            },
+        );
+
+    // The receiver confirms, returning half of the gift
+    let block = suite.get_block();
+    suite
+        .confirm_dissolution(
+            &proposer,
+            0,
+            &[],
+            |r: Result<AppResponse, anyhow::Error>| {
+                assert_eq!(
+                    r.unwrap_err().downcast::<ContractError>().unwrap(),
+                    ContractError::Unauthorized
+                )
+            },
+        )
+        .confirm_dissolution(
+            &receiver,
+            0,
+            &[coin(400, "uom")],
+            |r: Result<AppResponse, anyhow::Error>| {
+                assert!(matches!(
+                    r.unwrap_err().downcast::<ContractError>().unwrap(),
+                    ContractError::GiftReturnNotPaid { .. }
+                ))
+            },
+        )
+        .confirm_dissolution(
+            &receiver,
+            0,
+            &[coin(500, "uom")],
+            |r: Result<AppResponse, anyhow::Error>| {
+                let response = r.unwrap();
+                assert!(response.events.iter().any(|event| {
+                    event.attributes.iter().any(|attr| {
+                        attr.key == "gift_returned_to_proposer" && attr.value == "500uom"
+                    })
+                }));
+            },
+        )
+        .query_proposal(0, |r| {
+            let proposal = r.unwrap();
+            assert_eq!(proposal.status, ProposalStatus::Dissolved);
+            let dissolution = proposal.dissolution.unwrap();
+            assert_eq!(dissolution.initiator, proposer);
+            assert_eq!(dissolution.requested_at, block);
+            assert_eq!(dissolution.dissolved_at, Some(block));
+        })
+        .query_balance("uom", &proposer, |balance| {
+            assert_eq!(balance, Uint128::new(INITIAL_BALANCE - 3 * 1_100 + 500))
+        });
+
+    // The receiver initiates with the returned share escrowed, withdraws and tries again
+    let contract = suite.proposal_manager_contract_addr.clone();
+    suite
+        .request_dissolution(
+            &receiver,
+            1,
+            2_500,
+            &[],
+            |r: Result<AppResponse, anyhow::Error>| {
+                assert!(matches!(
+                    r.unwrap_err().downcast::<ContractError>().unwrap(),
+                    ContractError::GiftReturnNotPaid { .. }
+                ))
+            },
+        )
+        .request_dissolution(
+            &receiver,
+            1,
+            2_500,
+            &[coin(250, "uom")],
+            |r: Result<AppResponse, anyhow::Error>| assert!(r.is_ok()),
+        )
+        .cancel_dissolution(&proposer, 1, |r: Result<AppResponse, anyhow::Error>| {
+            assert_eq!(
+                r.unwrap_err().downcast::<ContractError>().unwrap(),
+                ContractError::Unauthorized
+            )
+        })
+        .cancel_dissolution(&receiver, 1, |r: Result<AppResponse, anyhow::Error>| {
+            assert!(r.is_ok())
+        })
+        .query_balance("uom", &receiver, |balance| {
+            assert_eq!(balance, Uint128::new(INITIAL_BALANCE + 2 * 1_000 - 500))
+        })
+        .request_dissolution(
+            &receiver,
+            1,
+            2_500,
+            &[coin(250, "uom")],
+            |r: Result<AppResponse, anyhow::Error>| assert!(r.is_ok()),
+        )
+        .confirm_dissolution(
+            &proposer,
+            1,
+            &[coin(1, "uom")],
+            |r: Result<AppResponse, anyhow::Error>| assert!(r.is_err()),
+        )
+        .confirm_dissolution(
+            &proposer,
+            1,
+            &[],
+            |r: Result<AppResponse, anyhow::Error>| assert!(r.is_ok()),
+        )
+        .query_balance("uom", &proposer, |balance| {
+            assert_eq!(balance, Uint128::new(INITIAL_BALANCE - 3 * 1_100 + 750))
+        })
+        // only the pending proposal's funds and the fees of the accepted ones are left
+        .query_balance("uom", &contract, |balance| {
+            assert_eq!(balance, Uint128::new(1_100 + 2 * 100))
+        })
+        .query_status(|r| {
+            let status = r.unwrap();
+            assert_eq!(status.total_proposals_yes, 0);
+            assert_eq!(status.total_proposals_dissolved, 2);
+            assert_eq!(status.total_proposals_pending, 1);
+        });
+
+    // Only the part of the gift the receiver kept counts towards the returned share
+    suite
+        .say_yes_with(
+            &receiver,
+            YesAnswer {
+                id: 2,
+                gift_disposition: Some(GiftDisposition::Split {
+                    address: stranger.clone(),
+                    share_bps: 4_000,
+                }),
+                ..Default::default()
+            },
+            &[],
+            |r: Result<AppResponse, anyhow::Error>| assert!(r.is_ok()),
+        )
+        .request_dissolution(
+            &receiver,
+            2,
+            5_000,
+            &[coin(500, "uom")],
+            |r: Result<AppResponse, anyhow::Error>| {
+                assert_eq!(
+                    r.unwrap_err().downcast::<ContractError>().unwrap(),
+                    ContractError::GiftReturnNotPaid {
+                        expected: "300uom".to_string(),
+                    }
+                )
+            },
+        )
+        .request_dissolution(
+            &receiver,
+            2,
+            5_000,
+            &[coin(300, "uom")],
+            |r: Result<AppResponse, anyhow::Error>| assert!(r.is_ok()),
+        );
+}
+
+#[test]
+fn test_update_proposal() {
+    let mut suite = TestingSuite::default_with_balances(vec![
+        coin(INITIAL_BALANCE, "uom"),
+        coin(INITIAL_BALANCE, "ibc/xxx"),
+    ]);
+
+    let admin = suite.admin();
+    let proposer = suite.senders[1].clone();
+    let receiver = suite.senders[2].clone();
+
+    suite
+        .instantiate_proposal_contract(Some(admin.to_string()))
+        .create_proposal(
+            &proposer,
+            Some("Title".to_string()),
+            Some("Speach".to_string()),
+            receiver.to_string(),
+            vec![coin(500, "uom")],
+            &[coin(600, "uom")],
+            |r: Result<AppResponse, anyhow::Error>| assert!(r.is_ok()),
+        );
+
+    let block = suite.get_block();
+    suite
+        .update_proposal(
+            &receiver,
+            0,
+            None,
+            Some("Speech".to_string()),
+            vec![],
+            &[],
+            |r: Result<AppResponse, anyhow::Error>| {
+                assert_eq!(
+                    r.unwrap_err().downcast::<ContractError>().unwrap(),
+                    ContractError::Unauthorized
+                )
+            },
+        )
+        // fix the typo
+        .update_proposal(
+            &proposer,
+            0,
+            None,
+            Some("Speech".to_string()),
+            vec![],
+            &[],
+            |r: Result<AppResponse, anyhow::Error>| assert!(r.is_ok()),
+        )
+        // the added gift must be paid
+        .update_proposal(
+            &proposer,
+            0,
+            None,
+            None,
+            vec![coin(200, "uom")],
+            &[coin(100, "uom")],
+            |r: Result<AppResponse, anyhow::Error>| {
+                assert!(matches!(
+                    r.unwrap_err().downcast::<ContractError>().unwrap(),
+                    ContractError::InvalidProposalCreationFee { .. }
+                ))
+            },
+        )
+        .update_proposal(
+            &proposer,
+            0,
+            None,
+            None,
+            vec![coin(100, "ibc/xxx")],
+            &[coin(100, "ibc/xxx"), coin(1, "uom")],
+            |r: Result<AppResponse, anyhow::Error>| assert!(r.is_err()),
+        )
+        .update_proposal(
+            &proposer,
+            0,
+            Some("New title".to_string()),
+            None,
+            vec![coin(200, "uom"), coin(100, "ibc/xxx")],
+            &[coin(200, "uom"), coin(100, "ibc/xxx")],
+            |r: Result<AppResponse, anyhow::Error>| assert!(r.is_ok()),
+        )
+        .query_proposal(0, |r| {
+            let proposal = r.unwrap();
+            assert_eq!(proposal.revision, 2);
+            assert_eq!(proposal.title, Some("New title".to_string()));
+            assert_eq!(proposal.speech, Some("Speech".to_string()));
+            assert_eq!(
+                proposal.gift,
+                vec![
+                    Gift::Native(coin(100, "ibc/xxx")),
+                    Gift::Native(coin(700, "uom"))
+                ]
+            );
+        })
+        .query_proposal_revisions(0, None, None, |r| {
+            let revisions = r.unwrap().revisions;
+            assert_eq!(revisions.len(), 2);
+            assert_eq!(revisions[0].revision, 0);
+            assert_eq!(revisions[0].speech, Some("Speach".to_string()));
+            assert_eq!(revisions[0].gift, vec![Gift::Native(coin(500, "uom"))]);
+            assert_eq!(revisions[0].replaced_at, block);
+            assert_eq!(revisions[1].revision, 1);
+            assert_eq!(revisions[1].title, Some("Title".to_string()));
+            assert_eq!(revisions[1].speech, Some("Speech".to_string()));
+        })
+        .query_proposal_revisions(0, Some(0), None, |r| {
+            assert_eq!(r.unwrap().revisions.len(), 1);
+        });
+
+    // The receiver gets the topped up gift
+    suite
+        .say_yes(
+            &receiver,
+            0,
+            None,
+            |r: Result<AppResponse, anyhow::Error>| assert!(r.is_ok()),
+        )
+        .query_balance("uom", &receiver, |balance| {
+            assert_eq!(balance, Uint128::new(INITIAL_BALANCE + 700))
+        })
+        .query_balance("ibc/xxx", &receiver, |balance| {
+            assert_eq!(balance, Uint128::new(INITIAL_BALANCE + 100))
+        })
+        .update_proposal(
+            &proposer,
+            0,
+            Some("Too late".to_string()),
+            None,
+            vec![],
+            &[],
+            |r: Result<AppResponse, anyhow::Error>| {
+                assert_eq!(
+                    r.unwrap_err().downcast::<ContractError>().unwrap(),
+                    ContractError::ProposalNotPending {
+                        status: ProposalStatus::Yes
+                    }
+                )
+            },
+        );
+}
+
+#[test]
+fn test_surprise_reveal() {
+    let mut suite = TestingSuite::default_with_balances(vec![coin(INITIAL_BALANCE, "uom")]);
+
+    let admin = suite.admin();
+    let proposer = suite.senders[1].clone();
+    let receiver = suite.senders[2].clone();
+
+    suite.instantiate_proposal_contract(Some(admin.to_string()));
+
+    let block = suite.get_block();
+
+    // The reveal time must be in the future and before the proposal's expiration
+    suite
+        .create_proposal_with(
+            &proposer,
+            NewProposal {
+                receiver: receiver.to_string(),
+                gift: vec![],
+                reveal_at: Some(Expiration::AtHeight(block)),
+                ..Default::default()
+            },
+            &[coin(100, "uom")],
+            |r: Result<AppResponse, anyhow::Error>| {
+                assert!(matches!(
+                    r.unwrap_err().downcast::<ContractError>().unwrap(),
+                    ContractError::InvalidRevealTime { .. }
+                ))
+            },
+        )
+        .create_proposal_with(
+            &proposer,
+            NewProposal {
+                receiver: receiver.to_string(),
+                gift: vec![],
+                reveal_at: Some(Expiration::Never {}),
+                ..Default::default()
+            },
+            &[coin(100, "uom")],
+            |r: Result<AppResponse, anyhow::Error>| {
+                assert!(matches!(
+                    r.unwrap_err().downcast::<ContractError>().unwrap(),
+                    ContractError::InvalidRevealTime { .. }
+                ))
+            },
+        )
+        .create_proposal_with(
+            &proposer,
+            NewProposal {
+                receiver: receiver.to_string(),
+                title: Some("Happy birthday".to_string()),
+                gift: vec![coin(500, "uom")],
+                reveal_at: Some(Expiration::AtHeight(block + 5)),
+                ..Default::default()
+            },
+            &[coin(600, "uom")],
+            |r: Result<AppResponse, anyhow::Error>| assert!(r.is_ok()),
+        );
+
+    // Before the reveal the details are hidden and the proposal can't be answered
+    suite
+        .query_proposal(0, |result| {
+            let proposal = result.unwrap();
+            assert_eq!(proposal.title, None);
+            assert!(proposal.gift.is_empty());
+            assert_eq!(proposal.reveal_at, Some(Expiration::AtHeight(block + 5)));
+        })
+        .query_proposals(None, None, None, None, |result| {
+            let proposals = result.unwrap().proposals;
+            assert_eq!(proposals[0].title, None);
+            assert!(proposals[0].gift.is_empty());
+        })
+        .say_yes(
+            &receiver,
+            0,
+            None,
+            |r: Result<AppResponse, anyhow::Error>| {
+                assert_eq!(
+                    r.unwrap_err().downcast::<ContractError>().unwrap(),
+                    ContractError::ProposalNotRevealed { id: 0 }
+                )
+            },
+        )
+        .say_no(
+            &receiver,
+            0,
+            None,
+            |r: Result<AppResponse, anyhow::Error>| {
+                assert_eq!(
+                    r.unwrap_err().downcast::<ContractError>().unwrap(),
+                    ContractError::ProposalNotRevealed { id: 0 }
+                )
+            },
+        )
+        .counter_propose(
+            &receiver,
+            0,
+            None,
+            vec![],
+            &[coin(100, "uom")],
+            |r: Result<AppResponse, anyhow::Error>| {
+                assert_eq!(
+                    r.unwrap_err().downcast::<ContractError>().unwrap(),
+                    ContractError::ProposalNotRevealed { id: 0 }
+                )
+            },
+        );
+
+    // Once revealed, the receiver sees the proposal and can accept it
+    suite
+        .add_100_block()
+        .query_proposal(0, |result| {
+            let proposal = result.unwrap();
+            assert_eq!(proposal.title, Some("Happy birthday".to_string()));
+            assert_eq!(proposal.gift, vec![Gift::Native(coin(500, "uom"))]);
+        })
+        .say_yes(
+            &receiver,
+            0,
+            None,
+            |r: Result<AppResponse, anyhow::Error>| assert!(r.is_ok()),
+        )
+        .query_balance("uom", &receiver, |balance| {
+            assert_eq!(balance, Uint128::new(INITIAL_BALANCE + 500));
+        });
+}
+
+#[test]
+fn test_cw20_gifts() {
+    let mut suite = TestingSuite::default_with_balances(vec![coin(INITIAL_BALANCE, "uom")]);
+
+    let admin = suite.admin();
+    let proposer = suite.senders[1].clone();
+    let receiver = suite.senders[2].clone();
+    let other = suite.senders[3].clone();
+
+    suite
+        .instantiate_proposal_contract(Some(admin.to_string()))
+        .instantiate_cw20_contract(INITIAL_BALANCE)
+        .allow_gift_tokens();
+    let token = suite.cw20_contract_addr.clone();
+
+    // The proposal fee can't be paid along with CW20 tokens
+    suite.send_cw20(
+        &proposer,
+        500,
+        ReceiveMsg::CreateProposal {
+            title: None,
+            speech: None,
+            receiver: receiver.to_string(),
+            expires_at: None,
+            stage: None,
+            reveal_at: None,
+            vesting: None,
+        },
+        |r: Result<AppResponse, anyhow::Error>| {
+            assert!(matches!(
+                r.unwrap_err().downcast::<ContractError>().unwrap(),
+                ContractError::ProposalCreationFeeMissing
+            ))
+        },
+    );
+
+    // Create the proposal paying the fee, then top it up with CW20 tokens
+    suite
+        .create_proposal(
+            &proposer,
+            None,
+            None,
+            receiver.to_string(),
+            vec![coin(200, "uom")],
+            &[coin(300, "uom")],
+            |r: Result<AppResponse, anyhow::Error>| assert!(r.is_ok()),
+        )
+        .send_cw20(
+            &other,
+            500,
+            ReceiveMsg::UpdateProposal {
+                id: 0,
+                title: None,
+                speech: None,
+                gift_option: None,
+            },
+            |r: Result<AppResponse, anyhow::Error>| {
+                assert_eq!(
+                    r.unwrap_err().downcast::<ContractError>().unwrap(),
+                    ContractError::Unauthorized
+                )
+            },
+        )
+        .send_cw20(
+            &proposer,
+            500,
+            ReceiveMsg::UpdateProposal {
+                id: 0,
+                title: Some("Be mine".to_string()),
+                speech: None,
+                gift_option: None,
+            },
+            |r: Result<AppResponse, anyhow::Error>| assert!(r.is_ok()),
+        )
+        .query_proposal(0, |result| {
+            let proposal = result.unwrap();
+            assert_eq!(proposal.title, Some("Be mine".to_string()));
+            assert_eq!(
+                proposal.gift,
+                vec![
+                    Gift::Native(coin(200, "uom")),
+                    Gift::Cw20(Cw20CoinVerified {
+                        address: token.clone(),
+                        amount: Uint128::new(500),
+                    })
+                ]
+            );
+        })
+        .query_cw20_balance(&proposer, |balance| {
+            assert_eq!(balance, Uint128::new(INITIAL_BALANCE - 500));
+        })
+        .query_cw20_balance(&other, |balance| {
+            assert_eq!(balance, Uint128::new(INITIAL_BALANCE));
+        });
+
+    // Accepting pays out both the native coins and the tokens
+    suite
+        .say_yes(
+            &receiver,
+            0,
+            None,
+            |r: Result<AppResponse, anyhow::Error>| assert!(r.is_ok()),
+        )
+        .query_cw20_balance(&receiver, |balance| {
+            assert_eq!(balance, Uint128::new(INITIAL_BALANCE + 500));
+        })
+        .query_balance("uom", &receiver, |balance| {
+            assert_eq!(balance, Uint128::new(INITIAL_BALANCE + 200));
+        })
+        .withdraw_fees(
+            &admin,
+            vec![],
+            None,
+            |r: Result<AppResponse, anyhow::Error>| assert!(r.is_ok()),
+        )
+        .query_balance("uom", &admin, |balance| {
+            assert_eq!(balance, Uint128::new(INITIAL_BALANCE + 100));
+        });
+
+    // Without a proposal fee, proposals can be created with CW20 tokens only
+    suite
+        .update_config(
+            &admin,
+            ConfigUpdate {
+                successful_proposal_fees: Some(vec![]),
+                ..Default::default()
+            },
+            |r: Result<AppResponse, anyhow::Error>| assert!(r.is_ok()),
+        )
+        .send_cw20(
+            &proposer,
+            300,
+            ReceiveMsg::CreateProposal {
+                title: None,
+                speech: None,
+                receiver: receiver.to_string(),
+                expires_at: None,
+                stage: None,
+                reveal_at: None,
+                vesting: None,
+            },
+            |r: Result<AppResponse, anyhow::Error>| assert!(r.is_ok()),
+        )
+        .send_cw20(
+            &proposer,
+            200,
+            ReceiveMsg::CreateProposal {
+                title: None,
+                speech: None,
+                receiver: receiver.to_string(),
+                expires_at: None,
+                stage: None,
+                reveal_at: None,
+                vesting: None,
+            },
+            |r: Result<AppResponse, anyhow::Error>| assert!(r.is_ok()),
+        )
+        .query_cw20_balance(&proposer, |balance| {
+            assert_eq!(balance, Uint128::new(INITIAL_BALANCE - 1_000));
+        });
+
+    // Rejecting and canceling refund the tokens to the proposer
+    suite
+        .say_no(
+            &receiver,
+            1,
+            None,
+            |r: Result<AppResponse, anyhow::Error>| assert!(r.is_ok()),
+        )
+        .query_cw20_balance(&proposer, |balance| {
+            assert_eq!(balance, Uint128::new(INITIAL_BALANCE - 700));
+        })
+        .cancel_proposal(&proposer, 2, |r: Result<AppResponse, anyhow::Error>| {
+            assert!(r.is_ok())
+        })
+        .query_cw20_balance(&proposer, |balance| {
+            assert_eq!(balance, Uint128::new(INITIAL_BALANCE - 500));
+        })
+        .query_balance("uom", &proposer, |balance| {
+            assert_eq!(balance, Uint128::new(INITIAL_BALANCE - 300));
+        });
+}
+
+#[test]
+fn test_nft_gifts() {
+    let mut suite = TestingSuite::default_with_balances(vec![coin(INITIAL_BALANCE, "uom")]);
+
+    let admin = suite.admin();
+    let proposer = suite.senders[1].clone();
+    let receiver = suite.senders[2].clone();
+    let other = suite.senders[3].clone();
+
+    suite
+        .instantiate_proposal_contract(Some(admin.to_string()))
+        .instantiate_cw721_contract(&[
+            ("ring-1", &proposer),
+            ("ring-2", &proposer),
+            ("ring-3", &proposer),
+            ("ring-4", &other),
+        ])
+        .allow_gift_tokens();
+    let collection = suite.cw721_contract_addr.clone();
+    let contract = suite.proposal_manager_contract_addr.clone();
+
+    for _ in 0..3 {
+        suite.create_proposal(
+            &proposer,
+            None,
+            None,
+            receiver.to_string(),
+            vec![],
+            &[coin(100, "uom")],
+            |r: Result<AppResponse, anyhow::Error>| assert!(r.is_ok()),
+        );
+    }
+
+    // The proposal fee can't be paid along with an NFT
+    suite
+        .send_nft(
+            &proposer,
+            "ring-1",
+            ReceiveMsg::CreateProposal {
+                title: None,
+                speech: None,
+                receiver: receiver.to_string(),
+                expires_at: None,
+                stage: None,
+                reveal_at: None,
+                vesting: None,
+            },
+            |r: Result<AppResponse, anyhow::Error>| {
+                assert_eq!(
+                    r.unwrap_err().downcast::<ContractError>().unwrap(),
+                    ContractError::ProposalCreationFeeMissing
+                )
+            },
+        )
+        .query_nft_owner(&collection, "ring-1", |owner| assert_eq!(owner, proposer));
+
+    // Only the proposer can bind an NFT to their proposal
+    suite
+        .send_nft(
+            &other,
+            "ring-4",
+            ReceiveMsg::UpdateProposal {
+                id: 0,
+                title: None,
+                speech: None,
+                gift_option: None,
+            },
+            |r: Result<AppResponse, anyhow::Error>| {
+                assert_eq!(
+                    r.unwrap_err().downcast::<ContractError>().unwrap(),
+                    ContractError::Unauthorized
+                )
+            },
+        )
+        .query_nft_owner(&collection, "ring-4", |owner| assert_eq!(owner, other));
+
+    for (id, token_id) in [(0, "ring-1"), (1, "ring-2"), (2, "ring-3")] {
+        suite.send_nft(
+            &proposer,
+            token_id,
+            ReceiveMsg::UpdateProposal {
+                id,
+                title: None,
+                speech: None,
+                gift_option: None,
+            },
+            |r: Result<AppResponse, anyhow::Error>| assert!(r.is_ok()),
+        );
+    }
+
+    // The NFTs are escrowed by the contract and listed in the proposal's gift
+    suite
+        .query_nft_owner(&collection, "ring-1", |owner| assert_eq!(owner, contract))
+        .query_proposal(0, |result| {
+            assert_eq!(
+                result.unwrap().gift,
+                vec![Gift::Cw721 {
+                    contract: collection.clone(),
+                    token_id: "ring-1".to_string(),
+                }]
+            );
+        });
+
+    // Accepting transfers the NFT to the receiver, rejecting or canceling returns it
+    suite
+        .say_yes(
+            &receiver,
+            0,
+            None,
+            |r: Result<AppResponse, anyhow::Error>| assert!(r.is_ok()),
+        )
+        .say_no(
+            &receiver,
+            1,
+            None,
+            |r: Result<AppResponse, anyhow::Error>| assert!(r.is_ok()),
+        )
+        .cancel_proposal(&proposer, 2, |r: Result<AppResponse, anyhow::Error>| {
+            assert!(r.is_ok())
+        })
+        .query_nft_owner(&collection, "ring-1", |owner| assert_eq!(owner, receiver))
+        .query_nft_owner(&collection, "ring-2", |owner| assert_eq!(owner, proposer))
+        .query_nft_owner(&collection, "ring-3", |owner| assert_eq!(owner, proposer));
+}
+
+#[test]
+fn test_acceptance_certificates() {
+    let mut suite = TestingSuite::default_with_balances(vec![coin(INITIAL_BALANCE, "uom")]);
+
+    let admin = suite.admin();
+    let proposer = suite.senders[1].clone();
+    let receiver = suite.senders[2].clone();
+
+    let certificate_code_id = suite.store_certificate_code();
+    suite.instantiate_proposal_contract_with_msg(InstantiateMsg {
+        certificate_code_id: Some(certificate_code_id),
+        ..TestingSuite::default_instantiate_msg(Some(admin.to_string()))
+    });
+
+    let mut collection = None;
+    suite.query_config(|result| {
+        collection = result.unwrap().certificate_collection;
+    });
+    let collection = collection.expect("the certificate collection is instantiated");
+
+    suite
+        .create_proposal(
+            &proposer,
+            Some("Will you marry me?".to_string()),
+            None,
+            receiver.to_string(),
+            vec![],
+            &[coin(100, "uom")],
+            |r: Result<AppResponse, anyhow::Error>| assert!(r.is_ok()),
+        )
+        .create_proposal(
+            &proposer,
+            None,
+            None,
+            receiver.to_string(),
+            vec![],
+            &[coin(100, "uom")],
+            |r: Result<AppResponse, anyhow::Error>| assert!(r.is_ok()),
+        )
+        .say_no(
+            &receiver,
+            1,
+            None,
+            |r: Result<AppResponse, anyhow::Error>| assert!(r.is_ok()),
+        )
+        .query_certificates(1, |result| {
+            assert!(result.unwrap().token_ids.is_empty());
+        });
+
+    let block = suite.get_block();
+    suite
+        .say_yes(
+            &receiver,
+            0,
+            None,
+            |r: Result<AppResponse, anyhow::Error>| assert!(r.is_ok()),
+        )
+        .query_certificates(0, |result| {
+            assert_eq!(
+                result.unwrap().token_ids,
+                vec!["0-proposer".to_string(), "0-receiver".to_string()]
+            );
+        })
+        .query_nft_owner(&collection, "0-proposer", |owner| {
+            assert_eq!(owner, proposer)
+        })
+        .query_nft_owner(&collection, "0-receiver", |owner| {
+            assert_eq!(owner, receiver)
+        })
+        // the certificate collection is soulbound
+        .transfer_nft(
+            &receiver,
+            &collection,
+            "0-receiver",
+            &proposer,
+            |r: Result<AppResponse, anyhow::Error>| assert!(r.is_err()),
+        )
+        .query_nft_info(&collection, "0-receiver", |info| {
+            let metadata = info.extension.unwrap();
+            assert_eq!(metadata.name, Some("Will you marry me?".to_string()));
+            let attributes = metadata
+                .attributes
+                .unwrap()
+                .into_iter()
+                .map(|attribute| (attribute.trait_type, attribute.value))
+                .collect::<Vec<_>>();
+            assert!(attributes.contains(&("proposal_id".to_string(), "0".to_string())));
+            assert!(attributes.contains(&("replied_at".to_string(), block.to_string())));
+        });
+}
+
+#[test]
+fn test_vesting_gifts() {
+    let mut suite = TestingSuite::default_with_balances(vec![coin(INITIAL_BALANCE, "uom")]);
+
+    let admin = suite.admin();
+    let proposer = suite.senders[1].clone();
+    let receiver = suite.senders[2].clone();
+
+    suite.instantiate_proposal_contract(Some(admin.to_string()));
+
+    let now = suite.get_time();
+    suite
+        .create_proposal_with(
+            &proposer,
+            NewProposal {
+                receiver: receiver.to_string(),
+                gift: vec![coin(1_000, "uom")],
+                vesting: Some(VestingSchedule::Linear { duration: 0 }),
+                ..Default::default()
+            },
+            &[coin(1_100, "uom")],
+            |r: Result<AppResponse, anyhow::Error>| {
+                assert!(matches!(
+                    r.unwrap_err().downcast::<ContractError>().unwrap(),
+                    ContractError::InvalidVestingSchedule { .. }
+                ))
+            },
+        )
+        .create_proposal_with(
+            &proposer,
+            NewProposal {
+                receiver: receiver.to_string(),
+                gift: vec![coin(1_000, "uom")],
+                vesting: Some(VestingSchedule::Tranches(vec![Tranche {
+                    unlocks_at: now.plus_seconds(10),
+                    share_bps: 5_000,
+                }])),
+                ..Default::default()
+            },
+            &[coin(1_100, "uom")],
+            |r: Result<AppResponse, anyhow::Error>| {
+                assert!(matches!(
+                    r.unwrap_err().downcast::<ContractError>().unwrap(),
+                    ContractError::InvalidVestingSchedule { .. }
+                ))
+            },
+        )
+        .create_proposal_with(
+            &proposer,
+            NewProposal {
+                receiver: receiver.to_string(),
+                gift: vec![coin(1_000, "uom")],
+                vesting: Some(VestingSchedule::Linear { duration: 100 }),
+                ..Default::default()
+            },
+            &[coin(1_100, "uom")],
+            |r: Result<AppResponse, anyhow::Error>| assert!(r.is_ok()),
+        )
+        .create_proposal_with(
+            &proposer,
+            NewProposal {
+                receiver: receiver.to_string(),
+                gift: vec![coin(500, "uom")],
+                vesting: Some(VestingSchedule::Tranches(vec![
+                    Tranche {
+                        unlocks_at: now.plus_seconds(10),
+                        share_bps: 4_000,
+                    },
+                    Tranche {
+                        unlocks_at: now.plus_seconds(20),
+                        share_bps: 6_000,
+                    },
+                ])),
+                ..Default::default()
+            },
+            &[coin(600, "uom")],
+            |r: Result<AppResponse, anyhow::Error>| assert!(r.is_ok()),
+        );
+
+    // Nothing is released before the proposals are accepted
+    suite
+        .claim_gift(&receiver, 0, |r: Result<AppResponse, anyhow::Error>| {
+            assert_eq!(
+                r.unwrap_err().downcast::<ContractError>().unwrap(),
+                ContractError::GiftNotVesting { id: 0 }
+            )
+        })
+        .say_yes(
+            &receiver,
+            0,
+            None,
+            |r: Result<AppResponse, anyhow::Error>| assert!(r.is_ok()),
+        )
+        .say_yes(
+            &receiver,
+            1,
+            None,
+            |r: Result<AppResponse, anyhow::Error>| assert!(r.is_ok()),
+        )
+        .query_balance("uom", &receiver, |balance| {
+            assert_eq!(balance, Uint128::new(INITIAL_BALANCE));
+        })
+        .claim_gift(&receiver, 0, |r: Result<AppResponse, anyhow::Error>| {
+            assert_eq!(
+                r.unwrap_err().downcast::<ContractError>().unwrap(),
+                ContractError::NothingToClaim { id: 0 }
+            )
+        });
+
+    // The linear gift is released over time and the tranches on their dates
+    suite
+        .add_seconds(10)
+        .claim_gift(&proposer, 0, |r: Result<AppResponse, anyhow::Error>| {
+            assert_eq!(
+                r.unwrap_err().downcast::<ContractError>().unwrap(),
+                ContractError::Unauthorized
+            )
+        })
+        .claim_gift(&receiver, 0, |r: Result<AppResponse, anyhow::Error>| {
+            assert!(r.is_ok())
+        })
+        .claim_gift(&receiver, 1, |r: Result<AppResponse, anyhow::Error>| {
+            assert!(r.is_ok())
+        })
+        .query_balance("uom", &receiver, |balance| {
+            assert_eq!(balance, Uint128::new(INITIAL_BALANCE + 100 + 200));
+        })
+        .add_seconds(15)
+        .query_vesting(0, |result| {
+            let vesting = result.unwrap();
+            assert_eq!(vesting.started_at, Some(now));
+            assert_eq!(
+                vesting.amounts,
+                vec![VestingAmount {
+                    denom: "uom".to_string(),
+                    total: Uint128::new(1_000),
+                    vested: Uint128::new(250),
+                    claimed: Uint128::new(100),
+                    remaining: Uint128::new(900),
+                }]
+            );
+        })
+        .add_seconds(100)
+        .claim_gift(&receiver, 0, |r: Result<AppResponse, anyhow::Error>| {
+            assert!(r.is_ok())
+        })
+        .claim_gift(&receiver, 1, |r: Result<AppResponse, anyhow::Error>| {
+            assert!(r.is_ok())
+        })
+        .query_balance("uom", &receiver, |balance| {
+            assert_eq!(balance, Uint128::new(INITIAL_BALANCE + 1_500));
+        })
+        .claim_gift(&receiver, 1, |r: Result<AppResponse, anyhow::Error>| {
+            assert_eq!(
+                r.unwrap_err().downcast::<ContractError>().unwrap(),
+                ContractError::NothingToClaim { id: 1 }
+            )
+        });
+}
+
+#[test]
+fn test_dissolution_stops_vesting() {
+    let mut suite = TestingSuite::default_with_balances(vec![coin(INITIAL_BALANCE, "uom")]);
+
+    let admin = suite.admin();
+    let proposer = suite.senders[1].clone();
+    let receiver = suite.senders[2].clone();
+
+    suite
+        .instantiate_proposal_contract(Some(admin.to_string()))
+        .create_proposal_with(
+            &proposer,
+            NewProposal {
+                receiver: receiver.to_string(),
+                gift: vec![coin(1_000, "uom")],
+                vesting: Some(VestingSchedule::Linear { duration: 100 }),
+                ..Default::default()
+            },
+            &[coin(1_100, "uom")],
+            |r: Result<AppResponse, anyhow::Error>| assert!(r.is_ok()),
+        )
+        .say_yes(
+            &receiver,
+            0,
+            None,
+            |r: Result<AppResponse, anyhow::Error>| assert!(r.is_ok()),
+        )
+        .add_seconds(20)
+        .claim_gift(&receiver, 0, |r: Result<AppResponse, anyhow::Error>| {
+            assert!(r.is_ok())
+        })
+        // the returned share is computed on the 200uom claimed so far
+        .request_dissolution(
+            &receiver,
+            0,
+            5_000,
+            &[coin(500, "uom")],
+            |r: Result<AppResponse, anyhow::Error>| {
+                assert_eq!(
+                    r.unwrap_err().downcast::<ContractError>().unwrap(),
+                    ContractError::GiftReturnNotPaid {
+                        expected: "100uom".to_string()
+                    }
+                )
+            },
+        )
+        .request_dissolution(
+            &receiver,
+            0,
+            5_000,
+            &[coin(100, "uom")],
+            |r: Result<AppResponse, anyhow::Error>| assert!(r.is_ok()),
+        )
+        // the vesting goes on until the dissolution is confirmed
+        .add_seconds(20)
+        .confirm_dissolution(
+            &proposer,
+            0,
+            &[],
+            |r: Result<AppResponse, anyhow::Error>| assert!(r.is_ok()),
+        )
+        // the receiver gets the 200uom vested since, the proposer the returned share and the
+        // 600uom that didn't vest
+        .query_balance("uom", &receiver, |balance| {
+            assert_eq!(balance, Uint128::new(INITIAL_BALANCE + 200 - 100 + 200));
+        })
+        .query_balance("uom", &proposer, |balance| {
+            assert_eq!(balance, Uint128::new(INITIAL_BALANCE - 1_100 + 100 + 600));
+        })
+        .add_seconds(100)
+        .claim_gift(&receiver, 0, |r: Result<AppResponse, anyhow::Error>| {
+            assert_eq!(
+                r.unwrap_err().downcast::<ContractError>().unwrap(),
+                ContractError::VestingStopped { id: 0 }
+            )
+        })
+        .query_vesting(0, |result| {
+            assert_eq!(
+                result.unwrap().amounts,
+                vec![VestingAmount {
+                    denom: "uom".to_string(),
+                    total: Uint128::new(1_000),
+                    vested: Uint128::new(400),
+                    claimed: Uint128::new(400),
+                    remaining: Uint128::zero(),
+                }]
+            );
+        });
+
+    // only the proposal fee is left in the contract
+    let contract = suite.proposal_manager_contract_addr.clone();
+    suite.query_balance("uom", &contract, |balance| {
+        assert_eq!(balance, Uint128::new(100));
+    });
+}
+
+#[test]
+fn test_crowdfunded_gifts() {
+    let mut suite = TestingSuite::default_with_balances(vec![coin(INITIAL_BALANCE, "uom")]);
+
+    let admin = suite.admin();
+    let proposer = suite.senders[1].clone();
+    let receiver = suite.senders[2].clone();
+    let friend = suite.senders[3].clone();
+
+    suite
+        .instantiate_proposal_contract(Some(admin.to_string()))
+        .create_proposal(
+            &proposer,
+            None,
+            None,
+            receiver.to_string(),
+            vec![coin(1_000, "uom")],
+            &[coin(1_100, "uom")],
+            |r: Result<AppResponse, anyhow::Error>| assert!(r.is_ok()),
+        )
+        .contribute_gift(
+            &receiver,
+            0,
+            &[coin(100, "uom")],
+            |r: Result<AppResponse, anyhow::Error>| {
+                assert!(matches!(
+                    r.unwrap_err().downcast::<ContractError>().unwrap(),
+                    ContractError::Unauthorized
+                ))
+            },
+        )
+        .contribute_gift(&friend, 0, &[], |r: Result<AppResponse, anyhow::Error>| {
+            assert!(matches!(
+                r.unwrap_err().downcast::<ContractError>().unwrap(),
+                ContractError::PaymentError { .. }
+            ))
+        })
+        .contribute_gift(
+            &friend,
+            0,
+            &[coin(200, "uom")],
+            |r: Result<AppResponse, anyhow::Error>| assert!(r.is_ok()),
+        )
+        .contribute_gift(
+            &friend,
+            0,
+            &[coin(100, "uom")],
+            |r: Result<AppResponse, anyhow::Error>| assert!(r.is_ok()),
+        )
+        .contribute_gift(
+            &admin,
+            0,
+            &[coin(500, "uom")],
+            |r: Result<AppResponse, anyhow::Error>| assert!(r.is_ok()),
+        )
+        .query_proposal(0, |r| {
+            assert_eq!(r.unwrap().gift, vec![Gift::Native(coin(1_800, "uom"))]);
+        })
+        .query_gift_contributors(0, None, None, |r| {
+            let contributors = r.unwrap().contributors;
+            assert_eq!(contributors.len(), 2);
+            assert_eq!(contributors[0].contributor, admin);
+            assert_eq!(contributors[0].amount, vec![coin(500, "uom")]);
+            assert_eq!(contributors[1].contributor, friend);
+            assert_eq!(contributors[1].amount, vec![coin(300, "uom")]);
+        })
+        .query_gift_contributors(0, Some(admin.to_string()), Some(1), |r| {
+            let contributors = r.unwrap().contributors;
+            assert_eq!(contributors.len(), 1);
+            assert_eq!(contributors[0].contributor, friend);
+        })
+        .say_no(
+            &receiver,
+            0,
+            None,
+            |r: Result<AppResponse, anyhow::Error>| {
+                let response = r.unwrap();
+                let attribute = |key: &str| {
+                    response
+                        .events
+                        .iter()
+                        .flat_map(|event| event.attributes.iter())
+                        .find(|attribute| attribute.key == key)
+                        .map(|attribute| attribute.value.clone())
+                };
+                assert_eq!(
+                    attribute("total_refund_to_proposer"),
+                    Some("1100uom".to_string())
+                );
+                assert_eq!(
+                    attribute("refunded_contributors"),
+                    Some(format!("{admin}:500uom;{friend}:300uom"))
+                );
+            },
+        )
+        // everyone got their own share back
+        .query_balance("uom", &proposer, |balance| {
+            assert_eq!(balance.u128(), INITIAL_BALANCE);
+        })
+        .query_balance("uom", &friend, |balance| {
+            assert_eq!(balance.u128(), INITIAL_BALANCE);
+        })
+        .query_balance("uom", &admin, |balance| {
+            assert_eq!(balance.u128(), INITIAL_BALANCE);
+        })
+        .contribute_gift(
+            &friend,
+            0,
+            &[coin(100, "uom")],
+            |r: Result<AppResponse, anyhow::Error>| {
+                assert!(matches!(
+                    r.unwrap_err().downcast::<ContractError>().unwrap(),
+                    ContractError::ProposalNotPending { .. }
+                ))
+            },
+        );
+
+    // contributions are refunded on cancellation too
+    suite
+        .create_proposal(
+            &proposer,
+            None,
+            None,
+            receiver.to_string(),
+            vec![coin(1_000, "uom")],
+            &[coin(1_100, "uom")],
+            |r: Result<AppResponse, anyhow::Error>| assert!(r.is_ok()),
+        )
+        .contribute_gift(
+            &friend,
+            1,
+            &[coin(200, "uom")],
+            |r: Result<AppResponse, anyhow::Error>| assert!(r.is_ok()),
+        )
+        .cancel_proposal(&proposer, 1, |r: Result<AppResponse, anyhow::Error>| {
+            assert!(r.is_ok())
+        })
+        .query_balance("uom", &proposer, |balance| {
+            assert_eq!(balance.u128(), INITIAL_BALANCE);
+        })
+        .query_balance("uom", &friend, |balance| {
+            assert_eq!(balance.u128(), INITIAL_BALANCE);
+        });
+
+    // the receiver gets the whole gift on yes
+    suite
+        .create_proposal(
+            &proposer,
+            None,
+            None,
+            receiver.to_string(),
+            vec![coin(1_000, "uom")],
+            &[coin(1_100, "uom")],
+            |r: Result<AppResponse, anyhow::Error>| assert!(r.is_ok()),
+        )
+        .contribute_gift(
+            &friend,
+            2,
+            &[coin(500, "uom")],
+            |r: Result<AppResponse, anyhow::Error>| assert!(r.is_ok()),
+        )
+        .say_yes(
+            &receiver,
+            2,
+            None,
+            |r: Result<AppResponse, anyhow::Error>| assert!(r.is_ok()),
+        )
+        .query_balance("uom", &receiver, |balance| {
+            assert_eq!(balance.u128(), INITIAL_BALANCE + 1_500);
+        })
+        .query_balance("uom", &friend, |balance| {
+            assert_eq!(balance.u128(), INITIAL_BALANCE - 500);
+        });
+
+    // a declined gift goes back to the proposer and the contributors
+    suite
+        .create_proposal(
+            &proposer,
+            None,
+            None,
+            receiver.to_string(),
+            vec![coin(1_000, "uom")],
+            &[coin(1_100, "uom")],
+            |r: Result<AppResponse, anyhow::Error>| assert!(r.is_ok()),
+        )
+        .contribute_gift(
+            &friend,
+            3,
+            &[coin(400, "uom")],
+            |r: Result<AppResponse, anyhow::Error>| assert!(r.is_ok()),
+        )
+        .say_yes_with(
+            &receiver,
+            YesAnswer {
+                id: 3,
+                gift_disposition: Some(GiftDisposition::Decline),
+                ..Default::default()
+            },
+            &[],
+            |r: Result<AppResponse, anyhow::Error>| {
+                let response = r.unwrap();
+                let attribute = |key: &str| {
+                    response
+                        .events
+                        .iter()
+                        .flat_map(|event| event.attributes.iter())
+                        .find(|attribute| attribute.key == key)
+                        .map(|attribute| attribute.value.clone())
+                };
+                assert_eq!(
+                    attribute("gift_transfers"),
+                    Some(format!("{proposer}:1000uom;{friend}:400uom"))
+                );
+            },
+        )
+        .query_balance("uom", &receiver, |balance| {
+            assert_eq!(balance.u128(), INITIAL_BALANCE + 1_500);
+        })
+        .query_balance("uom", &friend, |balance| {
+            assert_eq!(balance.u128(), INITIAL_BALANCE - 500);
+        })
+        // the proposer only paid the fees of the two accepted proposals
+        .query_balance("uom", &proposer, |balance| {
+            assert_eq!(balance.u128(), INITIAL_BALANCE - 1_000 - 200);
+        });
+}
+
+#[test]
+fn test_gift_disposition() {
+    let mut suite = TestingSuite::default_with_balances(vec![coin(INITIAL_BALANCE, "uom")]);
+
+    let admin = suite.admin();
+    let proposer = suite.senders[1].clone();
+    let receiver = suite.senders[2].clone();
+    let charity = suite.senders[3].clone();
+
+    suite.instantiate_proposal_contract(Some(admin.to_string()));
+    for _ in 0..3 {
+        suite.create_proposal(
+            &proposer,
+            None,
+            None,
+            receiver.to_string(),
+            vec![coin(1_000, "uom")],
+            &[coin(1_100, "uom")],
+            |r: Result<AppResponse, anyhow::Error>| assert!(r.is_ok()),
+        );
+    }
+
+    suite
+        .say_yes_with(
+            &receiver,
+            YesAnswer {
+                id: 0,
+                gift_disposition: Some(GiftDisposition::Split {
+                    address: charity.clone(),
+                    share_bps: 0,
+                }),
+                ..Default::default()
+            },
+            &[],
+            |r: Result<AppResponse, anyhow::Error>| {
+                assert!(matches!(
+                    r.unwrap_err().downcast::<ContractError>().unwrap(),
+                    ContractError::InvalidGiftDisposition { .. }
+                ))
+            },
+        )
+        .say_yes_with(
+            &receiver,
+            YesAnswer {
+                id: 0,
+                gift_disposition: Some(GiftDisposition::SendTo {
+                    address: Addr::unchecked("invalid"),
+                }),
+                ..Default::default()
+            },
+            &[],
+            |r: Result<AppResponse, anyhow::Error>| assert!(r.is_err()),
+        )
+        // a quarter of the gift goes to the charity
+        .say_yes_with(
+            &receiver,
+            YesAnswer {
+                id: 0,
+                gift_disposition: Some(GiftDisposition::Split {
+                    address: charity.clone(),
+                    share_bps: 2_500,
+                }),
+                ..Default::default()
+            },
+            &[],
+            |r: Result<AppResponse, anyhow::Error>| {
+                let response = r.unwrap();
+                let attribute = |key: &str| {
+                    response
+                        .events
+                        .iter()
+                        .flat_map(|event| event.attributes.iter())
+                        .find(|attribute| attribute.key == key)
+                        .map(|attribute| attribute.value.clone())
+                };
+                assert_eq!(
+                    attribute("gift_received_by_receiver"),
+                    Some("750uom".to_string())
+                );
+                assert_eq!(
+                    attribute("gift_transfers"),
+                    Some(format!("{receiver}:750uom;{charity}:250uom"))
+                );
+            },
+        )
+        .query_balance("uom", &receiver, |balance| {
+            assert_eq!(balance.u128(), INITIAL_BALANCE + 750);
+        })
+        .query_balance("uom", &charity, |balance| {
+            assert_eq!(balance.u128(), INITIAL_BALANCE + 250);
+        })
+        .query_proposal(0, |r| {
+            let proposal = r.unwrap();
+            assert_eq!(proposal.status, ProposalStatus::Yes);
+            assert_eq!(
+                proposal.gift_disposition,
+                Some(GiftDisposition::Split {
+                    address: charity.clone(),
+                    share_bps: 2_500,
+                })
+            );
+        })
+        // the whole gift goes to a cold wallet
+        .say_yes_with(
+            &receiver,
+            YesAnswer {
+                id: 1,
+                gift_disposition: Some(GiftDisposition::SendTo {
+                    address: charity.clone(),
+                }),
+                ..Default::default()
+            },
+            &[],
+            |r: Result<AppResponse, anyhow::Error>| assert!(r.is_ok()),
+        )
+        .query_balance("uom", &receiver, |balance| {
+            assert_eq!(balance.u128(), INITIAL_BALANCE + 750);
+        })
+        .query_balance("uom", &charity, |balance| {
+            assert_eq!(balance.u128(), INITIAL_BALANCE + 1_250);
+        })
+        // the proposal is accepted but the gift returns to the proposer, the fee is still due
+        .say_yes_with(
+            &receiver,
+            YesAnswer {
+                id: 2,
+                gift_disposition: Some(GiftDisposition::Decline),
+                ..Default::default()
+            },
+            &[],
+            |r: Result<AppResponse, anyhow::Error>| assert!(r.is_ok()),
+        )
+        .query_proposal(2, |r| {
+            assert_eq!(r.unwrap().status, ProposalStatus::Yes);
+        })
+        .query_balance("uom", &proposer, |balance| {
+            assert_eq!(balance.u128(), INITIAL_BALANCE - 2_000 - 300);
+        })
+        .withdraw_fees(
+            &admin,
+            vec![],
+            None,
+            |r: Result<AppResponse, anyhow::Error>| assert!(r.is_ok()),
+        )
+        .query_balance("uom", &admin, |balance| {
+            assert_eq!(balance.u128(), INITIAL_BALANCE + 300);
+        });
+}
+
+#[test]
+fn test_gift_policy() {
+    let mut suite = TestingSuite::default_with_balances(vec![
+        coin(INITIAL_BALANCE, "uom"),
+        coin(INITIAL_BALANCE, "uusdc"),
+        coin(INITIAL_BALANCE, "uspam"),
+    ]);
+
+    let admin = suite.admin();
+    let proposer = suite.senders[1].clone();
+    let receiver = suite.senders[2].clone();
+    let friend = suite.senders[3].clone();
+
+    suite
+        .instantiate_proposal_contract_with_msg(InstantiateMsg {
+            gift_policy: Some(GiftPolicy {
+                allowed_denoms: vec!["uom".to_string(), "uusdc".to_string()],
+                limits: vec![DenomLimit {
+                    denom: "uom".to_string(),
+                    min: Some(Uint128::new(10)),
+                    max: Some(Uint128::new(5_000)),
+                }],
+                max_denoms: Some(1),
+                escrow_caps: vec![coin(8_000, "uom")],
+                ..Default::default()
+            }),
+            ..TestingSuite::default_instantiate_msg(Some(admin.to_string()))
+        })
+        .update_config(
+            &admin,
+            ConfigUpdate {
+                gift_policy: Some(GiftPolicy {
+                    limits: vec![DenomLimit {
+                        denom: "uom".to_string(),
+                        min: Some(Uint128::new(10)),
+                        max: Some(Uint128::new(5)),
+                    }],
+                    ..Default::default()
+                }),
+                ..Default::default()
+            },
+            |r: Result<AppResponse, anyhow::Error>| {
+                assert!(matches!(
+                    r.unwrap_err().downcast::<ContractError>().unwrap(),
+                    ContractError::InvalidGiftPolicy { .. }
+                ))
+            },
+        )
+        .create_proposal(
+            &proposer,
+            None,
+            None,
+            receiver.to_string(),
+            vec![coin(100, "uspam")],
+            &[coin(100, "uom"), coin(100, "uspam")],
+            |r: Result<AppResponse, anyhow::Error>| {
+                assert!(matches!(
+                    r.unwrap_err().downcast::<ContractError>().unwrap(),
+                    ContractError::GiftDenomNotAllowed { .. }
+                ))
+            },
+        )
+        .create_proposal(
+            &proposer,
+            None,
+            None,
+            receiver.to_string(),
+            vec![coin(5, "uom")],
+            &[coin(105, "uom")],
+            |r: Result<AppResponse, anyhow::Error>| {
+                assert!(matches!(
+                    r.unwrap_err().downcast::<ContractError>().unwrap(),
+                    ContractError::GiftAmountTooLow { .. }
+                ))
+            },
+        )
+        .create_proposal(
+            &proposer,
+            None,
+            None,
+            receiver.to_string(),
+            vec![coin(6_000, "uom")],
+            &[coin(6_100, "uom")],
+            |r: Result<AppResponse, anyhow::Error>| {
+                assert!(matches!(
+                    r.unwrap_err().downcast::<ContractError>().unwrap(),
+                    ContractError::GiftAmountTooHigh { .. }
+                ))
+            },
+        )
+        .create_proposal(
+            &proposer,
+            None,
+            None,
+            receiver.to_string(),
+            vec![coin(100, "uom"), coin(100, "uusdc")],
+            &[coin(200, "uom"), coin(100, "uusdc")],
+            |r: Result<AppResponse, anyhow::Error>| {
+                assert!(matches!(
+                    r.unwrap_err().downcast::<ContractError>().unwrap(),
+                    ContractError::TooManyGiftDenoms { count: 2, max: 1 }
+                ))
+            },
+        )
+        .create_proposal(
+            &proposer,
+            None,
+            None,
+            receiver.to_string(),
+            vec![coin(4_000, "uom")],
+            &[coin(4_100, "uom")],
+            |r: Result<AppResponse, anyhow::Error>| assert!(r.is_ok()),
+        )
+        // the gifts in escrow would total 8_001uom
+        .create_proposal(
+            &proposer,
+            None,
+            None,
+            receiver.to_string(),
+            vec![coin(4_001, "uom")],
+            &[coin(4_101, "uom")],
+            |r: Result<AppResponse, anyhow::Error>| {
+                assert!(matches!(
+                    r.unwrap_err().downcast::<ContractError>().unwrap(),
+                    ContractError::EscrowCapExceeded { .. }
+                ))
+            },
+        )
+        // top-ups and contributions are subject to the policy too
+        .update_proposal(
+            &proposer,
+            0,
+            None,
+            None,
+            vec![coin(1_500, "uom")],
+            &[coin(1_500, "uom")],
+            |r: Result<AppResponse, anyhow::Error>| {
+                assert!(matches!(
+                    r.unwrap_err().downcast::<ContractError>().unwrap(),
+                    ContractError::GiftAmountTooHigh { .. }
+                ))
+            },
+        )
+        .contribute_gift(
+            &friend,
+            0,
+            &[coin(5, "uom")],
+            |r: Result<AppResponse, anyhow::Error>| {
+                assert!(matches!(
+                    r.unwrap_err().downcast::<ContractError>().unwrap(),
+                    ContractError::GiftAmountTooLow { .. }
+                ))
+            },
+        )
+        .contribute_gift(
+            &friend,
+            0,
+            &[coin(100, "uusdc")],
+            |r: Result<AppResponse, anyhow::Error>| {
+                assert!(matches!(
+                    r.unwrap_err().downcast::<ContractError>().unwrap(),
+                    ContractError::TooManyGiftDenoms { .. }
+                ))
+            },
+        )
+        .contribute_gift(
+            &friend,
+            0,
+            &[coin(1_000, "uom")],
+            |r: Result<AppResponse, anyhow::Error>| assert!(r.is_ok()),
+        )
+        // lifting the policy lets any gift through
+        .update_config(
+            &admin,
+            ConfigUpdate {
+                gift_policy: Some(GiftPolicy::default()),
+                ..Default::default()
+            },
+            |r: Result<AppResponse, anyhow::Error>| assert!(r.is_ok()),
+        )
+        .create_proposal(
+            &proposer,
+            None,
+            None,
+            receiver.to_string(),
+            vec![coin(100, "uspam")],
+            &[coin(100, "uom"), coin(100, "uspam")],
+            |r: Result<AppResponse, anyhow::Error>| assert!(r.is_ok()),
+        )
+        .query_config(|r| {
+            assert_eq!(r.unwrap().gift_policy, GiftPolicy::default());
+        });
+}
+
+#[test]
+fn test_escrow_caps_only_count_escrowed_gifts() {
+    let mut suite = TestingSuite::default_with_balances(vec![coin(INITIAL_BALANCE, "uom")]);
+
+    let admin = suite.admin();
+    let proposer = suite.senders[1].clone();
+    let receiver = suite.senders[2].clone();
+    let validator = "validator";
+    let unbonding_period = 60;
+
+    suite
+        .add_validator(validator, unbonding_period)
+        .instantiate_proposal_contract_with_msg(InstantiateMsg {
+            gift_policy: Some(GiftPolicy {
+                escrow_caps: vec![coin(1_000, "uom")],
+                ..Default::default()
+            }),
+            staking: Some(StakingConfig {
+                validator: validator.to_string(),
+                unbonding_period,
+            }),
+            ..TestingSuite::default_instantiate_msg(Some(admin.to_string()))
+        });
+
+    // the treasury fills up with the fees of accepted gift-less proposals
+    for id in 0..10 {
+        suite
+            .create_proposal(
+                &proposer,
+                None,
+                None,
+                receiver.to_string(),
+                vec![],
+                &[coin(100, "uom")],
+                |r: Result<AppResponse, anyhow::Error>| assert!(r.is_ok()),
+            )
+            .say_yes(
+                &receiver,
+                id,
+                None,
+                |r: Result<AppResponse, anyhow::Error>| assert!(r.is_ok()),
+            );
+    }
+
+    suite
+        .query_treasury(None, None, |r| {
+            assert_eq!(r.unwrap().fees[0].available, Uint128::new(1_000));
+        })
+        // the fees in the treasury don't count towards the cap
+        .create_proposal(
+            &proposer,
+            None,
+            None,
+            receiver.to_string(),
+            vec![coin(1_000, "uom")],
+            &[coin(1_100, "uom")],
+            |r: Result<AppResponse, anyhow::Error>| assert!(r.is_ok()),
+        )
+        .create_proposal(
+            &proposer,
+            None,
+            None,
+            receiver.to_string(),
+            vec![coin(10, "uom")],
+            &[coin(110, "uom")],
+            |r: Result<AppResponse, anyhow::Error>| {
+                assert_eq!(
+                    r.unwrap_err().downcast::<ContractError>().unwrap(),
+                    ContractError::EscrowCapExceeded {
+                        denom: "uom".to_string(),
+                        escrowed: Uint128::new(1_010),
+                        cap: Uint128::new(1_000),
+                    }
+                )
+            },
+        )
+        // the gift leaves the escrow once accepted
+        .say_yes(
+            &receiver,
+            10,
+            None,
+            |r: Result<AppResponse, anyhow::Error>| assert!(r.is_ok()),
+        )
+        .create_proposal_with(
+            &proposer,
+            NewProposal {
+                receiver: receiver.to_string(),
+                gift: vec![coin(1_000, "uom")],
+                stake_gift: true,
+                ..Default::default()
+            },
+            &[coin(1_100, "uom")],
+            |r: Result<AppResponse, anyhow::Error>| assert!(r.is_ok()),
+        )
+        // a staked gift counts towards the cap although it left the contract's balance
+        .create_proposal(
+            &proposer,
+            None,
+            None,
+            receiver.to_string(),
+            vec![coin(10, "uom")],
+            &[coin(110, "uom")],
+            |r: Result<AppResponse, anyhow::Error>| {
+                assert!(matches!(
+                    r.unwrap_err().downcast::<ContractError>().unwrap(),
+                    ContractError::EscrowCapExceeded { .. }
+                ))
+            },
+        )
+        // until it's paid out once unbonded
+        .cancel_proposal(&proposer, 11, |r: Result<AppResponse, anyhow::Error>| {
+            assert!(r.is_ok())
+        })
+        .create_proposal(
+            &proposer,
+            None,
+            None,
+            receiver.to_string(),
+            vec![coin(10, "uom")],
+            &[coin(110, "uom")],
+            |r: Result<AppResponse, anyhow::Error>| {
+                assert!(matches!(
+                    r.unwrap_err().downcast::<ContractError>().unwrap(),
+                    ContractError::EscrowCapExceeded { .. }
+                ))
+            },
+        )
+        .add_seconds(unbonding_period)
+        .claim_unbonded(&admin, 11, |r: Result<AppResponse, anyhow::Error>| {
+            assert!(r.is_ok())
+        })
+        .create_proposal(
+            &proposer,
+            None,
+            None,
+            receiver.to_string(),
+            vec![coin(1_000, "uom")],
+            &[coin(1_100, "uom")],
+            |r: Result<AppResponse, anyhow::Error>| assert!(r.is_ok()),
+        )
+        // the counter-gift goes to the proposer right away, it isn't escrowed
+        .create_proposal_with(
+            &proposer,
+            NewProposal {
+                receiver: receiver.to_string(),
+                counter_gift: vec![coin(500, "uom")],
+                ..Default::default()
+            },
+            &[coin(100, "uom")],
+            |r: Result<AppResponse, anyhow::Error>| assert!(r.is_ok()),
+        )
+        .say_yes_with(
+            &receiver,
+            YesAnswer {
+                id: 13,
+                ..Default::default()
+            },
+            &[coin(500, "uom")],
+            |r: Result<AppResponse, anyhow::Error>| assert!(r.is_ok()),
+        );
+
+    // a counter-gift held during the cooling-off window counts towards the cap, although its
+    // deposit isn't capped
+    suite
+        .cancel_proposal(&proposer, 12, |r: Result<AppResponse, anyhow::Error>| {
+            assert!(r.is_ok())
+        })
+        .update_config(
+            &admin,
+            ConfigUpdate {
+                finalization_delay: Some(Duration::Height(5)),
+                ..Default::default()
+            },
+            |r: Result<AppResponse, anyhow::Error>| assert!(r.is_ok()),
+        )
+        .create_proposal_with(
+            &proposer,
+            NewProposal {
+                receiver: receiver.to_string(),
+                counter_gift: vec![coin(995, "uom")],
+                ..Default::default()
+            },
+            &[coin(100, "uom")],
+            |r: Result<AppResponse, anyhow::Error>| assert!(r.is_ok()),
+        )
+        .say_yes_with(
+            &receiver,
+            YesAnswer {
+                id: 14,
+                ..Default::default()
+            },
+            &[coin(995, "uom")],
+            |r: Result<AppResponse, anyhow::Error>| assert!(r.is_ok()),
+        )
+        .create_proposal(
+            &proposer,
+            None,
+            None,
+            receiver.to_string(),
+            vec![coin(10, "uom")],
+            &[coin(110, "uom")],
+            |r: Result<AppResponse, anyhow::Error>| {
+                assert_eq!(
+                    r.unwrap_err().downcast::<ContractError>().unwrap(),
+                    ContractError::EscrowCapExceeded {
+                        denom: "uom".to_string(),
+                        escrowed: Uint128::new(995 + 10),
+                        cap: Uint128::new(1_000),
+                    }
+                )
+            },
+        )
+        // until it's refunded
+        .revoke_answer(&receiver, 14, |r: Result<AppResponse, anyhow::Error>| {
+            assert!(r.is_ok())
+        })
+        .create_proposal(
+            &proposer,
+            None,
+            None,
+            receiver.to_string(),
+            vec![coin(10, "uom")],
+            &[coin(110, "uom")],
+            |r: Result<AppResponse, anyhow::Error>| assert!(r.is_ok()),
+        );
+}
+
+#[test]
+fn test_staked_gifts() {
+    let mut suite = TestingSuite::default_with_balances(vec![coin(INITIAL_BALANCE, "uom")]);
+
+    let admin = suite.admin();
+    let proposer = suite.senders[1].clone();
+    let receiver = suite.senders[2].clone();
+    let friend = suite.senders[3].clone();
+    let validator = "validator";
+    let unbonding_period = 60;
+
+    suite
+        .add_validator(validator, unbonding_period)
+        .instantiate_proposal_contract(Some(admin.to_string()))
+        .create_proposal_with(
+            &proposer,
+            NewProposal {
+                receiver: receiver.to_string(),
+                gift: vec![coin(100_000, "uom")],
+                stake_gift: true,
+                ..Default::default()
+            },
+            &[coin(100_100, "uom")],
+            |r: Result<AppResponse, anyhow::Error>| {
+                assert!(matches!(
+                    r.unwrap_err().downcast::<ContractError>().unwrap(),
+                    ContractError::StakingNotEnabled
+                ))
+            },
+        )
+        .update_config(
+            &admin,
+            ConfigUpdate {
+                staking: Some(StakingConfig {
+                    validator: "unknown".to_string(),
+                    unbonding_period,
+                }),
+                ..Default::default()
+            },
+            |r: Result<AppResponse, anyhow::Error>| {
+                assert!(matches!(
+                    r.unwrap_err().downcast::<ContractError>().unwrap(),
+                    ContractError::InvalidStakingConfig { .. }
+                ))
+            },
+        )
+        .update_config(
+            &admin,
+            ConfigUpdate {
+                staking: Some(StakingConfig {
+                    validator: validator.to_string(),
+                    unbonding_period,
+                }),
+                ..Default::default()
+            },
+            |r: Result<AppResponse, anyhow::Error>| assert!(r.is_ok()),
+        )
+        .create_proposal_with(
+            &proposer,
+            NewProposal {
+                receiver: receiver.to_string(),
+                gift: vec![coin(100_000, "uom")],
+                stake_gift: true,
+                ..Default::default()
+            },
+            &[coin(100_100, "uom")],
+            |r: Result<AppResponse, anyhow::Error>| assert!(r.is_ok()),
+        )
+        .contribute_gift(
+            &friend,
+            0,
+            &[coin(100_000, "uom")],
+            |r: Result<AppResponse, anyhow::Error>| assert!(r.is_ok()),
+        )
+        .create_proposal_with(
+            &proposer,
+            NewProposal {
+                receiver: receiver.to_string(),
+                gift: vec![coin(100_000, "uom")],
+                stake_gift: true,
+                ..Default::default()
+            },
+            &[coin(100_100, "uom")],
+            |r: Result<AppResponse, anyhow::Error>| assert!(r.is_ok()),
+        )
+        .query_proposal(0, |r| {
+            let staking = r.unwrap().staking.unwrap();
+            assert_eq!(staking.validator, validator);
+            assert_eq!(staking.amount, coin(200_000, "uom"));
+        });
+
+    // only the fees are left in the contract
+    let contract = suite.proposal_manager_contract_addr.clone();
+    suite
+        .query_balance("uom", &contract, |balance| {
+            assert_eq!(balance.u128(), 200);
+        })
+        // a year at 10% on the 300_000uom delegated
+        .add_seconds(365 * 24 * 60 * 60)
+        .say_yes(
+            &receiver,
+            0,
+            None,
+            |r: Result<AppResponse, anyhow::Error>| assert!(r.is_ok()),
+        )
+        // the receiver gets the rewards of their gift right away
+        .query_balance("uom", &receiver, |balance| {
+            assert_eq!(balance.u128(), INITIAL_BALANCE + 20_000);
+        })
+        .claim_unbonded(&receiver, 0, |r: Result<AppResponse, anyhow::Error>| {
+            assert!(matches!(
+                r.unwrap_err().downcast::<ContractError>().unwrap(),
+                ContractError::UnbondingNotOver { .. }
+            ))
+        })
+        // the proposer gets the rewards back on cancellation, along with the fee
+        .cancel_proposal(&proposer, 1, |r: Result<AppResponse, anyhow::Error>| {
+            assert!(r.is_ok())
+        })
+        .query_balance("uom", &proposer, |balance| {
+            assert_eq!(balance.u128(), INITIAL_BALANCE - 200_200 + 100 + 10_000);
+        })
+        // the gift was queued for the next batch, the previous one being undelegated this epoch
+        .claim_unbonded(&admin, 1, |r: Result<AppResponse, anyhow::Error>| {
+            assert!(matches!(
+                r.unwrap_err().downcast::<ContractError>().unwrap(),
+                ContractError::UnbondingNotStarted { .. }
+            ))
+        })
+        .undelegate_queued(
+            &admin,
+            validator,
+            |r: Result<AppResponse, anyhow::Error>| {
+                assert!(matches!(
+                    r.unwrap_err().downcast::<ContractError>().unwrap(),
+                    ContractError::UndelegationNotDue { .. }
+                ))
+            },
+        )
+        .undelegate_queued(
+            &admin,
+            "unknown",
+            |r: Result<AppResponse, anyhow::Error>| {
+                assert!(matches!(
+                    r.unwrap_err().downcast::<ContractError>().unwrap(),
+                    ContractError::NothingToUndelegate { .. }
+                ))
+            },
+        )
+        .add_seconds(unbonding_period / 6)
+        .undelegate_queued(
+            &admin,
+            validator,
+            |r: Result<AppResponse, anyhow::Error>| assert!(r.is_ok()),
+        )
+        .add_seconds(unbonding_period)
+        .claim_unbonded(&admin, 0, |r: Result<AppResponse, anyhow::Error>| {
+            assert!(r.is_ok())
+        })
+        .claim_unbonded(&admin, 0, |r: Result<AppResponse, anyhow::Error>| {
+            assert!(matches!(
+                r.unwrap_err().downcast::<ContractError>().unwrap(),
+                ContractError::NoUnbondedGift { .. }
+            ))
+        })
+        .claim_unbonded(&admin, 1, |r: Result<AppResponse, anyhow::Error>| {
+            assert!(r.is_ok())
+        })
+        .query_balance("uom", &receiver, |balance| {
+            assert_eq!(balance.u128(), INITIAL_BALANCE + 220_000);
+        })
+        .query_balance("uom", &proposer, |balance| {
+            assert_eq!(balance.u128(), INITIAL_BALANCE - 100_100 + 10_000);
+        })
+        .withdraw_fees(
+            &admin,
+            vec![],
+            None,
+            |r: Result<AppResponse, anyhow::Error>| assert!(r.is_ok()),
+        )
+        .query_balance("uom", &admin, |balance| {
+            assert_eq!(balance.u128(), INITIAL_BALANCE + 100);
+        })
+        .query_balance("uom", &contract, |balance| {
+            assert_eq!(balance.u128(), 0);
+        })
+        .query_proposal(1, |r| {
+            let unbonding = r.unwrap().staking.unwrap().unbonding.unwrap();
+            assert!(unbonding.claimed);
+            assert_eq!(unbonding.payouts[0].recipient, proposer);
+        });
+}
+
+#[test]
+fn test_staked_gifts_unbond_in_batches_after_slashing() {
+    let mut suite = TestingSuite::default_with_balances(vec![coin(INITIAL_BALANCE, "uom")]);
+
+    let admin = suite.admin();
+    let proposer = suite.senders[1].clone();
+    let receiver = suite.senders[2].clone();
+    let validator = "validator";
+    let unbonding_period = 60;
+
+    suite
+        .add_validator(validator, unbonding_period)
+        .instantiate_proposal_contract(Some(admin.to_string()))
+        .update_config(
+            &admin,
+            ConfigUpdate {
+                staking: Some(StakingConfig {
+                    validator: validator.to_string(),
+                    unbonding_period,
+                }),
+                ..Default::default()
+            },
+            |r: Result<AppResponse, anyhow::Error>| assert!(r.is_ok()),
+        );
+    // more proposals than the chain allows unbonding entries
+    for _ in 0..8 {
+        suite.create_proposal_with(
+            &proposer,
+            NewProposal {
+                receiver: receiver.to_string(),
+                gift: vec![coin(10_000, "uom")],
+                stake_gift: true,
+                ..Default::default()
+            },
+            &[coin(10_100, "uom")],
+            |r: Result<AppResponse, anyhow::Error>| assert!(r.is_ok()),
+        );
+    }
+
+    // the delegation is worth half of the staked gifts
+    suite.slash_validator(validator, Decimal::percent(50));
+    for id in 0..8 {
+        suite.cancel_proposal(&proposer, id, |r: Result<AppResponse, anyhow::Error>| {
+            assert!(r.is_ok())
+        });
+    }
+    suite
+        .query_proposal(0, |r| {
+            let unbonding = r.unwrap().staking.unwrap().unbonding.unwrap();
+            assert_eq!(unbonding.batch, 0);
+            assert_eq!(unbonding.payouts[0].amount, coin(5_000, "uom"));
+        })
+        .query_proposal(7, |r| {
+            let unbonding = r.unwrap().staking.unwrap().unbonding.unwrap();
+            assert_eq!(unbonding.batch, 1);
+            assert_eq!(unbonding.payouts[0].amount, coin(5_000, "uom"));
+        })
+        // the first gift was undelegated right away, the others together once the epoch is over
+        .add_seconds(unbonding_period / 6)
+        .undelegate_queued(
+            &admin,
+            validator,
+            |r: Result<AppResponse, anyhow::Error>| {
+                let response = r.unwrap();
+                let undelegated = response
+                    .events
+                    .iter()
+                    .flat_map(|event| event.attributes.iter())
+                    .find(|attribute| attribute.key == "undelegated")
+                    .map(|attribute| attribute.value.clone());
+                assert_eq!(undelegated, Some("35000uom".to_string()));
+            },
+        )
+        .add_seconds(unbonding_period);
+    for id in 0..8 {
+        suite.claim_unbonded(&admin, id, |r: Result<AppResponse, anyhow::Error>| {
+            assert!(r.is_ok())
+        });
+    }
+
+    // the proposer bears the slashing, everything else was refunded
+    let contract = suite.proposal_manager_contract_addr.clone();
+    suite
+        .query_balance("uom", &proposer, |balance| {
+            assert_eq!(balance.u128(), INITIAL_BALANCE - 8 * 5_000);
+        })
+        .query_balance("uom", &contract, |balance| {
+            assert_eq!(balance.u128(), 0);
+        });
+}
+
+#[test]
+fn test_queued_gift_rewards_go_to_treasury() {
+    let mut suite = TestingSuite::default_with_balances(vec![coin(INITIAL_BALANCE, "uom")]);
+
+    let admin = suite.admin();
+    let proposer = suite.senders[1].clone();
+    let receiver = suite.senders[2].clone();
+    let validator = "validator";
+    // a year long epoch
+    let unbonding_period = 6 * 31_536_000;
+
+    suite
+        .add_validator(validator, unbonding_period)
+        .instantiate_proposal_contract(Some(admin.to_string()))
+        .update_config(
+            &admin,
+            ConfigUpdate {
+                staking: Some(StakingConfig {
+                    validator: validator.to_string(),
+                    unbonding_period,
+                }),
+                ..Default::default()
+            },
+            |r: Result<AppResponse, anyhow::Error>| assert!(r.is_ok()),
+        );
+    for _ in 0..2 {
+        suite.create_proposal_with(
+            &proposer,
+            NewProposal {
+                receiver: receiver.to_string(),
+                gift: vec![coin(10_000, "uom")],
+                stake_gift: true,
+                ..Default::default()
+            },
+            &[coin(10_100, "uom")],
+            |r: Result<AppResponse, anyhow::Error>| assert!(r.is_ok()),
+        );
+    }
+
+    // the second gift stays queued for the epoch, earning rewards no proposal is owed
+    suite
+        .cancel_proposal(&proposer, 0, |r: Result<AppResponse, anyhow::Error>| {
+            assert!(r.is_ok())
+        })
+        .cancel_proposal(&proposer, 1, |r: Result<AppResponse, anyhow::Error>| {
+            assert!(r.is_ok())
+        })
+        .add_seconds(unbonding_period / 6)
+        .undelegate_queued(
+            &admin,
+            validator,
+            |r: Result<AppResponse, anyhow::Error>| assert!(r.is_ok()),
+        )
+        .query_treasury(None, None, |r| {
+            let fees = r.unwrap().fees;
+            assert_eq!(fees.len(), 1);
+            assert_eq!(fees[0].denom, "uom");
+            assert_eq!(fees[0].accrued, Uint128::new(1_000));
+        })
+        .add_seconds(unbonding_period);
+    for id in 0..2 {
+        suite.claim_unbonded(&admin, id, |r: Result<AppResponse, anyhow::Error>| {
+            assert!(r.is_ok())
+        });
+    }
+
+    // the gifts were refunded, only the treasury's rewards are left
+    let contract = suite.proposal_manager_contract_addr.clone();
+    suite
+        .query_balance("uom", &proposer, |balance| {
+            assert_eq!(balance.u128(), INITIAL_BALANCE);
+        })
+        .query_balance("uom", &contract, |balance| {
+            assert_eq!(balance.u128(), 1_000);
+        });
+}
+
+#[test]
+fn test_unrevealed_staked_gift_is_hidden() {
+    let mut suite = TestingSuite::default_with_balances(vec![coin(INITIAL_BALANCE, "uom")]);
+
+    let admin = suite.admin();
+    let proposer = suite.senders[1].clone();
+    let receiver = suite.senders[2].clone();
+    let validator = "validator";
+
+    suite
+        .add_validator(validator, 60)
+        .instantiate_proposal_contract(Some(admin.to_string()))
+        .update_config(
+            &admin,
+            ConfigUpdate {
+                staking: Some(StakingConfig {
+                    validator: validator.to_string(),
+                    unbonding_period: 60,
+                }),
+                ..Default::default()
+            },
+            |r: Result<AppResponse, anyhow::Error>| assert!(r.is_ok()),
+        );
+
+    let block = suite.get_block();
+    suite
+        .create_proposal_with(
+            &proposer,
+            NewProposal {
+                receiver: receiver.to_string(),
+                gift: vec![coin(100_000, "uom")],
+                stake_gift: true,
+                reveal_at: Some(Expiration::AtHeight(block + 5)),
+                ..Default::default()
+            },
+            &[coin(100_100, "uom")],
+            |r: Result<AppResponse, anyhow::Error>| assert!(r.is_ok()),
+        )
+        // the delegated amount would give the gift away
+        .query_proposal(0, |r| {
+            let proposal = r.unwrap();
+            assert!(proposal.gift.is_empty());
+            assert_eq!(proposal.staking, None);
+        })
+        .query_proposals(None, None, None, None, |r| {
+            assert_eq!(r.unwrap().proposals[0].staking, None);
+        })
+        .add_100_block()
+        .query_proposal(0, |r| {
+            let staking = r.unwrap().staking.unwrap();
+            assert_eq!(staking.amount, coin(100_000, "uom"));
+        });
+}
+
+#[test]
+fn test_unrevealed_vesting_gift_is_hidden() {
+    let mut suite = TestingSuite::default_with_balances(vec![coin(INITIAL_BALANCE, "uom")]);
+
+    let admin = suite.admin();
+    let proposer = suite.senders[1].clone();
+    let receiver = suite.senders[2].clone();
+
+    suite.instantiate_proposal_contract(Some(admin.to_string()));
+
+    let block = suite.get_block();
+    suite
+        .create_proposal_with(
+            &proposer,
+            NewProposal {
+                receiver: receiver.to_string(),
+                gift: vec![coin(5_000, "uom")],
+                vesting: Some(VestingSchedule::Linear { duration: 100 }),
+                reveal_at: Some(Expiration::AtHeight(block + 5)),
+                ..Default::default()
+            },
+            &[coin(5_100, "uom")],
+            |r: Result<AppResponse, anyhow::Error>| assert!(r.is_ok()),
+        )
+        // the vested amounts would give the gift away
+        .query_vesting(0, |r| {
+            assert!(r
+                .unwrap_err()
+                .to_string()
+                .contains("Proposal 0 has no vesting gift"));
+        })
+        .add_100_block()
+        .query_vesting(0, |r| {
+            let vesting = r.unwrap();
+            assert_eq!(vesting.schedule, VestingSchedule::Linear { duration: 100 });
+            assert_eq!(vesting.amounts[0].total, Uint128::new(5_000));
+        });
+}
+
+#[test]
+fn test_swap_gift_on_acceptance() {
+    let mut suite = TestingSuite::default_with_balances(vec![coin(INITIAL_BALANCE, "uom")]);
+
+    let admin = suite.admin();
+    let proposer = suite.senders[1].clone();
+    let receiver = suite.senders[2].clone();
+    let friend = suite.senders[3].clone();
+    let receive_as = |min_out: u128| ReceiveAs {
+        denom: "uusdc".to_string(),
+        min_out: Uint128::new(min_out),
+    };
+
+    suite
+        .instantiate_proposal_contract(Some(admin.to_string()))
+        .create_proposal(
+            &proposer,
+            None,
+            None,
+            receiver.to_string(),
+            vec![coin(10_000, "uom")],
+            &[coin(10_100, "uom")],
+            |r: Result<AppResponse, anyhow::Error>| assert!(r.is_ok()),
+        )
+        .say_yes_with(
+            &receiver,
+            YesAnswer {
+                id: 0,
+                receive_as: Some(receive_as(20_000)),
+                ..Default::default()
+            },
+            &[],
+            |r: Result<AppResponse, anyhow::Error>| {
+                assert!(matches!(
+                    r.unwrap_err().downcast::<ContractError>().unwrap(),
+                    ContractError::SwapNotEnabled
+                ))
+            },
+        )
+        .instantiate_swap_pool(Decimal::percent(200), vec![coin(1_000_000, "uusdc")]);
+
+    let swap_pool = suite.swap_pool_addr.clone();
+    suite
+        .update_config(
+            &admin,
+            ConfigUpdate {
+                swap_pool: Some(swap_pool.to_string()),
+                ..Default::default()
+            },
+            |r: Result<AppResponse, anyhow::Error>| assert!(r.is_ok()),
+        )
+        .say_yes_with(
+            &receiver,
+            YesAnswer {
+                id: 0,
+                gift_disposition: Some(GiftDisposition::SendTo {
+                    address: friend.clone(),
+                }),
+                receive_as: Some(receive_as(20_000)),
+                ..Default::default()
+            },
+            &[],
+            |r: Result<AppResponse, anyhow::Error>| {
+                assert!(matches!(
+                    r.unwrap_err().downcast::<ContractError>().unwrap(),
+                    ContractError::InvalidSwap { .. }
+                ))
+            },
+        )
+        // the pool pays 20_000uusdc, less than the receiver's minimum
+        .say_yes_with(
+            &receiver,
+            YesAnswer {
+                id: 0,
+                receive_as: Some(receive_as(20_001)),
+                ..Default::default()
+            },
+            &[],
+            |r: Result<AppResponse, anyhow::Error>| {
+                assert_eq!(
+                    r.unwrap_err().root_cause().to_string(),
+                    ContractError::SwapSlippageExceeded {
+                        denom: "uusdc".to_string(),
+                        amount: Uint128::new(20_000),
+                        min_out: Uint128::new(20_001),
+                    }
+                    .to_string()
+                )
+            },
+        )
+        .query_proposal(0, |r| {
+            assert_eq!(r.unwrap().status, ProposalStatus::Pending);
+        })
+        .say_yes_with(
+            &receiver,
+            YesAnswer {
+                id: 0,
+                receive_as: Some(receive_as(20_000)),
+                ..Default::default()
+            },
+            &[],
+            |r: Result<AppResponse, anyhow::Error>| {
+                let response = r.unwrap();
+                let attribute = |key: &str| {
+                    response
+                        .events
+                        .iter()
+                        .flat_map(|event| event.attributes.iter())
+                        .find(|attribute| attribute.key == key)
+                        .map(|attribute| attribute.value.clone())
+                };
+                assert_eq!(attribute("receive_as"), Some("uusdc".to_string()));
+                assert_eq!(attribute("swapped"), Some("10000uom".to_string()));
+                assert_eq!(attribute("received"), Some("20000uusdc".to_string()));
+            },
+        )
+        .query_proposal(0, |r| {
+            let proposal = r.unwrap();
+            assert_eq!(proposal.status, ProposalStatus::Yes);
+            assert_eq!(proposal.receive_as, Some(receive_as(20_000)));
+        })
+        .query_balance("uusdc", &receiver, |balance| {
+            assert_eq!(balance, Uint128::new(20_000));
+        })
+        .query_balance("uom", &receiver, |balance| {
+            assert_eq!(balance, Uint128::new(INITIAL_BALANCE));
+        })
+        .query_balance("uom", &swap_pool, |balance| {
+            assert_eq!(balance, Uint128::new(10_000));
+        })
+        .withdraw_fees(
+            &admin,
+            vec![],
+            None,
+            |r: Result<AppResponse, anyhow::Error>| assert!(r.is_ok()),
+        )
+        .query_balance("uom", &admin, |balance| {
+            assert_eq!(balance, Uint128::new(INITIAL_BALANCE + 100));
+        })
+        .update_config(
+            &admin,
+            ConfigUpdate {
+                swap_pool: Some("".to_string()),
+                ..Default::default()
+            },
+            |r: Result<AppResponse, anyhow::Error>| assert!(r.is_ok()),
+        )
+        .query_config(|r| {
+            assert_eq!(r.unwrap().swap_pool, None);
+        });
+}
+
+#[test]
+fn test_swap_failure_after_cooling_off_window() {
+    let mut suite = TestingSuite::default_with_balances(vec![coin(INITIAL_BALANCE, "uom")]);
+
+    let admin = suite.admin();
+    let proposer = suite.senders[1].clone();
+    let receiver = suite.senders[2].clone();
+    let receive_as = |min_out: u128| ReceiveAs {
+        denom: "uusdc".to_string(),
+        min_out: Uint128::new(min_out),
+    };
+
+    suite
+        .instantiate_proposal_contract_with_msg(InstantiateMsg {
+            finalization_delay: Some(Duration::Time(60)),
+            ..TestingSuite::default_instantiate_msg(Some(admin.to_string()))
+        })
+        .instantiate_swap_pool(Decimal::percent(200), vec![coin(1_000_000, "uusdc")]);
+
+    let swap_pool = suite.swap_pool_addr.clone();
+    suite
+        .update_config(
+            &admin,
+            ConfigUpdate {
+                swap_pool: Some(swap_pool.to_string()),
+                ..Default::default()
+            },
+            |r: Result<AppResponse, anyhow::Error>| assert!(r.is_ok()),
+        )
+        .create_proposal(
+            &proposer,
+            None,
+            None,
+            receiver.to_string(),
+            vec![coin(10_000, "uom")],
+            &[coin(10_100, "uom")],
+            |r: Result<AppResponse, anyhow::Error>| assert!(r.is_ok()),
+        )
+        .create_proposal(
+            &proposer,
+            None,
+            None,
+            receiver.to_string(),
+            vec![coin(10_000, "uom")],
+            &[coin(10_100, "uom")],
+            |r: Result<AppResponse, anyhow::Error>| assert!(r.is_ok()),
+        )
+        // the pool pays 20_000uusdc, the swap only happens once finalized
+        .say_yes_with(
+            &receiver,
+            YesAnswer {
+                id: 0,
+                receive_as: Some(receive_as(20_001)),
+                ..Default::default()
+            },
+            &[],
+            |r: Result<AppResponse, anyhow::Error>| assert!(r.is_ok()),
+        )
+        .add_seconds(60)
+        .finalize(&proposer, 0, |r: Result<AppResponse, anyhow::Error>| {
+            assert_eq!(
+                r.unwrap_err().root_cause().to_string(),
+                ContractError::SwapSlippageExceeded {
+                    denom: "uusdc".to_string(),
+                    amount: Uint128::new(20_000),
+                    min_out: Uint128::new(20_001),
+                }
+                .to_string()
+            )
+        })
+        // the receiver can still revoke the acceptance to answer again
+        .revoke_answer(&receiver, 0, |r: Result<AppResponse, anyhow::Error>| {
+            assert!(r.is_ok())
+        })
+        .query_proposal(0, |r| {
+            let proposal = r.unwrap();
+            assert_eq!(proposal.status, ProposalStatus::Pending);
+            assert_eq!(proposal.receive_as, None);
+        })
+        .say_yes(
+            &receiver,
+            0,
+            None,
+            |r: Result<AppResponse, anyhow::Error>| assert!(r.is_ok()),
+        )
+        .add_seconds(60)
+        // without a swap, the acceptance can't be revoked once the window is over
+        .revoke_answer(&receiver, 0, |r: Result<AppResponse, anyhow::Error>| {
+            assert!(matches!(
+                r.unwrap_err().downcast::<ContractError>().unwrap(),
+                ContractError::CoolingOffWindowClosed { .. }
+            ))
+        })
+        .finalize(&proposer, 0, |r: Result<AppResponse, anyhow::Error>| {
+            assert!(r.is_ok())
+        })
+        .query_balance("uom", &receiver, |balance| {
+            assert_eq!(balance.u128(), INITIAL_BALANCE + 10_000);
+        });
+
+    // the gift is paid unswapped if swapping was disabled since the acceptance
+    suite
+        .say_yes_with(
+            &receiver,
+            YesAnswer {
+                id: 1,
+                receive_as: Some(receive_as(20_000)),
+                ..Default::default()
+            },
+            &[],
+            |r: Result<AppResponse, anyhow::Error>| assert!(r.is_ok()),
+        )
+        .update_config(
+            &admin,
+            ConfigUpdate {
+                swap_pool: Some(String::new()),
+                ..Default::default()
+            },
+            |r: Result<AppResponse, anyhow::Error>| assert!(r.is_ok()),
+        )
+        .add_seconds(60)
+        .finalize(&proposer, 1, |r: Result<AppResponse, anyhow::Error>| {
+            assert!(r.is_ok())
+        })
+        .query_balance("uom", &receiver, |balance| {
+            assert_eq!(balance.u128(), INITIAL_BALANCE + 20_000);
+        })
+        .query_balance("uusdc", &receiver, |balance| {
+            assert!(balance.is_zero());
+        });
+}
+
+#[test]
+fn test_mutual_gift_exchange() {
+    let mut suite = TestingSuite::default_with_balances(vec![coin(INITIAL_BALANCE, "uom")]);
+
+    let admin = suite.admin();
+    let proposer = suite.senders[1].clone();
+    let receiver = suite.senders[2].clone();
+
+    suite
+        .instantiate_proposal_contract(Some(admin.to_string()))
+        .create_proposal_with(
+            &proposer,
+            NewProposal {
+                receiver: receiver.to_string(),
+                gift: vec![coin(10_000, "uom")],
+                counter_gift: vec![coin(0, "uom")],
+                ..Default::default()
+            },
+            &[coin(10_100, "uom")],
+            |r: Result<AppResponse, anyhow::Error>| {
+                assert!(matches!(
+                    r.unwrap_err().downcast::<ContractError>().unwrap(),
+                    ContractError::InvalidCounterGift { .. }
+                ))
+            },
+        )
+        // the counter-gift must comply with the gift policy for the receiver to deposit it
+        .update_config(
+            &admin,
+            ConfigUpdate {
+                gift_policy: Some(GiftPolicy {
+                    allowed_denoms: vec!["uom".to_string()],
+                    limits: vec![DenomLimit {
+                        denom: "uom".to_string(),
+                        min: Some(Uint128::new(1_000)),
+                        max: None,
+                    }],
+                    ..Default::default()
+                }),
+                ..Default::default()
+            },
+            |r: Result<AppResponse, anyhow::Error>| assert!(r.is_ok()),
+        )
+        .create_proposal_with(
+            &proposer,
+            NewProposal {
+                receiver: receiver.to_string(),
+                gift: vec![coin(10_000, "uom")],
+                counter_gift: vec![coin(5_000, "uusdc")],
+                ..Default::default()
+            },
+            &[coin(10_100, "uom")],
+            |r: Result<AppResponse, anyhow::Error>| {
+                assert_eq!(
+                    r.unwrap_err().downcast::<ContractError>().unwrap(),
+                    ContractError::GiftDenomNotAllowed {
+                        denom: "uusdc".to_string()
+                    }
+                )
+            },
+        )
+        .create_proposal_with(
+            &proposer,
+            NewProposal {
+                receiver: receiver.to_string(),
+                gift: vec![coin(10_000, "uom")],
+                counter_gift: vec![coin(500, "uom")],
+                ..Default::default()
+            },
+            &[coin(10_100, "uom")],
+            |r: Result<AppResponse, anyhow::Error>| {
+                assert!(matches!(
+                    r.unwrap_err().downcast::<ContractError>().unwrap(),
+                    ContractError::GiftAmountTooLow { .. }
+                ))
+            },
+        )
+        .create_proposal_with(
+            &proposer,
+            NewProposal {
+                receiver: receiver.to_string(),
+                gift: vec![coin(10_000, "uom")],
+                counter_gift: vec![coin(5_000, "uom")],
+                ..Default::default()
+            },
+            &[coin(10_100, "uom")],
+            |r: Result<AppResponse, anyhow::Error>| assert!(r.is_ok()),
+        )
+        .query_proposal(0, |r| {
+            assert_eq!(r.unwrap().counter_gift, vec![coin(5_000, "uom")]);
+        })
+        .say_yes(
+            &receiver,
+            0,
+            None,
+            |r: Result<AppResponse, anyhow::Error>| {
+                assert!(matches!(
+                    r.unwrap_err().downcast::<ContractError>().unwrap(),
+                    ContractError::CounterGiftNotPaid { .. }
+                ))
+            },
+        )
+        .say_yes_with(
+            &receiver,
+            YesAnswer {
+                id: 0,
+                ..Default::default()
+            },
+            &[coin(4_000, "uom")],
+            |r: Result<AppResponse, anyhow::Error>| {
+                assert!(matches!(
+                    r.unwrap_err().downcast::<ContractError>().unwrap(),
+                    ContractError::CounterGiftNotPaid { .. }
+                ))
+            },
+        )
+        .say_yes_with(
+            &receiver,
+            YesAnswer {
+                id: 0,
+                ..Default::default()
+            },
+            &[coin(5_000, "uom")],
+            |r: Result<AppResponse, anyhow::Error>| {
+                let response = r.unwrap();
+                assert!(response
+                    .events
+                    .iter()
+                    .any(
+                        |event| event.attributes.iter().any(|attribute| attribute.key
+                            == "counter_gift_received_by_proposer"
+                            && attribute.value == "5000uom")
+                    ));
+            },
+        )
+        .query_balance("uom", &receiver, |balance| {
+            assert_eq!(balance, Uint128::new(INITIAL_BALANCE + 10_000 - 5_000));
+        })
+        .query_balance("uom", &proposer, |balance| {
+            assert_eq!(balance, Uint128::new(INITIAL_BALANCE - 10_100 + 5_000));
+        })
+        // nothing is taken from a receiver saying no
+        .create_proposal_with(
+            &proposer,
+            NewProposal {
+                receiver: receiver.to_string(),
+                gift: vec![coin(10_000, "uom")],
+                counter_gift: vec![coin(5_000, "uom")],
+                ..Default::default()
+            },
+            &[coin(10_100, "uom")],
+            |r: Result<AppResponse, anyhow::Error>| assert!(r.is_ok()),
+        )
+        .say_no(
+            &receiver,
+            1,
+            None,
+            |r: Result<AppResponse, anyhow::Error>| assert!(r.is_ok()),
+        )
+        .query_balance("uom", &receiver, |balance| {
+            assert_eq!(balance, Uint128::new(INITIAL_BALANCE + 5_000));
+        })
+        .query_balance("uom", &proposer, |balance| {
+            assert_eq!(balance, Uint128::new(INITIAL_BALANCE - 5_100));
+        })
+        // the counter-gift is escrowed during the cooling-off window
+        .update_config(
+            &admin,
+            ConfigUpdate {
+                finalization_delay: Some(Duration::Height(5)),
+                ..Default::default()
+            },
+            |r: Result<AppResponse, anyhow::Error>| assert!(r.is_ok()),
+        )
+        .create_proposal_with(
+            &proposer,
+            NewProposal {
+                receiver: receiver.to_string(),
+                gift: vec![coin(10_000, "uom")],
+                counter_gift: vec![coin(5_000, "uom")],
+                ..Default::default()
+            },
+            &[coin(10_100, "uom")],
+            |r: Result<AppResponse, anyhow::Error>| assert!(r.is_ok()),
+        )
+        .say_yes_with(
+            &receiver,
+            YesAnswer {
+                id: 2,
+                ..Default::default()
+            },
+            &[coin(5_000, "uom")],
+            |r: Result<AppResponse, anyhow::Error>| assert!(r.is_ok()),
+        )
+        .revoke_answer(&receiver, 2, |r: Result<AppResponse, anyhow::Error>| {
+            assert!(r.is_ok())
+        })
+        .query_balance("uom", &receiver, |balance| {
+            assert_eq!(balance, Uint128::new(INITIAL_BALANCE + 5_000));
+        })
+        .say_yes_with(
+            &receiver,
+            YesAnswer {
+                id: 2,
+                ..Default::default()
+            },
+            &[coin(5_000, "uom")],
+            |r: Result<AppResponse, anyhow::Error>| assert!(r.is_ok()),
+        )
+        .add_100_block()
+        .finalize(&admin, 2, |r: Result<AppResponse, anyhow::Error>| {
+            assert!(r.is_ok())
+        })
+        .query_proposal(2, |r| {
+            assert_eq!(r.unwrap().status, ProposalStatus::Yes);
+        })
+        .query_balance("uom", &receiver, |balance| {
+            assert_eq!(balance, Uint128::new(INITIAL_BALANCE + 10_000));
+        })
+        .query_balance("uom", &proposer, |balance| {
+            assert_eq!(balance, Uint128::new(INITIAL_BALANCE - 10_200));
+        });
+}
+
+#[test]
+fn test_gift_options() {
+    let mut suite = TestingSuite::default_with_balances(vec![coin(INITIAL_BALANCE, "uom")]);
+
+    let admin = suite.admin();
+    let proposer = suite.senders[1].clone();
+    let receiver = suite.senders[2].clone();
+
+    suite
+        .instantiate_proposal_contract(Some(admin.to_string()))
+        .instantiate_cw721_contract(&[("ring-1", &proposer), ("ring-2", &proposer)])
+        .allow_gift_tokens();
+    let collection = suite.cw721_contract_addr.clone();
+
+    suite
+        .create_proposal_with(
+            &proposer,
+            NewProposal {
+                receiver: receiver.to_string(),
+                gift: vec![],
+                gift_options: vec![vec![coin(1_000, "uom")]],
+                ..Default::default()
+            },
+            &[coin(1_100, "uom")],
+            |r: Result<AppResponse, anyhow::Error>| {
+                assert!(matches!(
+                    r.unwrap_err().downcast::<ContractError>().unwrap(),
+                    ContractError::InvalidGiftOptions { .. }
+                ))
+            },
+        )
+        // an empty option could be picked for nothing
+        .create_proposal_with(
+            &proposer,
+            NewProposal {
+                receiver: receiver.to_string(),
+                gift: vec![coin(500, "uom")],
+                gift_options: vec![vec![coin(1_000, "uom")], vec![]],
+                ..Default::default()
+            },
+            &[coin(1_600, "uom")],
+            |r: Result<AppResponse, anyhow::Error>| {
+                assert!(matches!(
+                    r.unwrap_err().downcast::<ContractError>().unwrap(),
+                    ContractError::InvalidGiftOptions { .. }
+                ))
+            },
+        )
+        // 1_000uom or a ring along with 100uom, on top of 500uom
+        .create_proposal_with(
+            &proposer,
+            NewProposal {
+                receiver: receiver.to_string(),
+                gift: vec![coin(500, "uom")],
+                gift_options: vec![vec![coin(1_000, "uom")], vec![coin(100, "uom")]],
+                ..Default::default()
+            },
+            &[coin(1_700, "uom")],
+            |r: Result<AppResponse, anyhow::Error>| assert!(r.is_ok()),
+        )
+        .send_nft(
+            &proposer,
+            "ring-2",
+            ReceiveMsg::UpdateProposal {
+                id: 0,
+                title: None,
+                speech: None,
+                gift_option: Some(2),
+            },
+            |r: Result<AppResponse, anyhow::Error>| {
+                assert!(matches!(
+                    r.unwrap_err().downcast::<ContractError>().unwrap(),
+                    ContractError::InvalidGiftOptions { .. }
+                ))
+            },
+        )
+        .send_nft(
+            &proposer,
+            "ring-1",
+            ReceiveMsg::UpdateProposal {
+                id: 0,
+                title: None,
+                speech: None,
+                gift_option: Some(1),
+            },
+            |r: Result<AppResponse, anyhow::Error>| assert!(r.is_ok()),
+        )
+        .query_proposal(0, |r| {
+            let proposal = r.unwrap();
+            assert_eq!(
+                proposal.gift,
+                vec![
+                    Gift::Native(coin(1_600, "uom")),
+                    Gift::Cw721 {
+                        contract: collection.clone(),
+                        token_id: "ring-1".to_string(),
+                    },
+                ]
+            );
+            assert_eq!(
+                proposal.gift_options[1],
+                vec![
+                    Gift::Native(coin(100, "uom")),
+                    Gift::Cw721 {
+                        contract: collection.clone(),
+                        token_id: "ring-1".to_string(),
+                    },
+                ]
+            );
+        })
+        .say_yes(
+            &receiver,
+            0,
+            None,
+            |r: Result<AppResponse, anyhow::Error>| {
+                assert!(matches!(
+                    r.unwrap_err().downcast::<ContractError>().unwrap(),
+                    ContractError::InvalidGiftOptions { .. }
+                ))
+            },
+        )
+        .say_yes_with(
+            &receiver,
+            YesAnswer {
+                id: 0,
+                gift_option: Some(2),
+                ..Default::default()
+            },
+            &[],
+            |r: Result<AppResponse, anyhow::Error>| {
+                assert!(matches!(
+                    r.unwrap_err().downcast::<ContractError>().unwrap(),
+                    ContractError::InvalidGiftOptions { .. }
+                ))
+            },
+        )
+        .say_yes_with(
+            &receiver,
+            YesAnswer {
+                id: 0,
+                gift_option: Some(1),
+                ..Default::default()
+            },
+            &[],
+            |r: Result<AppResponse, anyhow::Error>| {
+                let response = r.unwrap();
+                let attribute = |key: &str| {
+                    response
+                        .events
+                        .iter()
+                        .flat_map(|event| event.attributes.iter())
+                        .find(|attribute| attribute.key == key)
+                        .map(|attribute| attribute.value.clone())
+                };
+                assert_eq!(attribute("gift_option"), Some("1".to_string()));
+                assert_eq!(
+                    attribute("gift_options_refunded"),
+                    Some("1000uom".to_string())
+                );
+            },
+        )
+        .query_proposal(0, |r| {
+            let proposal = r.unwrap();
+            assert_eq!(proposal.gift_option, Some(1));
+            assert_eq!(
+                proposal.gift,
+                vec![
+                    Gift::Native(coin(600, "uom")),
+                    Gift::Cw721 {
+                        contract: collection.clone(),
+                        token_id: "ring-1".to_string(),
+                    },
+                ]
+            );
+        })
+        .query_nft_owner(&collection, "ring-1", |owner| assert_eq!(owner, receiver))
+        .query_balance("uom", &receiver, |balance| {
+            assert_eq!(balance, Uint128::new(INITIAL_BALANCE + 600));
+        })
+        .query_balance("uom", &proposer, |balance| {
+            assert_eq!(balance, Uint128::new(INITIAL_BALANCE - 700));
+        })
+        // every option is refunded if the proposal isn't accepted
+        .create_proposal_with(
+            &proposer,
+            NewProposal {
+                receiver: receiver.to_string(),
+                gift: vec![],
+                gift_options: vec![vec![coin(1_000, "uom")], vec![coin(2_000, "uom")]],
+                ..Default::default()
+            },
+            &[coin(3_100, "uom")],
+            |r: Result<AppResponse, anyhow::Error>| assert!(r.is_ok()),
+        )
+        .say_no(
+            &receiver,
+            1,
+            None,
+            |r: Result<AppResponse, anyhow::Error>| assert!(r.is_ok()),
+        )
+        .query_balance("uom", &proposer, |balance| {
+            assert_eq!(balance, Uint128::new(INITIAL_BALANCE - 700));
+        });
+}
+
+#[test]
+fn test_proposal_fee_in_several_denoms() {
+    let mut suite = TestingSuite::default_with_balances(vec![
+        coin(INITIAL_BALANCE, "uom"),
+        coin(INITIAL_BALANCE, "uusdc"),
+    ]);
+
+    let admin = suite.admin();
+    let proposer = suite.senders[1].clone();
+    let receiver = suite.senders[2].clone();
+
+    suite.instantiate_proposal_contract_with_msg(InstantiateMsg {
+        successful_proposal_fees: vec![coin(100, "uom"), coin(50, "uusdc")],
+        ..TestingSuite::default_instantiate_msg(Some(admin.to_string()))
+    });
+
+    for funds in [
+        vec![coin(1_000, "uom")],
+        vec![coin(1_000, "uom"), coin(60, "uusdc")],
+        vec![coin(1_100, "uom"), coin(50, "uusdc")],
+    ] {
+        suite.create_proposal(
+            &proposer,
+            None,
+            None,
+            receiver.to_string(),
+            vec![coin(1_000, "uom")],
+            &funds,
+            |r: Result<AppResponse, anyhow::Error>| {
+                assert!(matches!(
+                    r.unwrap_err().downcast::<ContractError>().unwrap(),
+                    ContractError::InvalidProposalCreationFee { .. }
+                ))
+            },
+        );
+    }
+
+    // any one of the fees is accepted, the gift is kept separate
+    for (gift, funds) in [
+        (
+            coin(1_000, "uom"),
+            vec![coin(1_000, "uom"), coin(50, "uusdc")],
+        ),
+        (coin(1_000, "uom"), vec![coin(1_100, "uom")]),
+        (coin(500, "uusdc"), vec![coin(550, "uusdc")]),
+        (
+            coin(500, "uusdc"),
+            vec![coin(100, "uom"), coin(500, "uusdc")],
+        ),
+    ] {
+        suite.create_proposal(
+            &proposer,
+            None,
+            None,
+            receiver.to_string(),
+            vec![gift],
+            &funds,
+            |r: Result<AppResponse, anyhow::Error>| assert!(r.is_ok()),
+        );
+    }
+
+    suite
+        .query_proposal(0, |r| {
+            let proposal = r.unwrap();
+            assert_eq!(proposal.fee, coin(50, "uusdc"));
+            assert_eq!(proposal.gift, vec![Gift::Native(coin(1_000, "uom"))]);
+        })
+        .query_proposal(1, |r| assert_eq!(r.unwrap().fee, coin(100, "uom")))
+        .query_proposal(2, |r| assert_eq!(r.unwrap().fee, coin(50, "uusdc")))
+        .query_proposal(3, |r| {
+            let proposal = r.unwrap();
+            assert_eq!(proposal.fee, coin(100, "uom"));
+            assert_eq!(proposal.gift, vec![Gift::Native(coin(500, "uusdc"))]);
+        })
+        .say_yes(
+            &receiver,
+            0,
+            None,
+            |r: Result<AppResponse, anyhow::Error>| assert!(r.is_ok()),
+        )
+        .withdraw_fees(
+            &admin,
+            vec![],
+            None,
+            |r: Result<AppResponse, anyhow::Error>| assert!(r.is_ok()),
+        )
+        .query_balance("uusdc", &admin, |balance| {
+            assert_eq!(balance, Uint128::new(INITIAL_BALANCE + 50));
+        })
+        .query_balance("uom", &receiver, |balance| {
+            assert_eq!(balance, Uint128::new(INITIAL_BALANCE + 1_000));
+        })
+        .cancel_proposal(&proposer, 1, |r: Result<AppResponse, anyhow::Error>| {
+            assert!(r.is_ok())
+        })
+        .query_balance("uom", &proposer, |balance| {
+            assert_eq!(balance, Uint128::new(INITIAL_BALANCE - 1_000 - 100));
+        });
+}
+
+#[test]
+fn test_gift_fee() {
+    let mut suite = TestingSuite::default_with_balances(vec![
+        coin(INITIAL_BALANCE, "uom"),
+        coin(INITIAL_BALANCE, "uusdc"),
+    ]);
+
+    let admin = suite.admin();
+    let proposer = suite.senders[1].clone();
+    let receiver = suite.senders[2].clone();
+    // 1.5% of each denom of the gift
+    let gift_fee_bps = Decimal::from_ratio(150u128, 1u128);
+
+    suite
+        .instantiate_proposal_contract_with_msg(InstantiateMsg {
+            gift_fee_bps: Some(gift_fee_bps),
+            ..TestingSuite::default_instantiate_msg(Some(admin.to_string()))
+        })
+        .update_config(
+            &admin,
+            ConfigUpdate {
+                gift_fee_bps: Some(Decimal::from_ratio(10_001u128, 1u128)),
+                ..Default::default()
+            },
+            |r: Result<AppResponse, anyhow::Error>| {
+                assert!(matches!(
+                    r.unwrap_err().downcast::<ContractError>().unwrap(),
+                    ContractError::InvalidGiftFee { .. }
+                ))
+            },
+        )
+        .query_gift_fee(vec![coin(10_000, "uom"), coin(99, "uusdc")], |r| {
+            let preview = r.unwrap();
+            assert_eq!(preview.fee, vec![coin(150, "uom"), coin(1, "uusdc")]);
+            assert_eq!(
+                preview.remaining,
+                vec![coin(9_850, "uom"), coin(98, "uusdc")]
+            );
+        })
+        .create_proposal(
+            &proposer,
+            None,
+            None,
+            receiver.to_string(),
+            vec![coin(10_000, "uom")],
+            &[coin(10_100, "uom")],
+            |r: Result<AppResponse, anyhow::Error>| assert!(r.is_ok()),
+        )
+        .say_yes(
+            &receiver,
+            0,
+            None,
+            |r: Result<AppResponse, anyhow::Error>| {
+                let response = r.unwrap();
+                assert!(response.events.iter().any(|event| event
+                    .attributes
+                    .iter()
+                    .any(|attribute| attribute.key == "gift_fee" && attribute.value == "150uom")));
+            },
+        )
+        .query_proposal(0, |r| {
+            let proposal = r.unwrap();
+            assert_eq!(proposal.gift_fee, vec![Gift::Native(coin(150, "uom"))]);
+            assert_eq!(proposal.gift, vec![Gift::Native(coin(9_850, "uom"))]);
+        })
+        .query_balance("uom", &receiver, |balance| {
+            assert_eq!(balance, Uint128::new(INITIAL_BALANCE + 9_850));
+        })
+        .query_treasury(None, None, |r| {
+            let fees = r.unwrap().fees;
+            assert_eq!(fees.len(), 1);
+            assert_eq!(fees[0].accrued, Uint128::new(100 + 150));
+        })
+        // a declined gift isn't charged
+        .create_proposal(
+            &proposer,
+            None,
+            None,
+            receiver.to_string(),
+            vec![coin(10_000, "uom")],
+            &[coin(10_100, "uom")],
+            |r: Result<AppResponse, anyhow::Error>| assert!(r.is_ok()),
+        )
+        .say_yes_with(
+            &receiver,
+            YesAnswer {
+                id: 1,
+                gift_disposition: Some(GiftDisposition::Decline),
+                ..Default::default()
+            },
+            &[],
+            |r: Result<AppResponse, anyhow::Error>| assert!(r.is_ok()),
+        )
+        .query_balance("uom", &proposer, |balance| {
+            assert_eq!(balance, Uint128::new(INITIAL_BALANCE - 10_100 - 100));
+        })
+        .update_config(
+            &admin,
+            ConfigUpdate {
+                gift_fee_bps: Some(Decimal::zero()),
+                ..Default::default()
+            },
+            |r: Result<AppResponse, anyhow::Error>| assert!(r.is_ok()),
+        )
+        .query_config(|r| assert_eq!(r.unwrap().gift_fee_bps, None))
+        .query_gift_fee(vec![coin(10_000, "uom")], |r| {
+            let preview = r.unwrap();
+            assert!(preview.fee.is_empty());
+            assert_eq!(preview.remaining, vec![coin(10_000, "uom")]);
+        });
+}
+
+#[test]
+fn test_fee_treasury() {
+    let mut suite = TestingSuite::default_with_balances(vec![coin(INITIAL_BALANCE, "uom")]);
+
+    let admin = suite.admin();
+    let proposer = suite.senders[1].clone();
+    let receiver = suite.senders[2].clone();
+    let friend = suite.senders[3].clone();
+
+    suite
+        .instantiate_proposal_contract_with_msg(InstantiateMsg {
+            gift_fee_bps: Some(Decimal::from_ratio(100u128, 1u128)),
+            ..TestingSuite::default_instantiate_msg(Some(admin.to_string()))
+        })
+        .instantiate_cw20_contract(INITIAL_BALANCE)
+        .allow_gift_tokens();
+    let token = suite.cw20_contract_addr.clone();
+    let contract = suite.proposal_manager_contract_addr.clone();
+
+    suite
+        .create_proposal(
+            &proposer,
+            None,
+            None,
+            receiver.to_string(),
+            vec![coin(1_000, "uom")],
+            &[coin(1_100, "uom")],
+            |r: Result<AppResponse, anyhow::Error>| assert!(r.is_ok()),
+        )
+        .send_cw20(
+            &proposer,
+            500,
+            ReceiveMsg::UpdateProposal {
+                id: 0,
+                title: None,
+                speech: None,
+                gift_option: None,
+            },
+            |r: Result<AppResponse, anyhow::Error>| assert!(r.is_ok()),
+        )
+        .say_yes(
+            &receiver,
+            0,
+            None,
+            |r: Result<AppResponse, anyhow::Error>| {
+                let response = r.unwrap();
+                assert!(response.events.iter().any(|event| event
+                    .attributes
+                    .iter()
+                    .any(|attribute| attribute.key == "fees_credited_to_treasury"
+                        && attribute.value == "true")));
+            },
+        )
+        // the fees stay in the contract instead of going to the owner
+        .query_balance("uom", &admin, |balance| {
+            assert_eq!(balance, Uint128::new(INITIAL_BALANCE));
+        })
+        .query_balance("uom", &contract, |balance| {
+            assert_eq!(balance, Uint128::new(100 + 10));
+        })
+        .query_treasury(None, None, |r| {
+            let fees = r.unwrap().fees;
+            assert_eq!(fees.len(), 2);
+            assert_eq!(fees[0].denom, token.to_string());
+            assert_eq!(fees[0].accrued, Uint128::new(5));
+            assert_eq!(fees[1].denom, "uom");
+            assert_eq!(fees[1].accrued, Uint128::new(110));
+            assert_eq!(fees[1].withdrawn, Uint128::zero());
+            assert_eq!(fees[1].available, Uint128::new(110));
+        })
+        .withdraw_fees(
+            &friend,
+            vec![],
+            None,
+            |r: Result<AppResponse, anyhow::Error>| {
+                assert!(matches!(
+                    r.unwrap_err().downcast::<ContractError>().unwrap(),
+                    ContractError::OwnershipError { .. }
+                ))
+            },
+        )
+        .withdraw_fees(
+            &admin,
+            vec!["uusdc".to_string()],
+            None,
+            |r: Result<AppResponse, anyhow::Error>| {
+                assert_eq!(
+                    r.unwrap_err().downcast::<ContractError>().unwrap(),
+                    ContractError::NoFeesToWithdraw
+                )
+            },
+        )
+        .withdraw_fees(
+            &admin,
+            vec![token.to_string()],
+            Some(friend.to_string()),
+            |r: Result<AppResponse, anyhow::Error>| assert!(r.is_ok()),
+        )
+        .query_cw20_balance(&friend, |balance| {
+            assert_eq!(balance, Uint128::new(INITIAL_BALANCE + 5));
+        })
+        .query_treasury(None, None, |r| {
+            let fees = r.unwrap().fees;
+            assert_eq!(fees[0].withdrawn, Uint128::new(5));
+            assert_eq!(fees[0].available, Uint128::zero());
+            assert_eq!(fees[1].available, Uint128::new(110));
+        })
+        .withdraw_fees(
+            &admin,
+            vec![],
+            None,
+            |r: Result<AppResponse, anyhow::Error>| assert!(r.is_ok()),
+        )
+        .query_balance("uom", &admin, |balance| {
+            assert_eq!(balance, Uint128::new(INITIAL_BALANCE + 110));
+        })
+        .withdraw_fees(
+            &admin,
+            vec![],
+            None,
+            |r: Result<AppResponse, anyhow::Error>| {
+                assert_eq!(
+                    r.unwrap_err().downcast::<ContractError>().unwrap(),
+                    ContractError::NoFeesToWithdraw
+                )
+            },
+        )
+        .query_treasury(Some(token.to_string()), None, |r| {
+            let fees = r.unwrap().fees;
+            assert_eq!(fees.len(), 1);
+            assert_eq!(fees[0].denom, "uom");
+            assert_eq!(fees[0].accrued, Uint128::new(110));
+            assert_eq!(fees[0].withdrawn, Uint128::new(110));
+        });
+
+    // without an owner the proposal fee goes back to the proposer, the gift fee taken from the
+    // receiver's gift is still credited
+    suite
+        .update_ownership(
+            &admin,
+            cw_ownable::Action::RenounceOwnership,
+            |r: Result<AppResponse, anyhow::Error>| assert!(r.is_ok()),
+        )
+        .create_proposal(
+            &proposer,
+            None,
+            None,
+            receiver.to_string(),
+            vec![coin(1_000, "uom")],
+            &[coin(1_100, "uom")],
+            |r: Result<AppResponse, anyhow::Error>| assert!(r.is_ok()),
+        )
+        .say_yes(
+            &receiver,
+            1,
+            None,
+            |r: Result<AppResponse, anyhow::Error>| assert!(r.is_ok()),
+        )
+        .query_balance("uom", &proposer, |balance| {
+            assert_eq!(balance, Uint128::new(INITIAL_BALANCE - 1_100 - 1_000));
+        })
+        .query_treasury(None, None, |r| {
+            let fees = r.unwrap().fees;
+            assert_eq!(fees[1].denom, "uom");
+            assert_eq!(fees[1].accrued, Uint128::new(110 + 10));
+        });
+}
+
+#[test]
+fn test_fee_split() {
+    let mut suite = TestingSuite::default_with_balances(vec![coin(INITIAL_BALANCE, "uom")]);
+
+    let admin = suite.admin();
+    let proposer = suite.senders[1].clone();
+    let receiver = suite.senders[2].clone();
+    let community_pool = suite.senders[3].clone();
+    let fee_shares = vec![
+        FeeShare {
+            recipient: FeeRecipient::Address {
+                address: admin.clone(),
+            },
+            weight_bps: 5_000,
+        },
+        FeeShare {
+            recipient: FeeRecipient::Address {
+                address: community_pool.clone(),
+            },
+            weight_bps: 3_333,
+        },
+        FeeShare {
+            recipient: FeeRecipient::Burn,
+            weight_bps: 1_667,
+        },
+    ];
+
+    suite
+        .instantiate_proposal_contract_with_msg(InstantiateMsg {
+            gift_fee_bps: Some(Decimal::from_ratio(100u128, 1u128)),
+            ..TestingSuite::default_instantiate_msg(Some(admin.to_string()))
+        })
+        .update_config(
+            &admin,
+            ConfigUpdate {
+                fee_shares: Some(vec![fee_shares[0].clone(), fee_shares[1].clone()]),
+                ..Default::default()
+            },
+            |r: Result<AppResponse, anyhow::Error>| {
+                assert!(matches!(
+                    r.unwrap_err().downcast::<ContractError>().unwrap(),
+                    ContractError::InvalidFeeShares { .. }
+                ))
+            },
+        )
+        .update_config(
+            &admin,
+            ConfigUpdate {
+                fee_shares: Some(vec![
+                    fee_shares[0].clone(),
+                    FeeShare {
+                        recipient: FeeRecipient::Burn,
+                        weight_bps: 0,
+                    },
+                ]),
+                ..Default::default()
+            },
+            |r: Result<AppResponse, anyhow::Error>| {
+                assert!(matches!(
+                    r.unwrap_err().downcast::<ContractError>().unwrap(),
+                    ContractError::InvalidFeeShares { .. }
+                ))
+            },
+        )
+        .update_config(
+            &admin,
+            ConfigUpdate {
+                fee_shares: Some(fee_shares.clone()),
+                ..Default::default()
+            },
+            |r: Result<AppResponse, anyhow::Error>| assert!(r.is_ok()),
+        )
+        .query_config(|r| assert_eq!(r.unwrap().fee_shares, fee_shares))
+        .create_proposal(
+            &proposer,
+            None,
+            None,
+            receiver.to_string(),
+            vec![coin(1_000, "uom")],
+            &[coin(1_100, "uom")],
+            |r: Result<AppResponse, anyhow::Error>| assert!(r.is_ok()),
+        );
+
+    let supply = INITIAL_BALANCE * suite.senders.len() as u128;
+    let contract = suite.proposal_manager_contract_addr.clone();
+    // the 100uom fee and the 10uom gift fee are split, the rounding remainder going to the
+    // first share
+    let expected_shares = format!("{admin}:56uom;{community_pool}:36uom;burn:18uom");
+    suite
+        .say_yes(
+            &receiver,
+            0,
+            None,
+            |r: Result<AppResponse, anyhow::Error>| {
+                let response = r.unwrap();
+                assert!(response.events.iter().any(|event| event
+                    .attributes
+                    .iter()
+                    .any(|attribute| attribute.key == "fee_shares"
+                        && attribute.value == expected_shares)));
+            },
+        )
+        .query_balance("uom", &admin, |balance| {
+            assert_eq!(balance, Uint128::new(INITIAL_BALANCE + 56));
+        })
+        .query_balance("uom", &community_pool, |balance| {
+            assert_eq!(balance, Uint128::new(INITIAL_BALANCE + 36));
+        })
+        .query_supply("uom", |amount| {
+            assert_eq!(amount, Uint128::new(supply - 18));
+        })
+        .query_balance("uom", &contract, |balance| {
+            assert_eq!(balance, Uint128::zero());
+        })
+        .query_treasury(None, None, |r| assert!(r.unwrap().fees.is_empty()))
+        // without shares, the fees are credited to the treasury again
+        .update_config(
+            &admin,
+            ConfigUpdate {
+                fee_shares: Some(vec![]),
+                ..Default::default()
+            },
+            |r: Result<AppResponse, anyhow::Error>| assert!(r.is_ok()),
+        )
+        .create_proposal(
+            &proposer,
+            None,
+            None,
+            receiver.to_string(),
+            vec![coin(1_000, "uom")],
+            &[coin(1_100, "uom")],
+            |r: Result<AppResponse, anyhow::Error>| assert!(r.is_ok()),
+        )
+        .say_yes(
+            &receiver,
+            1,
+            None,
+            |r: Result<AppResponse, anyhow::Error>| assert!(r.is_ok()),
+        )
+        .query_treasury(None, None, |r| {
+            let fees = r.unwrap().fees;
+            assert_eq!(fees.len(), 1);
+            assert_eq!(fees[0].available, Uint128::new(110));
+        });
+}
+
+#[test]
+fn test_gift_hooks_only_accept_allowed_tokens() {
+    let mut suite = TestingSuite::default_with_balances(vec![coin(INITIAL_BALANCE, "uom")]);
+
+    let admin = suite.admin();
+    let proposer = suite.senders[1].clone();
+    let receiver = suite.senders[2].clone();
+    let attacker = suite.senders[3].clone();
+
+    suite
+        .instantiate_proposal_contract(Some(admin.to_string()))
+        .instantiate_cw20_contract(INITIAL_BALANCE)
+        .instantiate_cw721_contract(&[("ring-1", &proposer)])
+        .create_proposal(
+            &proposer,
+            Some("Be mine".to_string()),
+            None,
+            receiver.to_string(),
+            vec![coin(500, "uom")],
+            &[coin(600, "uom")],
+            |r: Result<AppResponse, anyhow::Error>| assert!(r.is_ok()),
+        );
+
+    let update = ReceiveMsg::UpdateProposal {
+        id: 0,
+        title: Some("pwned".to_string()),
+        speech: None,
+        gift_option: None,
+    };
+    let spoofed_cw20 = ExecuteMsg::Receive(Cw20ReceiveMsg {
+        sender: proposer.to_string(),
+        amount: Uint128::new(1_000),
+        msg: to_json_binary(&update).unwrap(),
+    });
+    let spoofed_nft = ExecuteMsg::ReceiveNft(Cw721ReceiveMsg {
+        sender: proposer.to_string(),
+        token_id: "fake".to_string(),
+        msg: to_json_binary(&update).unwrap(),
+    });
+
+    suite
+        // tokens have to be allowed by the owner
+        .send_cw20(
+            &proposer,
+            500,
+            update.clone(),
+            |r: Result<AppResponse, anyhow::Error>| {
+                assert!(matches!(
+                    r.unwrap_err().downcast::<ContractError>().unwrap(),
+                    ContractError::GiftTokenNotAllowed { .. }
+                ))
+            },
+        )
+        .send_nft(
+            &proposer,
+            "ring-1",
+            update.clone(),
+            |r: Result<AppResponse, anyhow::Error>| {
+                assert!(matches!(
+                    r.unwrap_err().downcast::<ContractError>().unwrap(),
+                    ContractError::GiftTokenNotAllowed { .. }
+                ))
+            },
+        )
+        .allow_gift_tokens()
+        // an account calling the hooks directly can't impersonate the proposer
+        .execute(
+            &attacker,
+            spoofed_cw20,
+            &[],
+            |r: Result<AppResponse, anyhow::Error>| {
+                assert!(matches!(
+                    r.unwrap_err().downcast::<ContractError>().unwrap(),
+                    ContractError::GiftTokenNotAllowed { .. }
+                ))
+            },
+        )
+        .execute(
+            &attacker,
+            spoofed_nft,
+            &[],
+            |r: Result<AppResponse, anyhow::Error>| {
+                assert!(matches!(
+                    r.unwrap_err().downcast::<ContractError>().unwrap(),
+                    ContractError::GiftTokenNotAllowed { .. }
+                ))
+            },
+        )
+        .query_proposal(0, |r| {
+            let proposal = r.unwrap();
+            assert_eq!(proposal.title, Some("Be mine".to_string()));
+            assert_eq!(proposal.gift, vec![Gift::Native(coin(500, "uom"))]);
+        })
+        .cancel_proposal(&proposer, 0, |r: Result<AppResponse, anyhow::Error>| {
+            assert!(r.is_ok())
+        })
+        .query_balance("uom", &proposer, |balance| {
+            assert_eq!(balance, Uint128::new(INITIAL_BALANCE));
+        });
+}
+
+#[test]
+fn test_gift_fee_on_staked_gift() {
+    let mut suite = TestingSuite::default_with_balances(vec![coin(INITIAL_BALANCE, "uom")]);
+
+    let admin = suite.admin();
+    let proposer = suite.senders[1].clone();
+    let receiver = suite.senders[2].clone();
+    let validator = "validator";
+    let unbonding_period = 60;
+
+    suite
+        .add_validator(validator, unbonding_period)
+        .instantiate_proposal_contract_with_msg(InstantiateMsg {
+            staking: Some(StakingConfig {
+                validator: validator.to_string(),
+                unbonding_period,
+            }),
+            gift_fee_bps: Some(Decimal::from_ratio(1_000u128, 1u128)),
+            fee_shares: vec![FeeShare {
+                recipient: FeeRecipient::Burn,
+                weight_bps: 10_000,
+            }],
+            ..TestingSuite::default_instantiate_msg(Some(admin.to_string()))
+        })
+        .create_proposal_with(
+            &proposer,
+            NewProposal {
+                receiver: receiver.to_string(),
+                gift: vec![coin(100_000, "uom")],
+                stake_gift: true,
+                ..Default::default()
+            },
+            &[coin(100_100, "uom")],
+            |r: Result<AppResponse, anyhow::Error>| assert!(r.is_ok()),
+        )
+        // another proposal's escrow sits in the contract
+        .create_proposal(
+            &proposer,
+            None,
+            None,
+            receiver.to_string(),
+            vec![coin(50_000, "uom")],
+            &[coin(50_100, "uom")],
+            |r: Result<AppResponse, anyhow::Error>| assert!(r.is_ok()),
+        );
+
+    let contract = suite.proposal_manager_contract_addr.clone();
+    let supply = std::cell::Cell::new(Uint128::zero());
+    suite
+        .query_supply("uom", |amount| supply.set(amount))
+        // only the proposal fee is burned right away, the gift fee waits for the unbonding
+        .say_yes(
+            &receiver,
+            0,
+            None,
+            |r: Result<AppResponse, anyhow::Error>| {
+                let response = r.unwrap();
+                assert!(response.events.iter().any(|event| event
+                    .attributes
+                    .iter()
+                    .any(|attribute| attribute.key == "fee_shares"
+                        && attribute.value == "burn:100uom")));
+            },
+        )
+        .query_supply("uom", |amount| {
+            assert_eq!(amount, supply.get() - Uint128::new(100));
+        })
+        .query_balance("uom", &contract, |balance| {
+            assert_eq!(balance, Uint128::new(50_100));
+        })
+        .query_proposal(0, |r| {
+            let unbonding = r.unwrap().staking.unwrap().unbonding.unwrap();
+            assert_eq!(unbonding.fee, Some(coin(10_000, "uom")));
+            assert_eq!(unbonding.payouts.len(), 1);
+            assert_eq!(unbonding.payouts[0].recipient, receiver);
+            assert_eq!(unbonding.payouts[0].amount, coin(90_000, "uom"));
+        })
+        .add_seconds(unbonding_period)
+        .claim_unbonded(&admin, 0, |r: Result<AppResponse, anyhow::Error>| {
+            let response = r.unwrap();
+            assert!(response
+                .events
+                .iter()
+                .any(|event| event
+                    .attributes
+                    .iter()
+                    .any(|attribute| attribute.key == "fee_shares"
+                        && attribute.value == "burn:10000uom")));
+        })
+        .query_balance("uom", &receiver, |balance| {
+            assert_eq!(balance, Uint128::new(INITIAL_BALANCE + 90_000));
+        })
+        .query_supply("uom", |amount| {
+            assert_eq!(amount, supply.get() - Uint128::new(10_100));
+        })
+        // the other proposal's escrow is untouched
+        .query_balance("uom", &contract, |balance| {
+            assert_eq!(balance, Uint128::new(50_100));
+        })
+        .cancel_proposal(&proposer, 1, |r: Result<AppResponse, anyhow::Error>| {
+            assert!(r.is_ok())
+        })
+        .query_balance("uom", &contract, |balance| {
+            assert_eq!(balance, Uint128::zero());
+        });
+}
+
+#[test]
+fn test_migration_from_unsupported_versions_is_rejected() {
+    let mut suite = TestingSuite::default_with_balances(vec![coin(INITIAL_BALANCE, "uom")]);
+
+    let admin = suite.admin();
+
+    suite
+        .instantiate_proposal_contract(Some(admin.to_string()))
+        // the deployed version is already the current one
+        .migrate_contract(&admin, |r: Result<AppResponse, anyhow::Error>| {
+            assert!(matches!(
+                r.unwrap_err().downcast::<ContractError>().unwrap(),
+                ContractError::MigrateInvalidVersion { .. }
+            ))
+        })
+        .set_stored_contract_version("0.0.9")
+        .migrate_contract(&admin, |r: Result<AppResponse, anyhow::Error>| {
+            assert_eq!(
+                r.unwrap_err().downcast::<ContractError>().unwrap(),
+                ContractError::MigrationUnsupported {
+                    version: "0.0.9".to_string(),
+                    min_version: "0.1.0".to_string(),
+                }
+            )
+        });
+}
+
+#[test]
+fn test_migration_from_v0_1() {
+    let mut suite = TestingSuite::default_with_balances(vec![coin(INITIAL_BALANCE, "uom")]);
+
+    let admin = suite.admin();
+    let alice = suite.senders[1].clone();
+    let bob = suite.senders[2].clone();
+    let carol = suite.senders[3].clone();
+    let legacy_proposal =
+        |id: u64, proposer: &Addr, receiver: &Addr, status: ProposalStatus| LegacyProposal {
+            id,
+            proposer: proposer.clone(),
+            receiver: receiver.clone(),
+            gift: vec![coin(1_000, "uom")],
+            fee: coin(100, "uom"),
+            title: Some(format!("Proposal {id}")),
+            speech: None,
+            reply: None,
+            status,
+            created_at: 1,
+            replied_at: None,
+        };
+
+    suite
+        .instantiate_proposal_contract(Some(admin.to_string()))
+        .store_legacy_state(
+            LegacyConfig {
+                successful_proposal_fee: coin(100, "uom"),
+            },
+            vec![
+                legacy_proposal(0, &alice, &bob, ProposalStatus::Pending),
+                legacy_proposal(1, &alice, &carol, ProposalStatus::Yes),
+                legacy_proposal(2, &bob, &alice, ProposalStatus::No),
+            ],
+        );
+    let contract = suite.proposal_manager_contract_addr.clone();
+
+    suite
+        .migrate_contract(&admin, |r: Result<AppResponse, anyhow::Error>| {
+            assert!(r.is_ok())
+        })
+        .query_config(|r| {
+            let config = r.unwrap();
+            assert_eq!(config.successful_proposal_fees, vec![coin(100, "uom")]);
+            assert_eq!(config.gift_policy, GiftPolicy::default());
+            assert!(config.fee_shares.is_empty());
+        })
+        .query_proposal(0, |r| {
+            let proposal = r.unwrap();
+            assert_eq!(proposal.status, ProposalStatus::Pending);
+            assert_eq!(proposal.expires_at, Expiration::Never {});
+            assert_eq!(proposal.gift, vec![Gift::Native(coin(1_000, "uom"))]);
+            assert_eq!(proposal.fee, coin(100, "uom"));
+            assert_eq!(proposal.title, Some("Proposal 0".to_string()));
+        })
+        .query_status(|r| {
+            let status = r.unwrap();
+            assert_eq!(status.total_proposals, 3);
+            assert_eq!(status.total_proposals_pending, 1);
+            assert_eq!(status.total_proposals_yes, 1);
+            assert_eq!(status.total_proposals_no, 1);
+            assert_eq!(status.total_proposals_expired, 0);
+            assert_eq!(status.total_proposals_dissolved, 0);
+        })
+        // the proposer index is keyed by the proposer again
+        .query_proposals(
+            None,
+            Some(ProposalBy::Proposer(bob.to_string())),
+            Some(ProposalStatus::No),
+            None,
+            |r| {
+                let proposals = r.unwrap().proposals;
+                assert_eq!(proposals.len(), 1);
+                assert_eq!(proposals[0].id, 2);
+            },
+        )
+        .query_proposals(
+            None,
+            Some(ProposalBy::Proposer(alice.to_string())),
+            Some(ProposalStatus::No),
+            None,
+            |r| assert!(r.unwrap().proposals.is_empty()),
+        )
+        .query_proposals(
+            None,
+            Some(ProposalBy::Receiver(carol.to_string())),
+            Some(ProposalStatus::Yes),
+            None,
+            |r| {
+                let proposals = r.unwrap().proposals;
+                assert_eq!(proposals.len(), 1);
+                assert_eq!(proposals[0].id, 1);
+            },
+        )
+        // the escrowed funds of the pending proposal are paid out as usual
+        .say_yes(&bob, 0, None, |r: Result<AppResponse, anyhow::Error>| {
+            assert!(r.is_ok())
+        })
+        .query_balance("uom", &bob, |balance| {
+            assert_eq!(balance, Uint128::new(INITIAL_BALANCE + 1_000));
+        })
+        .query_treasury(None, None, |r| {
+            let fees = r.unwrap().fees;
+            assert_eq!(fees.len(), 1);
+            assert_eq!(fees[0].accrued, Uint128::new(100));
+        })
+        .query_balance("uom", &contract, |balance| {
+            assert_eq!(balance, Uint128::new(100));
+        })
+        // new proposals keep counting from the migrated ones
+        .create_proposal(
+            &alice,
+            None,
+            None,
+            bob.to_string(),
+            vec![],
+            &[coin(100, "uom")],
+            |r: Result<AppResponse, anyhow::Error>| assert!(r.is_ok()),
+        )
+        .query_proposal(3, |r| assert_eq!(r.unwrap().proposer, alice))
+        .migrate_contract(&admin, |r: Result<AppResponse, anyhow::Error>| {
+            assert!(matches!(
+                r.unwrap_err().downcast::<ContractError>().unwrap(),
+                ContractError::MigrateInvalidVersion { .. }
+            ))
+        });
+}
+
+#[test]
+fn test_migration_from_v0_1_in_batches() {
+    let mut suite = TestingSuite::default_with_balances(vec![coin(INITIAL_BALANCE, "uom")]);
+
+    let admin = suite.admin();
+    let alice = suite.senders[1].clone();
+    let bob = suite.senders[2].clone();
+    let proposals = (0..103)
+        .map(|id| LegacyProposal {
+            id,
+            proposer: alice.clone(),
+            receiver: bob.clone(),
+            gift: vec![coin(1_000, "uom")],
+            fee: coin(100, "uom"),
+            title: None,
+            speech: None,
+            reply: None,
+            status: ProposalStatus::Pending,
+            created_at: 1,
+            replied_at: None,
+        })
+        .collect();
+
+    suite
+        .instantiate_proposal_contract(Some(admin.to_string()))
+        .store_legacy_state(
+            LegacyConfig {
+                successful_proposal_fee: coin(100, "uom"),
+            },
+            proposals,
+        )
+        // the migration only migrates the first batch of proposals
+        .migrate_contract(&admin, |r: Result<AppResponse, anyhow::Error>| {
+            assert!(r.is_ok())
+        })
+        .query_proposal(99, |r| {
+            assert_eq!(r.unwrap().expires_at, Expiration::Never {})
+        })
+        .query_proposal(100, |r| assert!(r.is_err()))
+        .create_proposal(
+            &alice,
+            None,
+            None,
+            bob.to_string(),
+            vec![],
+            &[coin(100, "uom")],
+            |r: Result<AppResponse, anyhow::Error>| {
+                assert_eq!(
+                    r.unwrap_err().downcast::<ContractError>().unwrap(),
+                    ContractError::MigrationInProgress
+                )
+            },
+        )
+        .say_yes(&bob, 0, None, |r: Result<AppResponse, anyhow::Error>| {
+            assert_eq!(
+                r.unwrap_err().downcast::<ContractError>().unwrap(),
+                ContractError::MigrationInProgress
+            )
+        })
+        // anyone can migrate the rest
+        .migrate_proposals(&bob, Some(2), |r: Result<AppResponse, anyhow::Error>| {
+            let response = r.unwrap();
+            let attribute = |key: &str| {
+                response
+                    .events
+                    .iter()
+                    .flat_map(|event| event.attributes.iter())
+                    .find(|attribute| attribute.key == key)
+                    .map(|attribute| attribute.value.clone())
+            };
+            assert_eq!(attribute("migrated"), Some("2".to_string()));
+            assert_eq!(attribute("done"), Some("false".to_string()));
+        })
+        .query_proposal(101, |r| assert!(r.is_ok()))
+        .query_proposal(102, |r| assert!(r.is_err()))
+        .migrate_proposals(&bob, None, |r: Result<AppResponse, anyhow::Error>| {
+            let response = r.unwrap();
+            let attribute = |key: &str| {
+                response
+                    .events
+                    .iter()
+                    .flat_map(|event| event.attributes.iter())
+                    .find(|attribute| attribute.key == key)
+                    .map(|attribute| attribute.value.clone())
+            };
+            assert_eq!(attribute("migrated"), Some("1".to_string()));
+            assert_eq!(attribute("done"), Some("true".to_string()));
+        })
+        .migrate_proposals(&bob, None, |r: Result<AppResponse, anyhow::Error>| {
+            assert_eq!(
+                r.unwrap_err().downcast::<ContractError>().unwrap(),
+                ContractError::NothingToMigrate
+            )
+        })
+        .query_status(|r| {
+            let status = r.unwrap();
+            assert_eq!(status.total_proposals, 103);
+            assert_eq!(status.total_proposals_pending, 103);
+        })
+        .query_proposals(
+            Some(100),
+            Some(ProposalBy::Proposer(alice.to_string())),
+            Some(ProposalStatus::Pending),
+            None,
+            |r| assert_eq!(r.unwrap().proposals.len(), 100),
+        )
+        // the gifts of the proposals migrated last are escrowed too
+        .say_yes(&bob, 102, None, |r: Result<AppResponse, anyhow::Error>| {
+            assert!(r.is_ok())
+        })
+        .query_balance("uom", &bob, |balance| {
+            assert_eq!(balance, Uint128::new(INITIAL_BALANCE + 1_000));
+        });
 }